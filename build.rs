@@ -0,0 +1,7 @@
+fn main() {
+    // `self-update` downloads the release binary built for this target.
+    println!(
+        "cargo:rustc-env=TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
+}
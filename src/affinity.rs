@@ -0,0 +1,106 @@
+//! Which faces' moves force reorients, across a batch of algorithms.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+use crate::{display_move, threads, Alg, RktOptimizer};
+
+/// Solve a file of algorithms and report, for each face, how often a move of
+/// that face needs a reorient right before it.
+#[derive(clap::Args, Debug, Clone)]
+pub struct AffinityArgs {
+    /// File with one rotationless algorithm per line. Blank lines and lines
+    /// starting with `#` are ignored.
+    input: PathBuf,
+}
+
+/// Counts for the moves of one face.
+#[derive(Debug, Default, Clone)]
+struct FaceCounts {
+    moves: usize,
+    /// Moves with a reorient right before them.
+    reoriented: usize,
+    /// ETM of those reorients.
+    etm: usize,
+}
+
+pub fn run(args: &AffinityArgs, optimizer: &RktOptimizer, max_depth: usize) -> Result<(), String> {
+    let contents = std::fs::read_to_string(&args.input)
+        .map_err(|e| format!("error reading {}: {e}", args.input.display()))?;
+    let algs = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            Alg::parse_with(line, optimizer.cost_model())
+                .map_err(|e| format!("line {}: {e}", i + 1))
+        })
+        .collect::<Result<Vec<Alg>, String>>()?;
+    if algs.is_empty() {
+        return Err(format!("no algorithms in {}", args.input.display()));
+    }
+
+    let done = AtomicUsize::new(0);
+    let results = threads::map_on(optimizer.thread_count(), &algs, |alg| {
+        let (_, solutions) = optimizer.search(alg, max_depth, false, None, &mut None);
+        let done = done.fetch_add(1, SeqCst) + 1;
+        eprint!("\rSolved {}/{} ...", done, algs.len());
+        solutions
+            .into_iter()
+            .min_by_key(|s| alg.cost(optimizer.cost_model(), s))
+    });
+    eprintln!();
+
+    let mut faces: Vec<(String, FaceCounts)> = vec![];
+    let mut unsolved = 0;
+    for (alg, best) in algs.iter().zip(results) {
+        let Some(best) = best else {
+            unsolved += 1;
+            continue;
+        };
+        for (i, &mv) in alg.moves.iter().enumerate() {
+            let face: String = display_move(mv)
+                .chars()
+                .take_while(|c| c.is_alphabetic())
+                .collect();
+            let counts = match faces.iter().position(|(f, _)| *f == face) {
+                Some(j) => &mut faces[j].1,
+                None => {
+                    faces.push((face, FaceCounts::default()));
+                    &mut faces.last_mut().unwrap().1
+                }
+            };
+            counts.moves += 1;
+            if let Some(&r) = i.checked_sub(1).and_then(|gap| best.get(gap)) {
+                if !r.is_none() {
+                    counts.reoriented += 1;
+                    counts.etm += optimizer.cost_model().cost(r);
+                }
+            }
+        }
+    }
+
+    let solved = algs.len() - unsolved;
+    println!("Solved {solved} of {} algorithms.", algs.len());
+    if unsolved > 0 {
+        println!("{unsolved} of them need more than {max_depth} reorients.");
+    }
+    if solved == 0 {
+        return Ok(());
+    }
+    println!();
+
+    let rate = |c: &FaceCounts| c.reoriented as f64 / c.moves as f64;
+    faces.sort_by(|(_, a), (_, b)| rate(b).total_cmp(&rate(a)));
+    println!("Face  Moves  Reoriented  Rate  Reorient ETM");
+    for (face, counts) in &faces {
+        println!(
+            "{face:<4}  {:>5}  {:>10}  {:>3.0}%  {:>12}",
+            counts.moves,
+            counts.reoriented,
+            rate(counts) * 100.0,
+            counts.etm,
+        );
+    }
+    Ok(())
+}
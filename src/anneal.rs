@@ -0,0 +1,193 @@
+//! Simulated annealing over reorient placements, for algorithms too long to
+//! search exhaustively.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cubesim::Face;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::interrupt;
+use crate::perm::{self, Permutation, Stickers, STICKERS};
+use crate::sticker_cube::{self, Colors};
+use crate::table::{self, PruningTable};
+use crate::{solved_states, Alg, CostModel, Reorient, RktOptimizer, Solution};
+
+/// Energy added for each sticker that doesn't match the nearest solved
+/// orientation.
+const STICKER_PENALTY: f64 = 0.25;
+/// Energy added for each move that the pruning table says the end state is
+/// from solved.
+const BOUND_PENALTY: f64 = 2.0;
+/// Energy added for each chunk boundary or forbidden move that is violated.
+const CONSTRAINT_PENALTY: f64 = 8.0;
+const START_TEMPERATURE: f64 = 4.0;
+const END_TEMPERATURE: f64 = 0.05;
+/// Time to anneal for when `--strategy` picks annealing without `--anneal`.
+pub const DEFAULT_BUDGET: Duration = Duration::from_secs(30);
+
+/// Result of annealing.
+#[derive(Debug, Clone)]
+pub struct Annealed {
+    /// Cheapest placement found that solves the cube and meets every
+    /// constraint.
+    pub best: Option<Solution>,
+    /// Fewest stickers left unsolved by any placement that meets every
+    /// constraint.
+    pub fewest_unsolved: usize,
+    /// Number of placements tried.
+    pub steps: usize,
+}
+
+/// Searches for a cheap placement of reorients in `alg` under `optimizer`'s
+/// settings for `budget`, or until Ctrl-C is pressed, by randomly changing
+/// one gap at a time and accepting worse placements with a probability that
+/// falls over time.
+pub fn optimize(optimizer: &RktOptimizer, alg: &Alg, budget: Duration) -> Annealed {
+    let gaps = alg.moves.len().saturating_sub(1);
+    let mut ret = Annealed {
+        best: None,
+        fewest_unsolved: usize::MAX,
+        steps: 0,
+    };
+    if gaps == 0 {
+        ret.best = Some(vec![]);
+        return ret;
+    }
+
+    // Only gaps inside the window may change.
+    let open_gaps: Vec<usize> = (0..gaps).filter(|&gap| alg.allows_reorient(gap)).collect();
+    let evaluator = Evaluator::new(optimizer, alg);
+    let mut rng = StdRng::from_entropy();
+    let mut current: Solution = vec![Reorient::None; gaps];
+    let mut current_energy = evaluator.energy(&current, &mut ret);
+
+    let start = Instant::now();
+    let _search = interrupt::Search::start();
+    while start.elapsed() < budget && !open_gaps.is_empty() && !interrupt::requested() {
+        let progress = start.elapsed().as_secs_f64() / budget.as_secs_f64();
+        let temperature = START_TEMPERATURE * (END_TEMPERATURE / START_TEMPERATURE).powf(progress);
+
+        let gap = open_gaps[rng.gen_range(0..open_gaps.len())];
+        let old = current[gap];
+        // Removing reorients is as likely as changing them.
+        current[gap] = match rng.gen_bool(0.5) {
+            true => Reorient::None,
+            false => Reorient::ALL[rng.gen_range(1..Reorient::ALL.len())],
+        };
+        let energy = evaluator.energy(&current, &mut ret);
+        let delta = energy - current_energy;
+        if delta <= 0.0 || rng.gen::<f64>() < (-delta / temperature).exp() {
+            current_energy = energy;
+        } else {
+            current[gap] = old;
+        }
+    }
+    ret
+}
+
+struct Evaluator<'a> {
+    alg: &'a Alg,
+    start: Stickers,
+    /// Permutation made by each move of the algorithm.
+    moves: Vec<Permutation>,
+    /// Permutation made by each reorient.
+    reorients: Vec<Permutation>,
+    colors: Colors,
+    boundaries: Vec<Option<Reorient>>,
+    cost_model: CostModel,
+    table: Arc<PruningTable>,
+    /// Every state that counts as solved.
+    targets: Vec<Vec<Face>>,
+}
+impl<'a> Evaluator<'a> {
+    fn new(optimizer: &RktOptimizer, alg: &'a Alg) -> Self {
+        let colors = sticker_cube::colors(optimizer.mask());
+        Self {
+            alg,
+            start: Permutation::of_moves(alg.start.equivalent_rkt_moves())
+                .permute(&perm::solved_stickers()),
+            moves: alg
+                .moves
+                .iter()
+                .map(|&mv| Permutation::of_moves(&[mv]))
+                .collect(),
+            reorients: Reorient::ALL
+                .iter()
+                .map(|r| Permutation::of_moves(r.equivalent_rkt_moves()))
+                .collect(),
+            colors,
+            boundaries: alg.boundaries(),
+            cost_model: optimizer.cost_model().clone(),
+            table: optimizer.table(),
+            targets: solved_states(optimizer.up_to_auf())
+                .iter()
+                .map(|cube| cube.facelets(&colors).collect())
+                .collect(),
+        }
+    }
+
+    /// Returns the energy of a placement, which is lower for better
+    /// placements, and records it in `result` if it is the best so far.
+    fn energy(&self, solution: &Solution, result: &mut Annealed) -> f64 {
+        result.steps += 1;
+
+        let mut state = self.start;
+        let mut orientation = self.alg.start;
+        let mut violations = 0;
+        for (i, &mv) in self.alg.moves.iter().enumerate() {
+            if self.boundaries[i].is_some_and(|b| b != orientation) {
+                violations += 1;
+            }
+            if self.cost_model.forbidden_moves()[orientation as usize].contains(&mv) {
+                violations += 1;
+            }
+            state = self.moves[i].permute(&state);
+            if let Some(&r) = solution.get(i) {
+                if !r.is_none() {
+                    state = self.reorients[r as usize].permute(&state);
+                }
+                orientation = orientation.then(r);
+            }
+        }
+        if self.boundaries[self.alg.moves.len()].is_some_and(|b| b != orientation) {
+            violations += 1;
+        }
+
+        // Same criterion as `dfs`.
+        let state: Vec<Face> = state[..STICKERS]
+            .iter()
+            .map(|&sticker| self.colors[sticker as usize])
+            .collect();
+        let packed = table::pack_faces(state.iter().copied());
+        let bound = self.table.lower_bound_packed(packed);
+        let solved = self.table.within_one_move_packed(packed);
+        let unsolved = self
+            .targets
+            .iter()
+            .map(|target| target.iter().zip(&state).filter(|(a, b)| a != b).count())
+            .min()
+            .unwrap_or(0);
+
+        let cost = self.alg.cost(&self.cost_model, solution);
+        if violations == 0 {
+            if solved {
+                let best_cost = result
+                    .best
+                    .as_ref()
+                    .map(|best| self.alg.cost(&self.cost_model, best));
+                if best_cost.is_none_or(|best_cost| cost < best_cost) {
+                    result.best = Some(solution.clone());
+                }
+            }
+            result.fewest_unsolved = result.fewest_unsolved.min(unsolved);
+        }
+
+        let unsolved_penalty = match solved {
+            true => 0.0,
+            false => bound as f64 * BOUND_PENALTY + unsolved as f64 * STICKER_PENALTY,
+        };
+        cost as f64 + unsolved_penalty + violations as f64 * CONSTRAINT_PENALTY
+    }
+}
@@ -0,0 +1,209 @@
+//! Beam search over reorient placements, for algorithms too long to search
+//! exhaustively.
+
+use std::collections::HashMap;
+
+use cubesim::Face;
+
+use crate::interrupt;
+use crate::optimizer::step_tables;
+use crate::perm::STICKERS;
+use crate::sticker_cube::{self, Colors, PackTable, StickerCube};
+use crate::{threads, Alg, Reorient, RktOptimizer, SearchResult, Solution};
+
+/// Number of placements kept at each gap when `--beam-width` isn't given.
+pub const DEFAULT_WIDTH: usize = 1000;
+/// Score added for each ETM that a placement adds. Scores are in quarters of
+/// an ETM, like the energies in [`crate::anneal`].
+const COST_WEIGHT: usize = 4;
+/// Score added for each move that the pruning table says a placement is from
+/// solved if no more reorients are added.
+const BOUND_WEIGHT: usize = 8;
+/// Score added for each sticker that doesn't match its center if no more
+/// reorients are added, which tells placements apart when the pruning table
+/// is too shallow to.
+const STICKER_WEIGHT: usize = 1;
+
+/// Placement of reorients in the gaps up to some point in the algorithm.
+#[derive(Debug, Clone)]
+struct Partial {
+    /// State before the next move.
+    state: StickerCube,
+    orientation: Reorient,
+    /// Reorient in each gap so far.
+    solution: Solution,
+    reorients: usize,
+    /// ETM added so far.
+    cost: usize,
+    /// ETM added so far, plus how far from solved the rest of the algorithm
+    /// would leave the cube without more reorients, weighed as above.
+    score: usize,
+}
+
+/// Searches for solutions with at most `max_depth` reorients under
+/// `optimizer`'s settings by placing reorients one gap at a time, keeping
+/// only the `width` placements with the best scores at each gap. A placement scores better the less ETM it adds
+/// and the closer the rest of the algorithm would leave the cube to solved.
+///
+/// This takes time proportional to the length of the algorithm instead of
+/// exponential in it, but may miss the cheapest solutions, or all of them.
+/// The solutions returned are the ones found with the fewest reorients. The
+/// search stops early if Ctrl-C is pressed.
+pub fn search(optimizer: &RktOptimizer, alg: &Alg, width: usize, max_depth: usize) -> SearchResult {
+    let mut ret = SearchResult {
+        reorient_count: 0,
+        solutions: vec![],
+        timed_out: false,
+        interrupted: false,
+        nodes: vec![0],
+        optimal: false,
+    };
+    let moves = &alg.moves;
+    if moves.len() <= 1 {
+        ret.solutions = vec![vec![]];
+        return ret;
+    }
+    let gaps = moves.len() - 1;
+    let table = optimizer.table();
+    let colors = sticker_cube::colors(optimizer.mask());
+    let pack_table = PackTable::new(&colors);
+    let bound =
+        |state: &StickerCube| table.lower_bound_packed(state.pack_with(&pack_table)) as usize;
+    let solved = |state: &StickerCube| table.within_one_move_packed(state.pack_with(&pack_table));
+    let cost_model = optimizer.cost_model();
+    let forbidden = cost_model.forbidden_moves();
+    let boundaries = alg.boundaries();
+    let (steps, suffixes) = step_tables(moves);
+    // Returns whether the rest of the algorithm, from move `i` on, can be
+    // made without reorients in `orientation`.
+    let rest_allowed = |i: usize, orientation: Reorient| {
+        boundaries[i..]
+            .iter()
+            .all(|b| b.is_none_or(|required| required == orientation))
+            && moves[i..]
+                .iter()
+                .all(|mv| !forbidden[orientation as usize].contains(mv))
+    };
+
+    let mut found: Vec<Solution> = vec![];
+    let start = *alg.start.sticker_cube();
+    if solved(&start.apply(&suffixes[0])) && rest_allowed(0, alg.start) {
+        found.push(vec![Reorient::None; gaps]);
+    }
+    let mut beam = vec![Partial {
+        state: start,
+        orientation: alg.start,
+        solution: vec![],
+        reorients: 0,
+        cost: 0,
+        score: 0,
+    }];
+    let _search = interrupt::Search::start();
+    for gap in 0..gaps {
+        if interrupt::requested() {
+            ret.interrupted = true;
+            break;
+        }
+        // Each placement is extended on its own thread, with the placements
+        // that solve the cube once extended, and the number of extensions.
+        let extended = threads::map_on(optimizer.thread_count(), &beam, |partial| {
+            let mut children = vec![];
+            let mut complete = vec![];
+            let mut tried = 0;
+            let orientation = partial.orientation;
+            if boundaries[gap].is_some_and(|required| required != orientation)
+                || forbidden[orientation as usize].contains(&moves[gap])
+            {
+                return (children, complete, tried);
+            }
+            let moved = partial.state.apply(&steps[gap]);
+            for &r in Reorient::ALL {
+                if !r.is_none() && (!alg.allows_reorient(gap) || partial.reorients >= max_depth) {
+                    continue;
+                }
+                tried += 1;
+                let state = moved.apply(r.move_table());
+                // Same pruning as the exhaustive search.
+                if bound(&state) > moves.len() - gap {
+                    continue;
+                }
+                let orientation = orientation.then(r);
+                let mut solution = partial.solution.clone();
+                solution.push(r);
+                let cost = alg.cost(cost_model, &solution);
+                let end = state.apply(&suffixes[gap + 1]);
+                let end_bound = bound(&end);
+                if !r.is_none() && solved(&end) && rest_allowed(gap + 1, orientation) {
+                    let mut solved = solution.clone();
+                    solved.resize(gaps, Reorient::None);
+                    complete.push(solved);
+                }
+                children.push(Partial {
+                    state,
+                    orientation,
+                    solution,
+                    reorients: partial.reorients + !r.is_none() as usize,
+                    cost,
+                    score: cost * COST_WEIGHT
+                        + end_bound * BOUND_WEIGHT
+                        + misplaced(&end, &colors) * STICKER_WEIGHT,
+                });
+            }
+            (children, complete, tried)
+        });
+
+        // Placements that reach the same state in the same orientation with
+        // the same number of reorients have the same solutions from here on,
+        // so only the cheapest is kept.
+        let mut next: HashMap<([u64; 3], Reorient, usize), Partial> = HashMap::new();
+        for (children, complete, tried) in extended {
+            found.extend(complete);
+            ret.nodes[0] += tried;
+            for child in children {
+                let key = (
+                    child.state.pack_with(&pack_table),
+                    child.orientation,
+                    child.reorients,
+                );
+                match next.get(&key) {
+                    Some(kept) if kept.cost <= child.cost => {}
+                    _ => {
+                        next.insert(key, child);
+                    }
+                }
+            }
+        }
+        beam = next.into_values().collect();
+        // Ties go to the placement that is earliest in gap order, so that
+        // the result doesn't depend on the order of the hash map.
+        beam.sort_by(|a, b| {
+            (a.score, a.reorients, &a.solution).cmp(&(b.score, b.reorients, &b.solution))
+        });
+        beam.truncate(width);
+    }
+
+    if let Some(fewest) = found.iter().map(count_reorients).min() {
+        found.retain(|s| count_reorients(s) == fewest);
+        found.sort();
+        found.dedup();
+        ret.reorient_count = fewest;
+        ret.solutions = found;
+    }
+    ret
+}
+
+/// Returns the number of stickers that don't match the center of their face,
+/// not counting blank ones.
+fn misplaced(state: &StickerCube, colors: &Colors) -> usize {
+    let mut facelets = [Face::X; STICKERS];
+    for (facelet, face) in facelets.iter_mut().zip(state.facelets(colors)) {
+        *facelet = face;
+    }
+    (0..STICKERS)
+        .filter(|&i| facelets[i] != Face::X && facelets[i] != facelets[i / 9 * 9 + 4])
+        .count()
+}
+
+fn count_reorients(solution: &Solution) -> usize {
+    solution.iter().filter(|r| !r.is_none()).count()
+}
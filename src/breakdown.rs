@@ -0,0 +1,39 @@
+//! Describing the composition of an input algorithm.
+
+use cubesim::{Move, MoveVariant};
+
+use crate::display_move;
+
+/// Prints the length of an algorithm in several metrics and how often each
+/// face is turned.
+pub fn print(moves: &[Move]) {
+    let doubles = moves
+        .iter()
+        .filter(|mv| mv.get_variant() == MoveVariant::Double)
+        .count();
+    let quarters = moves.len() - doubles;
+    println!(
+        "Input: {} HTM, {} QTM ({quarters} quarter turns, {doubles} double turns)",
+        moves.len(),
+        quarters + 2 * doubles,
+    );
+
+    // Faces in order of first appearance, so ties read like the algorithm.
+    let mut faces: Vec<(String, usize)> = vec![];
+    for &mv in moves {
+        let face: String = display_move(mv)
+            .chars()
+            .take_while(|c| c.is_alphabetic())
+            .collect();
+        match faces.iter_mut().find(|(f, _)| *f == face) {
+            Some((_, count)) => *count += 1,
+            None => faces.push((face, 1)),
+        }
+    }
+    faces.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    let faces: Vec<String> = faces
+        .into_iter()
+        .map(|(face, count)| format!("{face} {count}"))
+        .collect();
+    println!("Faces: {}", faces.join(", "));
+}
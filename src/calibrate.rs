@@ -0,0 +1,196 @@
+//! Timing the executor doing each reorient, to derive reorient costs from how
+//! fast they actually are instead of from the ETM model.
+//!
+//! The executor first does a fixed sequence of moves, which sets the time of
+//! one move, and then each reorient. Each reorient costs its time in moves,
+//! rounded, and at least 1 ETM.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::cost::CostModel;
+use crate::{meta, Reorient};
+
+/// Moves the executor does to set the time of one move.
+const BASELINE: &str = "R U R' U' R' F R2 U' R' U' R U R' F'";
+/// First line of the block of settings that calibration writes.
+const HEADER: &str = "# Calibrated on";
+
+/// Time each reorient and write the resulting costs and times to the config.
+#[derive(clap::Args, Debug, Clone)]
+pub struct CalibrateArgs {
+    /// Number of times to do each reorient. The median time is used.
+    #[clap(short = 'n', long, default_value = "3")]
+    reps: usize,
+
+    /// Type in each time in milliseconds, such as from a stopwatch, instead
+    /// of pressing Enter before and after.
+    #[clap(long)]
+    manual: bool,
+
+    /// Reorients to time, separated by spaces. Defaults to all of them.
+    #[clap(long, value_name = "REORIENTS")]
+    only: Option<String>,
+
+    /// File to write the settings to. Defaults to the config file. Settings
+    /// there for the same reorients are replaced.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Print the settings instead of writing them.
+    #[clap(long)]
+    dry_run: bool,
+}
+
+pub fn run(
+    args: &CalibrateArgs,
+    cost_model: &CostModel,
+    config: Option<PathBuf>,
+) -> Result<(), String> {
+    if args.reps == 0 {
+        return Err("Invalid reps: must be at least 1".to_string());
+    }
+    let reorients = match &args.only {
+        Some(only) => only
+            .split_whitespace()
+            .map(|s| cost_model.parse_reorient(s))
+            .filter(|r| r.as_ref().map_or(true, |r| !r.is_none()))
+            .collect::<Result<Vec<Reorient>, String>>()
+            .map_err(|e| format!("Invalid reorient: {e}"))?,
+        None => Reorient::ALL[1..].to_vec(),
+    };
+    let path = args.output.clone().or(config);
+    if path.is_none() && !args.dry_run {
+        return Err("no config file to write to; use `--output`".to_string());
+    }
+
+    match args.manual {
+        true => println!("Type how long each one takes, in milliseconds."),
+        false => {
+            println!("Press Enter, do each one, then press Enter again as soon as it is done.")
+        }
+    }
+    println!("Type `s` to skip a reorient, or `q` to stop without writing anything.");
+    println!();
+    let baseline_moves = BASELINE.split_whitespace().count();
+    let Some(baseline) = measure(&format!("the moves {BASELINE}"), args.reps, args.manual)? else {
+        return Err("the moves can't be skipped, since costs are relative to them".to_string());
+    };
+    let move_ms = baseline / baseline_moves as f64;
+    let mut lines = vec![
+        format!("{HEADER} {} from {} times each", meta::today(), args.reps),
+        format!("tps {:.2}", 1000.0 / move_ms),
+    ];
+    for &r in &reorients {
+        let Some(ms) = measure(r.xyz_name(), args.reps, args.manual)? else {
+            continue;
+        };
+        let cost = (ms / move_ms).round().max(1.0) as usize;
+        println!("{} takes {ms:.0} ms, so it costs {cost} ETM.", r.xyz_name());
+        lines.push(format!("cost {} = {cost}", r.xyz_name()));
+        lines.push(format!("time {} = {ms:.0}", r.xyz_name()));
+    }
+    println!();
+
+    let (Some(path), false) = (path, args.dry_run) else {
+        lines.iter().for_each(|line| println!("{line}"));
+        return Ok(());
+    };
+    let calibrated = (lines.len() - 2) / 2;
+    write(&path, cost_model, &lines)?;
+    println!(
+        "Wrote costs for {calibrated} reorients and {:.2} TPS to {}.",
+        1000.0 / move_ms,
+        path.display(),
+    );
+    Ok(())
+}
+
+/// Times the executor doing `what` `reps` times and returns the median time
+/// in milliseconds, or `None` if it is skipped.
+fn measure(what: &str, reps: usize, manual: bool) -> Result<Option<f64>, String> {
+    let mut times = vec![];
+    while times.len() < reps {
+        let attempt = format!("{what} ({}/{reps})", times.len() + 1);
+        if manual {
+            let line = prompt(&format!("{attempt}: "))?;
+            match line.parse::<f64>() {
+                Ok(ms) if ms > 0.0 => times.push(ms),
+                _ if line == "s" => return Ok(None),
+                _ => println!("Invalid time {line:?}"),
+            }
+            continue;
+        }
+        match prompt(&format!("{attempt}: press Enter to start "))?.as_str() {
+            "" => (),
+            "s" => return Ok(None),
+            _ => continue,
+        }
+        let start = Instant::now();
+        prompt("Press Enter when done ")?;
+        times.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+    times.sort_by(f64::total_cmp);
+    let median = match reps % 2 {
+        1 => times[reps / 2],
+        _ => (times[reps / 2 - 1] + times[reps / 2]) / 2.0,
+    };
+    Ok(Some(median))
+}
+
+/// Prints `message` and returns the line typed in response, trimmed. Returns
+/// an error if it is `q` or the input ends.
+fn prompt(message: &str) -> Result<String, String> {
+    print!("{message}");
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    match std::io::stdin().read_line(&mut line) {
+        Ok(0) => Err("Stopped; nothing was written".to_string()),
+        Err(e) => Err(e.to_string()),
+        Ok(_) if line.trim() == "q" => Err("Stopped; nothing was written".to_string()),
+        Ok(_) => Ok(line.trim().to_string()),
+    }
+}
+
+/// Writes `lines` to the end of the config file at `path`, replacing the
+/// settings that they override and the previous calibration header.
+fn write(path: &Path, cost_model: &CostModel, lines: &[String]) -> Result<(), String> {
+    let contents = match path.exists() {
+        true => std::fs::read_to_string(path)
+            .map_err(|e| format!("error reading {}: {e}", path.display()))?,
+        false => String::new(),
+    };
+    // Settings for a reorient are named by `<setting> <reorient> =`.
+    let key = |line: &str| -> Option<(String, Option<Reorient>)> {
+        let (setting, rest) = line.trim().split_once(char::is_whitespace)?;
+        match setting {
+            "tps" => Some((setting.to_string(), None)),
+            "cost" | "time" => {
+                let (reorient, _) = rest.split_once('=')?;
+                Some((
+                    setting.to_string(),
+                    cost_model.parse_reorient(reorient).ok(),
+                ))
+            }
+            _ => None,
+        }
+    };
+    let replaced: Vec<_> = lines.iter().filter_map(|line| key(line)).collect();
+    let mut out: String = contents
+        .lines()
+        .filter(|line| !line.starts_with(HEADER))
+        .filter(|line| key(line).is_none_or(|key| !replaced.contains(&key)))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+    lines.iter().for_each(|line| out += &format!("{line}\n"));
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("error creating {}: {e}", dir.display()))?;
+    }
+    std::fs::write(path, out).map_err(|e| format!("error writing {}: {e}", path.display()))
+}
@@ -0,0 +1,154 @@
+//! Moves that cancel across the boundary between consecutive algorithms.
+
+use cubesim::{Move, MoveVariant};
+
+use crate::{display_move, is_face_move, Reorient};
+
+/// Moves at the end of one algorithm that combine with moves at the start of
+/// the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cancellation {
+    /// Moves at the end of the previous algorithm that are involved.
+    pub prev: Vec<Move>,
+    /// Moves at the start of the next algorithm that are involved.
+    pub next: Vec<Move>,
+    /// Move that replaces all of them, if they don't cancel completely. This
+    /// is written in the next algorithm's notation.
+    pub merged: Option<Move>,
+}
+impl Cancellation {
+    /// Returns the number of ETM saved.
+    pub fn saved(&self) -> usize {
+        self.prev.len() + self.next.len() - self.merged.is_some() as usize
+    }
+}
+
+/// Returns the moves that cancel between an algorithm ending in orientation
+/// `prev_end` and the next one starting in orientation `next_start`, or
+/// `None` if the physical faces turned at the boundary differ. Wide turns and
+/// rotations don't cancel, and nothing cancels past them.
+pub fn find(
+    prev: &[Move],
+    prev_end: Reorient,
+    next: &[Move],
+    next_start: Reorient,
+) -> Option<Cancellation> {
+    let mut ret = Cancellation {
+        prev: vec![],
+        next: vec![],
+        merged: None,
+    };
+    for (&a, &b) in prev.iter().rev().zip(next) {
+        if !is_face_move(a) || !is_face_move(b) {
+            break;
+        }
+        let physical_a = prev_end.physical_move(a);
+        let physical_b = next_start.physical_move(b);
+        if physical_a.with_variant(MoveVariant::Standard)
+            != physical_b.with_variant(MoveVariant::Standard)
+        {
+            break;
+        }
+        ret.prev.insert(0, a);
+        ret.next.push(b);
+        let quarter_turns = (quarter_turns(physical_a) + quarter_turns(physical_b)) % 4;
+        if quarter_turns != 0 {
+            let variant = match quarter_turns {
+                1 => MoveVariant::Standard,
+                2 => MoveVariant::Double,
+                _ => MoveVariant::Inverse,
+            };
+            ret.merged = Some(b.with_variant(variant));
+            break;
+        }
+    }
+    (!ret.next.is_empty()).then_some(ret)
+}
+
+fn quarter_turns(mv: Move) -> u8 {
+    match mv.get_variant() {
+        MoveVariant::Standard => 1,
+        MoveVariant::Double => 2,
+        MoveVariant::Inverse => 3,
+    }
+}
+
+impl std::fmt::Display for Cancellation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let moves = |moves: &[Move]| moves.iter().map(|&mv| display_move(mv)).collect::<Vec<_>>();
+        let merged = match self.merged {
+            Some(mv) => display_move(mv),
+            None => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "{} | {} -> {merged}",
+            moves(&self.prev).join(" "),
+            moves(&self.next).join(" "),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Alg;
+
+    fn moves(s: &str) -> Vec<Move> {
+        Alg::parse(s).unwrap().moves
+    }
+
+    #[test]
+    fn inverse_moves_cancel_completely() {
+        let cancellation = find(
+            &moves("R U R' U'"),
+            Reorient::None,
+            &moves("U R F"),
+            Reorient::None,
+        )
+        .unwrap();
+        assert_eq!(cancellation.prev, moves("R' U'"));
+        assert_eq!(cancellation.next, moves("U R"));
+        assert_eq!(cancellation.merged, None);
+        assert_eq!(cancellation.saved(), 4);
+    }
+
+    #[test]
+    fn quarter_turns_merge_into_a_double() {
+        let cancellation =
+            find(&moves("R U"), Reorient::None, &moves("U R"), Reorient::None).unwrap();
+        assert_eq!(cancellation.prev, moves("U"));
+        assert_eq!(cancellation.next, moves("U"));
+        assert_eq!(cancellation.merged, moves("U2").first().copied());
+        assert_eq!(cancellation.saved(), 1);
+    }
+
+    #[test]
+    fn different_physical_faces_dont_cancel() {
+        assert_eq!(
+            find(&moves("R U"), Reorient::None, &moves("R U"), Reorient::None),
+            None
+        );
+        // After a y rotation, R turns the physical B face.
+        let oy: Reorient = "Oy".parse().unwrap();
+        assert_eq!(find(&moves("R"), Reorient::None, &moves("R'"), oy), None);
+        assert!(find(&moves("B"), Reorient::None, &moves("R'"), oy).is_some());
+    }
+
+    #[test]
+    fn rotations_and_wide_turns_stop_the_scan() {
+        assert_eq!(
+            find(
+                &moves("R x"),
+                Reorient::None,
+                &moves("x' R'"),
+                Reorient::None
+            ),
+            None
+        );
+        assert_eq!(
+            find(&moves("Rw"), Reorient::None, &moves("Rw'"), Reorient::None),
+            None
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Compact display of many solutions as independent choices per gap.
+
+use std::collections::HashMap;
+
+use cubesim::MoveVariant;
+
+use crate::{display_move, Alg, Reorient, RktOptimizer, Solution};
+
+/// Set of solutions consisting of every combination of the reorients allowed
+/// at each gap.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    /// Reorients allowed at each gap, sorted.
+    pub gaps: Vec<Vec<Reorient>>,
+}
+impl Pattern {
+    /// Returns the number of solutions in the pattern.
+    pub fn len(&self) -> usize {
+        self.gaps.iter().map(Vec::len).product()
+    }
+    /// Returns whether the pattern contains no solutions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Partitions solutions into patterns, so that the solutions are exactly the
+/// combinations of choices that the patterns allow.
+///
+/// Patterns that agree on every gap but one are merged until no more can be,
+/// preserving the order of the solutions so that the first pattern contains
+/// the most preferred solution.
+pub fn patterns(solutions: &[Solution]) -> Vec<Pattern> {
+    let mut patterns: Vec<Pattern> = solutions
+        .iter()
+        .map(|solution| Pattern {
+            gaps: solution.iter().map(|&r| vec![r]).collect(),
+        })
+        .collect();
+    let gap_count = solutions.first().map_or(0, Vec::len);
+    loop {
+        let old_len = patterns.len();
+        for gap in 0..gap_count {
+            let mut merged: Vec<Pattern> = vec![];
+            let mut indices: HashMap<Vec<Vec<Reorient>>, usize> = HashMap::new();
+            for pattern in patterns {
+                let mut key = pattern.gaps.clone();
+                key[gap].clear();
+                match indices.get(&key) {
+                    Some(&i) => {
+                        let choices = &mut merged[i].gaps[gap];
+                        choices.extend(&pattern.gaps[gap]);
+                        choices.sort_unstable();
+                        choices.dedup();
+                    }
+                    None => {
+                        indices.insert(key, merged.len());
+                        merged.push(pattern);
+                    }
+                }
+            }
+            patterns = merged;
+        }
+        if patterns.len() == old_len {
+            return patterns;
+        }
+    }
+}
+
+/// Returns the algorithm with the choices of a pattern in braces, such as
+/// `R U {Ox|Oy2} R' U'`, in `optimizer`'s notation. `-` stands for no
+/// reorient.
+pub fn display(optimizer: &RktOptimizer, alg: &Alg, pattern: &Pattern) -> String {
+    let name = |choices: &[Reorient]| match choices {
+        [r] if r.is_none() => String::new(),
+        [r] => optimizer.reorient_name(*r),
+        _ => {
+            let names: Vec<String> = choices
+                .iter()
+                .map(|r| match r.is_none() {
+                    true => "-".to_string(),
+                    false => optimizer.reorient_name(*r),
+                })
+                .collect();
+            format!("{{{}}}", names.join("|"))
+        }
+    };
+    let mut words = vec![];
+    if !alg.start.is_none() {
+        words.push(name(&[alg.start]));
+    }
+    let anchors = |words: &mut Vec<String>, i: usize| {
+        for anchor in alg.anchors.iter().filter(|a| a.position == i) {
+            words.push(format!("@{}", anchor.name));
+        }
+    };
+    let mut i = 0;
+    while i < alg.moves.len() {
+        anchors(&mut words, i);
+        if i > 0 {
+            words.push(name(&pattern.gaps[i - 1]));
+        }
+        if alg.split_gaps.contains(&i) && pattern.gaps[i] == [Reorient::None] {
+            words.push(display_move(alg.moves[i].with_variant(MoveVariant::Double)));
+            i += 2;
+        } else {
+            words.push(display_move(alg.moves[i]));
+            i += 1;
+        }
+    }
+    anchors(&mut words, alg.moves.len());
+    words.retain(|w| !w.is_empty());
+    words.join(" ")
+}
@@ -0,0 +1,69 @@
+//! Side-by-side comparison of two solutions of the same algorithm, for
+//! deciding between candidates that are about as cheap.
+
+use crate::{display_move, Alg, Reorient, RktOptimizer, Solution};
+
+/// Prints two solutions of `alg` gap by gap, with the orientation that each
+/// one is in after every gap, marking the gaps where they place different
+/// reorients with `*`. `labels` name the solutions in the header. Reorients
+/// are named in `optimizer`'s notation and cost under its cost model.
+pub fn print(optimizer: &RktOptimizer, alg: &Alg, labels: [&str; 2], solutions: [&Solution; 2]) {
+    let [a, b] = solutions;
+    let differing = (0..a.len()).filter(|&gap| a[gap] != b[gap]).count();
+    println!(
+        "{} adds {} ETM and {} adds {} ETM; they differ at {differing} gaps.",
+        labels[0],
+        alg.cost(optimizer.cost_model(), a),
+        labels[1],
+        alg.cost(optimizer.cost_model(), b),
+    );
+
+    let name = |r: Reorient| match r.is_none() {
+        true => String::new(),
+        false => optimizer.reorient_name(r),
+    };
+    let orientation = |r: Reorient| match r.is_none() {
+        true => "-".to_string(),
+        false => optimizer.reorient_name(r),
+    };
+    let mut rows = vec![[
+        String::new(),
+        "Move".to_string(),
+        labels[0].to_string(),
+        labels[1].to_string(),
+        format!("{} orientation", labels[0]),
+        format!("{} orientation", labels[1]),
+    ]];
+    let mut orientations = [alg.start; 2];
+    for (i, &mv) in alg.moves.iter().enumerate() {
+        // There is no gap after the last move.
+        let (ra, rb) = match i < a.len() {
+            true => (a[i], b[i]),
+            false => (Reorient::None, Reorient::None),
+        };
+        orientations = [orientations[0].then(ra), orientations[1].then(rb)];
+        rows.push([
+            if ra != rb { "*" } else { "" }.to_string(),
+            display_move(mv),
+            name(ra),
+            name(rb),
+            orientation(orientations[0]),
+            orientation(orientations[1]),
+        ]);
+    }
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in &rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
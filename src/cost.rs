@@ -0,0 +1,563 @@
+//! Cost of each reorient, in ETM, and the rest of the executor's profile.
+
+use std::str::FromStr;
+
+use cubesim::{Cube, FaceletCube, Move, MoveVariant};
+
+use crate::{meta, Reorient};
+
+/// Moves per second assumed by the time objective if the config doesn't say.
+const DEFAULT_TPS: f64 = 4.0;
+/// Cells of the 4D puzzle, by their names in sticker notation.
+const CELLS: &str = "IORLUDFB";
+
+/// Cost in ETM of each reorient, along with how the executor performs
+/// reorients and how long they take.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostModel {
+    costs: [usize; 24],
+    macros: Vec<Macro>,
+    expansions: Vec<Option<Vec<Move>>>,
+    forbidden: Vec<Vec<Move>>,
+    /// Time to execute one move, in milliseconds.
+    move_ms: f64,
+    /// Time to execute each reorient, in milliseconds, if known.
+    reorient_ms: [Option<f64>; 24],
+    /// Names to use for cells in sticker notation instead of the standard
+    /// ones.
+    cell_names: Vec<(char, String)>,
+}
+impl Default for CostModel {
+    fn default() -> Self {
+        let mut costs = [0; 24];
+        for &r in Reorient::ALL {
+            costs[r as usize] = standard_cost(r);
+        }
+        Self {
+            costs,
+            macros: vec![],
+            expansions: vec![None; 24],
+            forbidden: vec![vec![]; 24],
+            move_ms: 1000.0 / DEFAULT_TPS,
+            reorient_ms: [None; 24],
+            cell_names: vec![],
+        }
+    }
+}
+impl CostModel {
+    /// Makes each of `cheap` cost 1 ETM.
+    pub fn make_cheap(&mut self, cheap: &[Reorient]) {
+        for &r in cheap {
+            if !r.is_none() {
+                self.set_cost(r, 1);
+            }
+        }
+    }
+
+    /// Returns a hash of everything in the cost model as 16 hex digits, which
+    /// is the same for equal cost models on every run.
+    pub fn fingerprint(&self) -> String {
+        format!("{:016x}", meta::hash(&format!("{self:?}")))
+    }
+
+    /// Returns the cost of a reorient, which is the cost of the cheapest
+    /// macro that performs it if that is cheaper than the reorient itself.
+    pub fn cost(&self, r: Reorient) -> usize {
+        match self.macro_for(r) {
+            Some(m) => m.cost,
+            None => self.costs[r as usize],
+        }
+    }
+    /// Sets the cost of a reorient.
+    pub fn set_cost(&mut self, r: Reorient, cost: usize) {
+        self.costs[r as usize] = cost;
+    }
+
+    /// Returns the cheapest macro that performs a reorient, if it is cheaper
+    /// than the reorient itself.
+    pub fn macro_for(&self, r: Reorient) -> Option<&Macro> {
+        self.macros
+            .iter()
+            .filter(|m| m.net() == r && m.cost < self.costs[r as usize])
+            .min_by_key(|m| m.cost)
+    }
+    /// Returns the macro with a given name.
+    pub fn macro_named(&self, name: &str) -> Option<&Macro> {
+        self.macros.iter().find(|m| m.name == name)
+    }
+    /// Adds a macro. A macro has the same effect as the single reorient it
+    /// composes to, so the search uses it wherever it is the cheaper way to
+    /// perform that reorient.
+    pub fn add_macro(&mut self, m: Macro) {
+        self.macros.push(m);
+    }
+
+    /// Returns the whole-cube rotations that the executor uses to perform a
+    /// reorient.
+    pub fn expansion(&self, r: Reorient) -> Vec<Move> {
+        match &self.expansions[r as usize] {
+            Some(moves) => moves.clone(),
+            None => r.equivalent_rkt_moves().to_vec(),
+        }
+    }
+    /// Sets the whole-cube rotations that the executor uses to perform a
+    /// reorient, which must have the same effect as the reorient.
+    pub fn set_expansion(&mut self, r: Reorient, moves: Vec<Move>) -> Result<(), String> {
+        let solved = FaceletCube::new(3);
+        if solved.apply_moves(&moves).state()
+            != solved.apply_moves(r.equivalent_rkt_moves()).state()
+        {
+            return Err(format!("expansion does not perform {}", r.xyz_name()));
+        }
+        self.expansions[r as usize] = Some(moves);
+        Ok(())
+    }
+
+    /// Returns the time to execute one move, in milliseconds.
+    pub fn move_time(&self) -> f64 {
+        self.move_ms
+    }
+    /// Returns the time to execute a reorient, in milliseconds. Reorients
+    /// without their own timing take as long as their cost in moves.
+    pub fn reorient_time(&self, r: Reorient) -> f64 {
+        self.reorient_ms[r as usize].unwrap_or(self.cost(r) as f64 * self.move_ms)
+    }
+
+    /// Returns the moves that the executor can't perform in each orientation,
+    /// indexed by orientation.
+    pub fn forbidden_moves(&self) -> &[Vec<Move>] {
+        &self.forbidden
+    }
+    /// Forbids a move in an orientation.
+    pub fn forbid(&mut self, orientation: Reorient, mv: Move) {
+        self.forbidden[orientation as usize].push(mv);
+    }
+
+    /// Names a cell differently in sticker and hypercubing notation, such as
+    /// `K` for the `I` cell.
+    pub fn set_cell_name(&mut self, cell: char, name: &str) -> Result<(), String> {
+        if !CELLS.contains(cell) {
+            return Err(format!("unknown cell {cell:?}"));
+        }
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || "=:{}".contains(c)) {
+            return Err(format!("invalid cell name {name:?}"));
+        }
+        self.cell_names.retain(|(c, _)| *c != cell);
+        self.cell_names.push((cell, name.to_string()));
+        Ok(())
+    }
+
+    /// Returns a name in sticker, hypercubing, or 4D notation with each cell
+    /// renamed as configured.
+    pub fn localize(&self, name: &str) -> String {
+        name.chars()
+            .map(
+                |c| match self.cell_names.iter().find(|(cell, _)| *cell == c) {
+                    Some((_, name)) => name.clone(),
+                    None => c.to_string(),
+                },
+            )
+            .collect()
+    }
+
+    /// Returns the reorient with a name in sticker or hypercubing notation
+    /// using the configured cell names, if there are any.
+    pub fn parse_localized(&self, s: &str) -> Option<Reorient> {
+        if self.cell_names.is_empty() {
+            return None;
+        }
+        Reorient::ALL
+            .iter()
+            .copied()
+            .find(|r| self.localized_names(*r).contains(&s.to_string()))
+    }
+
+    /// Parses a reorient in any notation, including sticker and hypercubing
+    /// notation with the cell names configured in this model.
+    pub fn parse_reorient(&self, s: &str) -> Result<Reorient, String> {
+        // Configured cell names take precedence over the standard ones they
+        // may coincide with.
+        match self.parse_localized(s.trim()) {
+            Some(r) => Ok(r),
+            None => s.parse(),
+        }
+    }
+
+    /// Returns the names of a reorient in sticker and hypercubing notation,
+    /// for every puzzle size, using the configured cell names.
+    fn localized_names(&self, r: Reorient) -> Vec<String> {
+        let mut names = vec![self.localize(r.sticker_name())];
+        if !r.is_none() {
+            names.extend([2, 3].map(|size| self.localize(&r.hypercubing_name(size))));
+        }
+        names
+    }
+
+    /// Returns an error if two reorients have the same name with the
+    /// configured cell names.
+    fn check_cell_names(&self) -> Result<(), String> {
+        let mut seen = std::collections::HashMap::new();
+        for &r in Reorient::ALL {
+            for name in self.localized_names(r) {
+                if let Some(other) = seen.insert(name.clone(), r).filter(|&other| other != r) {
+                    return Err(format!(
+                        "cell names make {name} mean both {} and {}",
+                        other.xyz_name(),
+                        r.xyz_name(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads settings from a config file. Each line is one of:
+    ///
+    /// - `cost <reorient> = <cost>`
+    /// - `tps <moves per second>`, for `--objective time`
+    /// - `time <reorient> = <milliseconds>`, for `--objective time`
+    /// - `macro <name> = <reorient>... cost <cost>`
+    /// - `expand <reorient> = <rotation>... [cost <cost>]`, where each
+    ///   rotation is `x`, `y`, or `z` with an optional `'` or `2`. Without an
+    ///   explicit cost, the reorient costs one ETM per quarter turn.
+    /// - `forbid <orientation> = <move>...`, where the orientation is relative
+    ///   to solved, as in chunk headers, and each move is a face turn such as
+    ///   `B'`. Solutions never make those moves in that orientation.
+    /// - `name <cell> = <name>`, where the cell is one of `I`, `O`, `R`, `L`,
+    ///   `U`, `D`, `F`, or `B`, to write it differently in sticker,
+    ///   hypercubing, and 4D notation, such as `K` instead of `I`. Reorients
+    ///   in the rest of the file may use the new names.
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn load_config(&mut self, contents: &str) -> Result<(), String> {
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |e: String| format!("line {}: {e}", i + 1);
+            match line.split_once(char::is_whitespace) {
+                Some(("cost", rest)) => self.load_cost(rest).map_err(err)?,
+                Some(("tps", rest)) => match rest.trim().parse::<f64>() {
+                    Ok(tps) if tps > 0.0 => self.move_ms = 1000.0 / tps,
+                    _ => return Err(err(format!("invalid TPS {:?}", rest.trim()))),
+                },
+                Some(("time", rest)) => self.load_time(rest).map_err(err)?,
+                Some(("macro", rest)) => {
+                    let m = Macro::parse_with(rest, |r| self.parse_reorient(r)).map_err(err)?;
+                    self.add_macro(m)
+                }
+                Some(("expand", rest)) => self.load_expansion(rest).map_err(err)?,
+                Some(("forbid", rest)) => self.load_forbidden(rest).map_err(err)?,
+                Some(("name", rest)) => self.load_cell_name(rest).map_err(err)?,
+                _ => return Err(err(format!("unknown setting {line:?}"))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses and applies a `name` line from a config file.
+    fn load_cell_name(&mut self, s: &str) -> Result<(), String> {
+        let syntax = || "expected `name <cell> = <name>`".to_string();
+        let (cell, name) = s.split_once('=').ok_or_else(syntax)?;
+        let mut chars = cell.trim().chars();
+        let (Some(cell), None) = (chars.next(), chars.next()) else {
+            return Err(format!("unknown cell {:?}", cell.trim()));
+        };
+        self.set_cell_name(cell, name.trim())?;
+        self.check_cell_names()
+    }
+
+    /// Parses and applies a `cost` line from a config file.
+    fn load_cost(&mut self, s: &str) -> Result<(), String> {
+        let (reorient, cost) = s
+            .split_once('=')
+            .ok_or("expected `cost <reorient> = <cost>`")?;
+        let reorient = self.parse_reorient(reorient)?;
+        let cost = cost
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid cost {:?}", cost.trim()))?;
+        if !reorient.is_none() {
+            self.set_cost(reorient, cost);
+        }
+        Ok(())
+    }
+
+    /// Parses and applies a `time` line from a config file.
+    fn load_time(&mut self, s: &str) -> Result<(), String> {
+        let (reorient, ms) = s
+            .split_once('=')
+            .ok_or("expected `time <reorient> = <milliseconds>`")?;
+        let reorient = self.parse_reorient(reorient)?;
+        let ms = ms
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|ms| *ms >= 0.0)
+            .ok_or_else(|| format!("invalid time {:?}", ms.trim()))?;
+        if !reorient.is_none() {
+            self.reorient_ms[reorient as usize] = Some(ms);
+        }
+        Ok(())
+    }
+
+    /// Parses and applies an `expand` line from a config file.
+    fn load_expansion(&mut self, s: &str) -> Result<(), String> {
+        let (reorient, rest) = s
+            .split_once('=')
+            .ok_or("expected `expand <reorient> = <rotation>... [cost <cost>]`")?;
+        let reorient = self.parse_reorient(reorient)?;
+        let (rotations, cost) = match rest.rsplit_once("cost") {
+            Some((rotations, cost)) => {
+                let cost = cost
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid cost {:?}", cost.trim()))?;
+                (rotations, Some(cost))
+            }
+            None => (rest, None),
+        };
+        let moves = rotations
+            .split_whitespace()
+            .map(parse_rotation)
+            .collect::<Result<Vec<Move>, _>>()?;
+        let quarter_turns = moves
+            .iter()
+            .map(|mv| match mv.get_variant() {
+                MoveVariant::Double => 2,
+                _ => 1,
+            })
+            .sum();
+        self.set_expansion(reorient, moves)?;
+        self.set_cost(reorient, cost.unwrap_or(quarter_turns));
+        Ok(())
+    }
+
+    /// Parses and applies a `forbid` line from a config file.
+    fn load_forbidden(&mut self, s: &str) -> Result<(), String> {
+        let (orientation, moves) = s
+            .split_once('=')
+            .ok_or("expected `forbid <orientation> = <move>...`")?;
+        let orientation = self.parse_reorient(orientation)?;
+        for mv in moves.split_whitespace() {
+            self.forbid(orientation, parse_face_move(mv)?);
+        }
+        Ok(())
+    }
+}
+
+/// Parses a whole-cube rotation such as `x`, `y'`, or `z2`.
+fn parse_rotation(s: &str) -> Result<Move, String> {
+    parse_move(s, "xyz").ok_or_else(|| format!("invalid rotation {s:?}"))
+}
+
+/// Parses a face turn such as `R`, `U'`, or `F2`.
+pub fn parse_face_move(s: &str) -> Result<Move, String> {
+    parse_move(s, "RLUDFB").ok_or_else(|| format!("invalid face turn {s:?}"))
+}
+
+/// Parses a move of an algorithm: a face turn, a wide turn such as `Rw'`, or
+/// a whole-cube rotation. Half turns may also be written as `R2'`.
+pub fn parse_alg_move(s: &str) -> Result<Move, String> {
+    let error = || format!("unsupported move {s:?}");
+    let token = s.replace("2'", "2");
+    if token.get(1..2) != Some("w") {
+        return parse_move(&token, "RLUDFBxyz").ok_or_else(error);
+    }
+    Ok(
+        match parse_move(&token.replacen('w', "", 1), "RLUDFB").ok_or_else(error)? {
+            Move::R(v) => Move::Rw(2, v),
+            Move::L(v) => Move::Lw(2, v),
+            Move::U(v) => Move::Uw(2, v),
+            Move::D(v) => Move::Dw(2, v),
+            Move::F(v) => Move::Fw(2, v),
+            Move::B(v) => Move::Bw(2, v),
+            _ => unreachable!(),
+        },
+    )
+}
+
+/// Parses a move whose letter is one of `letters`.
+fn parse_move(s: &str, letters: &str) -> Option<Move> {
+    let mut chars = s.chars();
+    let letter = chars.next().filter(|&c| letters.contains(c))?;
+    let variant = match chars.as_str() {
+        "" => MoveVariant::Standard,
+        "2" => MoveVariant::Double,
+        "'" => MoveVariant::Inverse,
+        _ => return None,
+    };
+    Some(match letter {
+        'R' => Move::R(variant),
+        'L' => Move::L(variant),
+        'U' => Move::U(variant),
+        'D' => Move::D(variant),
+        'F' => Move::F(variant),
+        'B' => Move::B(variant),
+        'x' => Move::X(variant),
+        'y' => Move::Y(variant),
+        _ => Move::Z(variant),
+    })
+}
+
+/// Named sequence of reorients that is performed as a unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Macro {
+    pub name: String,
+    pub steps: Vec<Reorient>,
+    pub cost: usize,
+}
+impl FromStr for Macro {
+    type Err = String;
+
+    /// Parses a macro in the form `<name> = <reorient>... cost <cost>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with(s, str::parse)
+    }
+}
+impl Macro {
+    /// Parses a macro like [`Macro::from_str()`], parsing each reorient with
+    /// `parse_reorient`.
+    fn parse_with(
+        s: &str,
+        parse_reorient: impl Fn(&str) -> Result<Reorient, String>,
+    ) -> Result<Self, String> {
+        let syntax = || "expected `<name> = <reorient>... cost <cost>`".to_string();
+        let (name, rest) = s.split_once('=').ok_or_else(syntax)?;
+        let (steps, cost) = rest.rsplit_once("cost").ok_or_else(syntax)?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("invalid macro name {name:?}"));
+        }
+        let steps = steps
+            .split_whitespace()
+            .map(parse_reorient)
+            .collect::<Result<Vec<Reorient>, _>>()?;
+        let cost = cost
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid cost {:?}", cost.trim()))?;
+        let ret = Self {
+            name: name.to_string(),
+            steps,
+            cost,
+        };
+        if ret.net().is_none() {
+            return Err(format!("macro {name} does not change the orientation"));
+        }
+        Ok(ret)
+    }
+
+    /// Returns the reorient with the same effect as the whole macro.
+    pub fn net(&self) -> Reorient {
+        self.steps
+            .iter()
+            .fold(Reorient::None, |acc, &r| acc.then(r))
+    }
+}
+
+impl Reorient {
+    /// Returns every reorient grouped by its cost under `model`, cheapest
+    /// group first. Within a group, reorients are in the order of
+    /// [`Reorient::ALL`].
+    pub fn by_cost(model: &CostModel) -> Vec<(usize, Vec<Reorient>)> {
+        let mut ret: Vec<(usize, Vec<Reorient>)> = vec![];
+        let mut all = Reorient::ALL.to_vec();
+        all.sort_by_key(|&r| model.cost(r));
+        for r in all {
+            match ret.last_mut() {
+                Some((cost, group)) if *cost == model.cost(r) => group.push(r),
+                _ => ret.push((model.cost(r), vec![r])),
+            }
+        }
+        ret
+    }
+}
+
+/// Returns the cost of a reorient when no moves are made cheap.
+fn standard_cost(r: Reorient) -> usize {
+    use Reorient::*;
+
+    match r {
+        None => 0,
+        R | L | U | D | F | B => 1,
+        R2 | U2 | F2 => 2,
+        UF | UR | FR | DF | UL | BR => 3,
+        UFR | DBL | UFL | DBR | DFR | UBL | UBR | DFL => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "
+        # Costs of a one-handed executor.
+        cost Ox = 2
+        cost O = 5
+        tps 5
+        time Oy = 300
+        macro sune = Ox Oy cost 1
+        expand Oz = z
+        expand Ox2 = x x cost 3
+        forbid Oy = B' D
+        name I = K
+    ";
+
+    #[test]
+    fn load_config_applies_every_setting() {
+        let mut cost_model = CostModel::default();
+        cost_model.load_config(CONFIG).unwrap();
+
+        assert_eq!(cost_model.cost(Reorient::R), 2);
+        assert_eq!(cost_model.cost(Reorient::None), 0);
+        assert_eq!(cost_model.move_time(), 200.0);
+        assert_eq!(cost_model.reorient_time(Reorient::U), 300.0);
+        assert_eq!(cost_model.reorient_time(Reorient::R), 400.0);
+
+        let sune = cost_model.macro_named("sune").unwrap();
+        assert_eq!(sune.net(), Reorient::R.then(Reorient::U));
+        assert_eq!(cost_model.cost(sune.net()), 1);
+
+        assert_eq!(
+            cost_model.expansion(Reorient::F),
+            [Move::Z(MoveVariant::Standard)]
+        );
+        assert_eq!(cost_model.cost(Reorient::F), 1);
+        assert_eq!(cost_model.cost(Reorient::R2), 3);
+
+        assert_eq!(
+            cost_model.forbidden_moves()[Reorient::U as usize],
+            [
+                Move::B(MoveVariant::Inverse),
+                Move::D(MoveVariant::Standard)
+            ],
+        );
+
+        let renamed = cost_model.localize(Reorient::R.sticker_name());
+        assert!(renamed.contains('K') && !renamed.contains('I'), "{renamed}");
+        assert_eq!(cost_model.parse_reorient(&renamed), Ok(Reorient::R));
+    }
+
+    #[test]
+    fn load_config_reports_the_bad_line() {
+        for (config, line) in [
+            ("cost Ox = 1\ncost Ox 2", 2),
+            ("\n\ntps 0", 3),
+            ("time Oy = -1", 1),
+            ("macro noop = Ox Ox' cost 1", 1),
+            ("expand Oy = x", 1),
+            ("forbid Oy = Rw", 1),
+            ("name Q = K", 1),
+            ("\nname R = L", 2),
+            ("speed 5", 1),
+        ] {
+            let error = CostModel::default().load_config(config).expect_err(config);
+            assert!(
+                error.starts_with(&format!("line {line}: ")),
+                "{config:?}: {error}"
+            );
+        }
+    }
+}
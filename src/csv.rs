@@ -0,0 +1,127 @@
+//! Results as CSV rows for spreadsheets.
+
+use serde_json::Value;
+
+use crate::{Alg, Reorient, RktOptimizer, Solution};
+
+/// Header row naming the columns of [`rows()`].
+pub const HEADER: &str =
+    "line,alg,reorients,stm,etm,solution,id,pre_rotation,savings,version,cost_model,settings,timestamp,elapsed_ms";
+
+/// Returns one CSV row per solution of an algorithm found by `optimizer`,
+/// in its notation and cost model. `line` is the line of
+/// the input file that the algorithm is from, if any, `neutral` is the
+/// recommended starting orientation and the ETM it saves when solving from
+/// every orientation, and `meta` is the [`crate::meta::record()`] of the
+/// search, whose settings are kept as JSON.
+#[allow(clippy::too_many_arguments)]
+pub fn rows(
+    optimizer: &RktOptimizer,
+    input: &str,
+    line: Option<usize>,
+    alg: &Alg,
+    reorient_count: usize,
+    solutions: &[Solution],
+    neutral: Option<(Reorient, Option<usize>)>,
+    meta: &Value,
+) -> Vec<String> {
+    let line = line.map_or(String::new(), |line| line.to_string());
+    let (pre_rotation, savings) = match neutral {
+        Some((start, savings)) => (
+            start.pre_rotation_name().to_string(),
+            savings.map_or(String::new(), |savings| savings.to_string()),
+        ),
+        None => (String::new(), String::new()),
+    };
+    // Fields that are missing from deterministic output are left empty.
+    let meta_field = |key: &str| match &meta[key] {
+        Value::Null => String::new(),
+        Value::String(s) => field(s),
+        value => field(&value.to_string()),
+    };
+    solutions
+        .iter()
+        .map(|solution| {
+            [
+                line.clone(),
+                field(input.trim()),
+                reorient_count.to_string(),
+                (alg.original_len() + reorient_count).to_string(),
+                (alg.original_len() + alg.cost(optimizer.cost_model(), solution)).to_string(),
+                field(&optimizer.display_pre_rotated(alg, solution)),
+                alg.solution_id(solution),
+                pre_rotation.clone(),
+                savings.clone(),
+                meta_field("version"),
+                meta_field("cost_model"),
+                meta_field("settings"),
+                meta_field("timestamp"),
+                meta_field("elapsed_ms"),
+            ]
+            .join(",")
+        })
+        .collect()
+}
+
+/// Quotes a field if it contains characters that are special in CSV.
+fn field(s: &str) -> String {
+    match s.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{meta, CostModel};
+
+    #[test]
+    fn rows_quote_fields_and_leave_out_missing_ones() {
+        let optimizer = RktOptimizer::new(3, 0, CostModel::default());
+        let alg = Alg::parse("R U R' U'").unwrap();
+        let solution = vec![Reorient::None, Reorient::R, Reorient::None];
+        let settings = json!({ "depth": 3 });
+        let meta = meta::record(&settings, optimizer.cost_model(), None, true);
+        let rows = rows(
+            &optimizer,
+            "R U R' U'",
+            Some(7),
+            &alg,
+            1,
+            std::slice::from_ref(&solution),
+            Some((Reorient::U, None)),
+            &meta,
+        );
+
+        let expected = [
+            "7",
+            "R U R' U'",
+            "1",
+            "5",
+            "5",
+            &optimizer.display_pre_rotated(&alg, &solution),
+            &alg.solution_id(&solution),
+            Reorient::U.pre_rotation_name(),
+            "",
+            meta::VERSION,
+            &optimizer.cost_model().fingerprint(),
+            "\"{\"\"depth\"\":3}\"",
+            "",
+            "",
+        ]
+        .join(",");
+        assert_eq!(rows, [expected]);
+        assert_eq!(HEADER.split(',').count(), 14);
+    }
+
+    #[test]
+    fn field_quotes_special_characters() {
+        assert_eq!(field("R U"), "R U");
+        assert_eq!(field("a,b"), "\"a,b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("a\nb"), "\"a\nb\"");
+    }
+}
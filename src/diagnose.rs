@@ -0,0 +1,122 @@
+//! Diagnostics comparing the pruning table's lower bounds against the true
+//! distance to a solved state, along solutions that were found.
+
+use std::collections::HashSet;
+use std::mem::discriminant;
+
+use cubesim::{Cube, Face, FaceletCube, Move};
+
+use crate::table::PruningTable;
+use crate::{Alg, RktOptimizer, Solution};
+
+/// Summary of how tight the heuristic was along a set of solutions.
+#[derive(Debug, Default, Clone)]
+pub struct HeuristicReport {
+    /// Number of distinct states visited along the solutions.
+    pub states: usize,
+    /// Number of states whose exact distance is within the search cap.
+    pub exact: usize,
+    /// Sum of (true distance - lower bound) over states with known distance.
+    pub total_slack: usize,
+    /// Number of states where the lower bound equals the true distance.
+    pub tight: usize,
+    /// Sum of (pruning budget - lower bound) over all states.
+    pub total_budget_slack: usize,
+}
+impl HeuristicReport {
+    pub fn print(&self, max_distance: usize) {
+        println!(
+            "Heuristic diagnostics over {} states along the solutions:",
+            self.states,
+        );
+        if self.states == 0 {
+            return;
+        }
+        println!(
+            "  exact distance found for {} of them (searched up to {} moves)",
+            self.exact, max_distance,
+        );
+        if self.exact > 0 {
+            println!(
+                "  average slack (true distance - lower bound): {:.2}",
+                self.total_slack as f64 / self.exact as f64,
+            );
+            println!(
+                "  lower bound was exact for {:.0}% of them",
+                100.0 * self.tight as f64 / self.exact as f64,
+            );
+        }
+        println!(
+            "  average slack (pruning budget - lower bound): {:.2}",
+            self.total_budget_slack as f64 / self.states as f64,
+        );
+    }
+}
+
+/// Compares the lower bound from `optimizer`'s pruning table of every state
+/// along the given solutions with the true distance of that state from
+/// solved, searching up to `max_distance` moves to find the true distance.
+pub fn diagnose_heuristic(
+    optimizer: &RktOptimizer,
+    alg: &Alg,
+    solutions: &[Solution],
+    max_distance: usize,
+) -> HeuristicReport {
+    let mut report = HeuristicReport::default();
+    let moves = &alg.moves;
+    let mut seen: HashSet<Vec<Face>> = HashSet::new();
+    let table = optimizer.table();
+
+    for solution in solutions {
+        let mut state = optimizer
+            .solved_cube()
+            .apply_moves(alg.start.equivalent_rkt_moves());
+        for (i, &mv) in moves.iter().enumerate() {
+            if seen.insert(state.state()) {
+                let bound = table.lower_bound(&state) as usize;
+                // `dfs` prunes states whose lower bound exceeds this.
+                let budget = moves.len() - i + 1;
+                report.states += 1;
+                report.total_budget_slack += budget.saturating_sub(bound);
+                if let Some(distance) = exact_distance(&table, &state, max_distance) {
+                    report.exact += 1;
+                    report.total_slack += distance - bound;
+                    report.tight += (distance == bound) as usize;
+                }
+            }
+
+            state = state.apply_move(mv);
+            if let Some(reorient) = solution.get(i) {
+                state = state.apply_moves(reorient.equivalent_rkt_moves());
+            }
+        }
+    }
+
+    report
+}
+
+/// Returns the number of moves needed to bring a state to any solved
+/// orientation, or `None` if it needs more than `max_distance` moves.
+fn exact_distance(table: &PruningTable, state: &FaceletCube, max_distance: usize) -> Option<usize> {
+    let lower_bound = table.lower_bound(state) as usize;
+    (lower_bound..=max_distance).find(|&limit| reachable_within(table, state, limit, None))
+}
+
+fn reachable_within(
+    table: &PruningTable,
+    state: &FaceletCube,
+    limit: usize,
+    last_move: Option<Move>,
+) -> bool {
+    let bound = table.lower_bound(state) as usize;
+    if bound == 0 {
+        return true;
+    }
+    if bound > limit {
+        return false;
+    }
+    table.moves.iter().any(|&mv| {
+        last_move.is_none_or(|last| discriminant(&last) != discriminant(&mv))
+            && reachable_within(table, &state.apply_move(mv), limit - 1, Some(mv))
+    })
+}
@@ -0,0 +1,153 @@
+//! Comparing two result files written with `--json`.
+
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::merge;
+
+/// Report how the results for each algorithm changed between two result
+/// files written with `--json`, such as before and after changing the cost
+/// model.
+#[derive(clap::Args, Debug, Clone)]
+pub struct DiffResultsArgs {
+    old: PathBuf,
+    new: PathBuf,
+}
+
+pub fn run(args: &DiffResultsArgs) -> Result<(), String> {
+    let old = merge::read(&args.old)?;
+    let new = merge::read(&args.new)?;
+    let find = |results: &[Value], input: &Value| -> Option<Value> {
+        results.iter().find(|r| r["input"] == *input).cloned()
+    };
+
+    let mut unchanged = 0;
+    let mut changed = 0;
+    for old_result in &old {
+        let input = old_result["input"].as_str().unwrap_or("");
+        let Some(new_result) = find(&new, &old_result["input"]) else {
+            println!("{input}: only in {}", args.old.display());
+            println!();
+            continue;
+        };
+        if same(old_result, &new_result) {
+            unchanged += 1;
+            continue;
+        }
+        let old_solutions = solution_algs(old_result);
+        let new_solutions = solution_algs(&new_result);
+        changed += 1;
+        println!(
+            "{input}: {} -> {} reorients, {} -> {} ETM",
+            reorient_count(old_result),
+            reorient_count(&new_result),
+            etm(old_result),
+            etm(&new_result),
+        );
+        for alg in old_solutions.iter().filter(|s| !new_solutions.contains(s)) {
+            println!("  - {alg}");
+        }
+        for alg in new_solutions.iter().filter(|s| !old_solutions.contains(s)) {
+            println!("  + {alg}");
+        }
+        println!();
+    }
+    for new_result in &new {
+        if find(&old, &new_result["input"]).is_none() {
+            let input = new_result["input"].as_str().unwrap_or("");
+            println!("{input}: only in {}", args.new.display());
+            println!();
+        }
+    }
+    println!("{changed} changed, {unchanged} unchanged.");
+    Ok(())
+}
+
+/// Returns whether two results for an algorithm have the same solutions, in
+/// any order, and the same error, if any.
+fn same(old: &Value, new: &Value) -> bool {
+    let old_solutions = solution_algs(old);
+    let new_solutions = solution_algs(new);
+    old_solutions.len() == new_solutions.len()
+        && old_solutions.iter().all(|s| new_solutions.contains(s))
+        && old["error"] == new["error"]
+}
+
+/// Returns the solutions in a result as strings.
+fn solution_algs(result: &Value) -> Vec<String> {
+    result["solutions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|s| s["alg"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// Formats the number of reorients in a result.
+fn reorient_count(result: &Value) -> String {
+    match result.get("error") {
+        Some(_) => "error".to_string(),
+        None => result["reorient_count"]
+            .as_u64()
+            .map_or("none".to_string(), |n| n.to_string()),
+    }
+}
+
+/// Formats the ETM of the cheapest solution in a result.
+fn etm(result: &Value) -> String {
+    match (result.get("error"), merge::best_etm(result)) {
+        (Some(_), _) => "error".to_string(),
+        (None, u64::MAX) => "none".to_string(),
+        (None, etm) => etm.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn results_with_reordered_solutions_are_the_same() {
+        let old = json!({
+            "input": "R U",
+            "reorient_count": 1,
+            "solutions": [{ "alg": "R Ox U", "etm": 3 }, { "alg": "R Oy U", "etm": 3 }],
+        });
+        let reordered = json!({
+            "input": "R U",
+            "reorient_count": 1,
+            "solutions": [{ "alg": "R Oy U", "etm": 3 }, { "alg": "R Ox U", "etm": 3 }],
+        });
+        let changed = json!({
+            "input": "R U",
+            "reorient_count": 1,
+            "solutions": [{ "alg": "R Ox U", "etm": 3 }],
+        });
+        let error = json!({ "input": "R U", "error": "no solutions" });
+        assert!(same(&old, &reordered));
+        assert!(!same(&old, &changed));
+        assert!(!same(&json!({ "input": "R U", "solutions": [] }), &error));
+    }
+
+    #[test]
+    fn summaries_describe_missing_solutions() {
+        let found = json!({ "reorient_count": 2, "solutions": [{ "etm": 7 }, { "etm": 5 }] });
+        let none = json!({ "reorient_count": null, "solutions": [] });
+        let error = json!({ "error": "unsupported move" });
+        assert_eq!(
+            (reorient_count(&found), etm(&found)),
+            ("2".into(), "5".into())
+        );
+        assert_eq!(
+            (reorient_count(&none), etm(&none)),
+            ("none".into(), "none".into())
+        );
+        assert_eq!(
+            (reorient_count(&error), etm(&error)),
+            ("error".into(), "error".into())
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Practice drills for the reorient placements on an alg sheet.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::{display_move, invert_moves, Alg, RktOptimizer, Solution};
+
+/// Drill the algorithms on an alg sheet in random order: show a scramble that
+/// sets up each one, then its reorients when asked.
+#[derive(clap::Args, Debug, Clone)]
+pub struct DrillArgs {
+    /// Alg sheet with one algorithm per line, including its reorients, as
+    /// printed by rocket. Blank lines and lines starting with `#` are ignored.
+    input: PathBuf,
+
+    /// Number of drills. Defaults to one per algorithm.
+    #[clap(short = 'n', long)]
+    count: Option<usize>,
+
+    /// Seed for the random number generator.
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Write the scrambles to this file, one per line as csTimer imports
+    /// them, instead of drilling interactively.
+    #[clap(long)]
+    export: Option<PathBuf>,
+}
+
+pub fn run(args: &DrillArgs, optimizer: &RktOptimizer) -> Result<(), String> {
+    let cost_model = optimizer.cost_model();
+    let contents = std::fs::read_to_string(&args.input)
+        .map_err(|e| format!("error reading {}: {e}", args.input.display()))?;
+    let sheet = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            Alg::parse_solved(line, cost_model).map_err(|e| format!("line {}: {e}", i + 1))
+        })
+        .collect::<Result<Vec<(Alg, Solution)>, String>>()?;
+    if sheet.is_empty() {
+        return Err(format!("no algorithms in {}", args.input.display()));
+    }
+
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    // Go through the whole sheet in a random order before repeating anything.
+    let count = args.count.unwrap_or(sheet.len());
+    let mut order = vec![];
+    while order.len() < count {
+        let mut round: Vec<usize> = (0..sheet.len()).collect();
+        round.shuffle(&mut rng);
+        order.extend(round);
+    }
+    order.truncate(count);
+
+    let scramble = |(alg, solution): &(Alg, Solution)| {
+        let moves: Vec<String> = invert_moves(&alg.rkt_moves(cost_model, solution))
+            .into_iter()
+            .map(display_move)
+            .collect();
+        moves.join(" ")
+    };
+
+    if let Some(path) = &args.export {
+        let out: String = order.iter().map(|&i| scramble(&sheet[i]) + "\n").collect();
+        std::fs::write(path, out).map_err(|e| format!("error writing {}: {e}", path.display()))?;
+        println!("Wrote {count} scrambles to {}.", path.display());
+        return Ok(());
+    }
+
+    for (n, &i) in order.iter().enumerate() {
+        let (alg, solution) = &sheet[i];
+        println!("Drill {}/{count}: {}", n + 1, scramble(&sheet[i]));
+        print!("Press Enter to show the solution ");
+        std::io::stdout().flush().unwrap();
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+            _ => (),
+        }
+        println!("{}", optimizer.display_pre_rotated(alg, solution));
+        println!("  RKT: {}", alg.display_rkt(cost_model, solution));
+        println!();
+    }
+    Ok(())
+}
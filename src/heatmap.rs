@@ -0,0 +1,71 @@
+//! Grid of which reorients can go in each gap of an algorithm, for seeing why
+//! the solutions cluster their reorients where they do.
+
+use crate::{display_move, Alg, Reorient, RktOptimizer, Solution};
+
+/// Width of the column with the reorient names.
+const LABEL_WIDTH: usize = 9;
+/// Width of each move and the gap after it.
+const CELL_WIDTH: usize = 3;
+
+/// Prints a grid with a row for each reorient used by any of `solutions` and
+/// a column for each gap, marking the gaps where the reorient is part of a
+/// solution with as few reorients as any. `solutions` should be every
+/// solution found, not just the cheapest.
+///
+/// Each gap is shown after the move it follows. `.` means that no solution
+/// has the reorient there, a digit from 1 to 9 is the share of the solutions
+/// that do in ninths, rounded up, and `#` means that all of them do. Gaps
+/// outside the window are left blank. Reorients are named in `optimizer`'s
+/// notation.
+pub fn print(optimizer: &RktOptimizer, alg: &Alg, solutions: &[Solution]) {
+    let gaps = solutions.first().map_or(0, Vec::len);
+    let mut counts = vec![[0; 24]; gaps];
+    let mut any = vec![0; gaps];
+    for solution in solutions {
+        for (gap, &r) in solution.iter().enumerate() {
+            counts[gap][r as usize] += 1;
+            if !r.is_none() {
+                any[gap] += 1;
+            }
+        }
+    }
+    let rows: Vec<Reorient> = Reorient::ALL[1..]
+        .iter()
+        .copied()
+        .filter(|&r| counts.iter().any(|gap_counts| gap_counts[r as usize] > 0))
+        .collect();
+
+    println!(
+        "Reorients that are part of some solution with {} reorients, by gap:",
+        solutions
+            .first()
+            .map_or(0, |s| s.iter().filter(|r| !r.is_none()).count()),
+    );
+    let moves: String = alg
+        .moves
+        .iter()
+        .map(|&mv| format!("{:<CELL_WIDTH$}", display_move(mv)))
+        .collect();
+    println!("{:LABEL_WIDTH$}  {}", "", moves.trim_end());
+    let row = |label: &str, count: &dyn Fn(usize) -> usize| {
+        let cells: String = (0..gaps)
+            .map(|gap| {
+                let cell = match count(gap) {
+                    _ if !alg.allows_reorient(gap) => ' ',
+                    0 => '.',
+                    n if n == solutions.len() => '#',
+                    n => char::from_digit((n * 9).div_ceil(solutions.len()) as u32, 10)
+                        .unwrap_or('#'),
+                };
+                // Line the cell up with the space after its move.
+                format!("{:>CELL_WIDTH$}", cell)
+            })
+            .collect();
+        println!("{label:>LABEL_WIDTH$}  {}", cells.trim_end());
+    };
+    row("any", &|gap| any[gap]);
+    for r in rows {
+        row(&optimizer.reorient_name(r), &|gap| counts[gap][r as usize]);
+    }
+}
@@ -0,0 +1,68 @@
+//! Checking whether an existing placement of reorients can be beaten.
+
+use crate::{display_move, Alg, Reorient, RktOptimizer};
+
+/// Strip the reorients from an algorithm, solve it again, and compare the
+/// best placement found with the original one.
+#[derive(clap::Args, Debug, Clone)]
+pub struct ImproveArgs {
+    /// Algorithm with reorients, such as `R U Ozx2 R' U'`.
+    #[clap(required = true)]
+    alg: Vec<String>,
+}
+
+pub fn run(args: &ImproveArgs, optimizer: &RktOptimizer, max_depth: usize) -> Result<(), String> {
+    let cost_model = optimizer.cost_model();
+    let (alg, original) = Alg::parse_solved(&args.alg.join(" "), cost_model)?;
+    if !alg.solves(optimizer, &original) {
+        return Err("the original reorients don't solve the cube".to_string());
+    }
+    let original_cost = alg.cost(cost_model, &original);
+    println!(
+        "Original: {} ({original_cost} ETM)",
+        optimizer.display_pre_rotated(&alg, &original)
+    );
+
+    let (reorient_count, solutions) = optimizer.search(&alg, max_depth, false, None, &mut None);
+    let Some(best) = solutions.iter().min_by_key(|s| alg.cost(cost_model, s)) else {
+        println!("No solutions with at most {max_depth} reorients.");
+        return Ok(());
+    };
+    let best_cost = alg.cost(cost_model, best);
+    if best_cost >= original_cost {
+        println!(
+            "The original placement is as cheap as any with {reorient_count} reorients, \
+             the fewest possible.",
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Best:     {} ({best_cost} ETM)",
+        optimizer.display_pre_rotated(&alg, best)
+    );
+    println!("Saves {} ETM.", original_cost - best_cost);
+    println!();
+    println!("Changes:");
+    for (gap, (old, new)) in original.iter().zip(best).enumerate() {
+        if old == new {
+            continue;
+        }
+        let name = |r: &Reorient| match r.is_none() {
+            true => "nothing".to_string(),
+            false => optimizer.reorient_name(*r),
+        };
+        let anchor = match alg.anchor_at(gap) {
+            Some(anchor) => format!(" @{anchor}"),
+            None => String::new(),
+        };
+        println!(
+            "  after move {} ({}){anchor}: {} -> {}",
+            gap + 1,
+            display_move(alg.moves[gap]),
+            name(old),
+            name(new),
+        );
+    }
+    Ok(())
+}
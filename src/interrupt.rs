@@ -0,0 +1,126 @@
+//! Stopping searches early, with Ctrl-C or from another thread.
+//!
+//! Once [`install()`] has been called, Ctrl-C during a search asks the search
+//! to stop instead of exiting, which would throw away the pruning table, and
+//! the search returns the solutions found so far. Ctrl-C at any other time,
+//! or a second Ctrl-C before the search stops, exits as usual.
+//!
+//! Programs that embed the search can stop it the same way by giving the
+//! [`crate::RktOptimizer`] a [`CancelToken`]. Ctrl-C is the one stop that is
+//! shared by the whole process, since there is one signal handler; a
+//! [`CancelToken`] stops only the searches of the optimizers it was given
+//! to.
+
+use std::io::{ErrorKind, Read};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+
+/// Number of searches running.
+static SEARCHES: AtomicUsize = AtomicUsize::new(0);
+/// Whether Ctrl-C was pressed during a search.
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Makes Ctrl-C stop the current search instead of exiting, if there is one.
+pub fn install() {
+    #[cfg(unix)]
+    {
+        extern "C" fn handle(_: libc::c_int) {
+            if SEARCHES.load(SeqCst) == 0 || REQUESTED.swap(true, SeqCst) {
+                // SAFETY: `_exit()` is async-signal-safe.
+                unsafe { libc::_exit(130) };
+            }
+        }
+        // SAFETY: the handler only touches atomics and calls `_exit()`.
+        let result = unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            // No `SA_RESTART`, so that Ctrl-C also interrupts waiting for an
+            // answer in `read_line()`.
+            action.sa_flags = 0;
+            libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut())
+        };
+        if result != 0 {
+            eprintln!(
+                "Warning: could not handle Ctrl-C: {}",
+                std::io::Error::last_os_error(),
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    eprintln!("Warning: stopping searches with Ctrl-C is not supported on this platform");
+}
+
+/// Returns whether Ctrl-C was pressed during the current search.
+pub fn requested() -> bool {
+    REQUESTED.load(SeqCst)
+}
+
+/// Reads a line from standard input without the line ending, or returns
+/// `None` at the end of input or if Ctrl-C is pressed during a search before
+/// the line is finished.
+pub fn read_line() -> Option<String> {
+    let mut stdin = std::io::stdin().lock();
+    let mut line = vec![];
+    let mut byte = [0];
+    loop {
+        if requested() {
+            return None;
+        }
+        match stdin.read(&mut byte) {
+            Ok(0) if line.is_empty() => return None,
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) => line.push(byte[0]),
+            // Ctrl-C, which the next iteration checks for.
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(_) => return None,
+        }
+    }
+    Some(
+        String::from_utf8_lossy(&line)
+            .trim_end_matches('\r')
+            .to_string(),
+    )
+}
+
+/// Marks a search as running until it is dropped, so that Ctrl-C stops it.
+pub struct Search(());
+impl Search {
+    pub fn start() -> Self {
+        SEARCHES.fetch_add(1, SeqCst);
+        Self(())
+    }
+}
+impl Drop for Search {
+    fn drop(&mut self) {
+        // Ctrl-C only stops the searches that were running when it was
+        // pressed.
+        if SEARCHES.fetch_sub(1, SeqCst) == 1 {
+            REQUESTED.store(false, SeqCst);
+        }
+    }
+}
+
+/// Handle for stopping searches from another thread, such as when the user
+/// edits the algorithm that is being optimized. Clones share the same state,
+/// and once cancelled, a token stays cancelled, so each search that should be
+/// stopped separately needs a new one.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the searches using this token to stop. They return the solutions
+    /// found so far, marked as interrupted.
+    pub fn cancel(&self) {
+        self.0.store(true, SeqCst);
+    }
+
+    /// Returns whether [`CancelToken::cancel()`] has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(SeqCst)
+    }
+}
@@ -0,0 +1,182 @@
+//! Machine-readable results.
+
+use serde_json::{json, Value};
+
+use crate::usage::{CheapSuggestion, Usage};
+use crate::{Alg, RktOptimizer, Solution};
+
+/// Returns the results of optimizing an algorithm with `optimizer` as a JSON
+/// object, in its notation and cost model. The
+/// reorient count and STM are `null` if there are no solutions. `optimal` is
+/// whether the solutions are proven to include the cheapest, or that there
+/// are none if there are no solutions.
+///
+/// Each solution has the id from [`Alg::solution_id()`], and each reorient
+/// is listed with the gap it is placed in, where gap `i` is between move `i`
+/// and move `i + 1` counting from 0, along with the name of the anchor at
+/// that gap, if there is one.
+pub fn result(
+    optimizer: &RktOptimizer,
+    input: &str,
+    alg: &Alg,
+    reorient_count: usize,
+    solutions: &[Solution],
+    optimal: bool,
+) -> Value {
+    let cost_model = optimizer.cost_model();
+    let solutions: Vec<Value> = solutions
+        .iter()
+        .map(|solution| {
+            let reorients: Vec<Value> = solution
+                .iter()
+                .enumerate()
+                .filter(|(_, r)| !r.is_none())
+                .map(|(gap, &r)| {
+                    json!({
+                        "gap": gap,
+                        "reorient": optimizer.reorient_name(r),
+                        "anchor": alg.anchor_at(gap),
+                    })
+                })
+                .collect();
+            json!({
+                "id": alg.solution_id(solution),
+                "alg": optimizer.display_pre_rotated(alg, solution),
+                "rkt": alg.display_rkt(cost_model, solution),
+                "etm": alg.original_len() + alg.cost(cost_model, solution),
+                "added_etm": alg.cost(cost_model, solution),
+                "reorients": reorients,
+            })
+        })
+        .collect();
+    let anchors: Vec<Value> = alg
+        .anchors
+        .iter()
+        .map(|a| json!({ "name": a.name, "position": a.position }))
+        .collect();
+    // Without solutions, the search gave up before finding out how many
+    // reorients are needed.
+    let found = !solutions.is_empty();
+    json!({
+        "input": input.trim(),
+        "start": (!alg.start.is_none()).then(|| optimizer.reorient_name(alg.start)),
+        "reorient_count": found.then_some(reorient_count),
+        "stm": found.then_some(alg.original_len() + reorient_count),
+        "optimal": optimal,
+        "anchors": anchors,
+        "solutions": solutions,
+    })
+}
+
+/// Returns an error for an algorithm that couldn't be optimized as a JSON
+/// object.
+pub fn error(input: &str, error: &str) -> Value {
+    json!({
+        "input": input.trim(),
+        "error": error,
+    })
+}
+
+/// Returns the reorient usage across a batch as a JSON object, with the
+/// reorients from most to least used, named in `optimizer`'s notation.
+pub fn usage(optimizer: &RktOptimizer, usage: &Usage) -> Value {
+    let reorients: Vec<Value> = usage
+        .rows()
+        .into_iter()
+        .map(|(r, usage)| {
+            json!({
+                "reorient": optimizer.reorient_name(r),
+                "uses": usage.uses,
+                "solutions": usage.solutions,
+                "etm": usage.etm,
+            })
+        })
+        .collect();
+    json!({
+        "usage": {
+            "solutions": usage.solutions(),
+            "reorients": reorients,
+        },
+    })
+}
+
+/// Returns the reorients suggested by [`Usage::suggest_cheap()`] as a JSON
+/// object, along with the total ETM of the batch under `optimizer`'s cost
+/// model before making any of them cheap.
+pub fn cheap_suggestions(
+    optimizer: &RktOptimizer,
+    usage: &Usage,
+    suggestions: &[CheapSuggestion],
+) -> Value {
+    let suggestions: Vec<Value> = suggestions
+        .iter()
+        .map(|suggestion| {
+            json!({
+                "reorient": optimizer.reorient_name(suggestion.reorient),
+                "saved": suggestion.saved,
+                "total_etm": suggestion.total,
+            })
+        })
+        .collect();
+    json!({
+        "suggest_cheap": {
+            "total_etm": usage.total_etm(optimizer.cost_model()),
+            "reorients": suggestions,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CostModel, Reorient};
+
+    #[test]
+    fn result_lists_each_reorient_with_its_gap() {
+        let optimizer = RktOptimizer::new(3, 0, CostModel::default());
+        let alg = Alg::parse("R U @pair R' U'").unwrap();
+        let solution = vec![Reorient::None, Reorient::R, Reorient::None];
+        let result = result(
+            &optimizer,
+            " R U @pair R' U' ",
+            &alg,
+            1,
+            std::slice::from_ref(&solution),
+            true,
+        );
+
+        assert_eq!(result["input"], "R U @pair R' U'");
+        assert_eq!(result["start"], Value::Null);
+        assert_eq!(result["reorient_count"], 1);
+        assert_eq!(result["stm"], 5);
+        assert_eq!(result["optimal"], true);
+        assert_eq!(
+            result["anchors"],
+            json!([{ "name": "pair", "position": 2 }])
+        );
+        let found = &result["solutions"][0];
+        assert_eq!(found["id"], alg.solution_id(&solution));
+        assert_eq!(found["alg"], optimizer.display_pre_rotated(&alg, &solution));
+        assert_eq!(found["etm"], 5);
+        assert_eq!(found["added_etm"], 1);
+        assert_eq!(
+            found["reorients"],
+            json!([{ "gap": 1, "reorient": "Ox", "anchor": "pair" }]),
+        );
+    }
+
+    #[test]
+    fn result_without_solutions_has_no_counts() {
+        let optimizer = RktOptimizer::new(3, 0, CostModel::default());
+        let alg = Alg::parse("R U").unwrap();
+        let result = result(&optimizer, "R U", &alg, 3, &[], false);
+        assert_eq!(result["reorient_count"], Value::Null);
+        assert_eq!(result["stm"], Value::Null);
+        assert_eq!(result["solutions"], json!([]));
+
+        assert_eq!(
+            error("R Q ", "unsupported move \"Q\""),
+            json!({ "input": "R Q", "error": "unsupported move \"Q\"" }),
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Deriving reorient costs from a Hyperspeedcube keymap.
+//!
+//! Each keybinding that performs a reorient costs 1 ETM plus the distance its
+//! key is from the home row position of the finger that presses it. Reorients
+//! without their own keybinding are performed with two keybindings, with an
+//! extra penalty when both keys are on the same hand. The moves of the
+//! algorithm itself are fixed, so only reorient costs are derived.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde_yaml::Value;
+
+use crate::Reorient;
+
+/// QWERTY rows, from the number row down.
+const ROWS: [&str; 4] = ["1234567890", "QWERTYUIOP", "ASDFGHJKL;", "ZXCVBNM,./"];
+const HOME_ROW: usize = 2;
+/// Finger that presses each column, numbered from the left pinky (0) to the
+/// right pinky (7).
+const COLUMN_FINGERS: [usize; 10] = [0, 1, 2, 3, 3, 4, 4, 5, 6, 7];
+/// Home column of each finger.
+const FINGER_HOMES: [usize; 8] = [0, 1, 2, 3, 6, 7, 8, 9];
+/// Extra cost of pressing two keys in a row with the same hand.
+const SAME_HAND_PENALTY: usize = 1;
+
+/// Derive reorient costs from a Hyperspeedcube keybinding config and print
+/// them as config file lines.
+#[derive(clap::Args, Debug, Clone)]
+pub struct ImportKeymapArgs {
+    /// Hyperspeedcube keybinding config (YAML).
+    input: PathBuf,
+
+    /// File to write the costs to, instead of standard output.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: &ImportKeymapArgs) -> Result<(), String> {
+    let contents = std::fs::read_to_string(&args.input)
+        .map_err(|e| format!("error reading {}: {e}", args.input.display()))?;
+    let yaml: Value = serde_yaml::from_str(&contents)
+        .map_err(|e| format!("error parsing {}: {e}", args.input.display()))?;
+
+    let mut bindings = vec![];
+    find_bindings(&yaml, &mut bindings);
+    // Keep the easiest key for each reorient.
+    let mut keys: HashMap<Reorient, Key> = HashMap::new();
+    for (key_name, reorient) in bindings {
+        match Key::parse(&key_name) {
+            Some(key) => {
+                let best = keys.entry(reorient).or_insert(key);
+                if key.effort() < best.effort() {
+                    *best = key;
+                }
+            }
+            None => eprintln!("Skipping keybinding for unknown key {key_name:?}"),
+        }
+    }
+    if keys.is_empty() {
+        return Err("no keybindings for reorients found".to_string());
+    }
+
+    let mut out = format!("# Reorient costs derived from {}\n", args.input.display());
+    for &r in &Reorient::ALL[1..] {
+        match cost(r, &keys) {
+            Some(cost) => out += &format!("cost {} = {cost}\n", r.xyz_name()),
+            None => out += &format!("# {}: no keybinding\n", r.xyz_name()),
+        }
+    }
+
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, out).map_err(|e| format!("error writing {}: {e}", path.display()))
+        }
+        None => {
+            print!("{out}");
+            Ok(())
+        }
+    }
+}
+
+/// Returns the cost of a reorient using one keybinding if possible, or else
+/// the cheapest pair of keybindings.
+fn cost(r: Reorient, keys: &HashMap<Reorient, Key>) -> Option<usize> {
+    if let Some(key) = keys.get(&r) {
+        return Some(key.effort());
+    }
+    keys.iter()
+        .flat_map(|(&a, ka)| keys.iter().map(move |(&b, kb)| (a, ka, b, kb)))
+        .filter(|&(a, _, b, _)| a.then(b) == r)
+        .map(|(_, ka, _, kb)| {
+            let same_hand = ka.is_left_hand() == kb.is_left_hand();
+            ka.effort() + kb.effort() + same_hand as usize * SAME_HAND_PENALTY
+        })
+        .min()
+}
+
+/// Collects every `(key, reorient)` pair from mappings that have a `key` and a
+/// `command` that performs a reorient.
+fn find_bindings(value: &Value, out: &mut Vec<(String, Reorient)>) {
+    match value {
+        Value::Mapping(map) => {
+            let key = map.get("key").and_then(first_string);
+            let reorient = map.get("command").and_then(find_reorient);
+            if let (Some(key), Some(reorient)) = (key, reorient) {
+                out.push((key, reorient));
+            }
+            for (_, v) in map {
+                find_bindings(v, out);
+            }
+        }
+        Value::Sequence(seq) => seq.iter().for_each(|v| find_bindings(v, out)),
+        _ => (),
+    }
+}
+
+/// Returns the first string anywhere in a value.
+fn first_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Mapping(map) => map.values().find_map(first_string),
+        Value::Sequence(seq) => seq.iter().find_map(first_string),
+        _ => None,
+    }
+}
+
+/// Returns the first string anywhere in a value that names a reorient.
+fn find_reorient(value: &Value) -> Option<Reorient> {
+    match value {
+        Value::String(s) => s.parse().ok().filter(|r: &Reorient| !r.is_none()),
+        Value::Mapping(map) => map.values().find_map(find_reorient),
+        Value::Sequence(seq) => seq.iter().find_map(find_reorient),
+        _ => None,
+    }
+}
+
+/// Position of a key on a QWERTY keyboard.
+#[derive(Debug, Copy, Clone)]
+struct Key {
+    row: usize,
+    column: usize,
+}
+impl Key {
+    /// Parses a key name such as `K`, `KeyK`, `Digit2`, or `Semicolon`.
+    fn parse(name: &str) -> Option<Self> {
+        let name = name
+            .strip_prefix("Key")
+            .or_else(|| name.strip_prefix("Digit"))
+            .unwrap_or(name);
+        let c = match name {
+            "Semicolon" => ';',
+            "Comma" => ',',
+            "Period" => '.',
+            "Slash" => '/',
+            _ if name.chars().count() == 1 => name.chars().next()?.to_ascii_uppercase(),
+            _ => return None,
+        };
+        ROWS.iter().enumerate().find_map(|(row, keys)| {
+            let column = keys.find(c)?;
+            Some(Self { row, column })
+        })
+    }
+
+    fn finger(self) -> usize {
+        COLUMN_FINGERS[self.column]
+    }
+
+    fn is_left_hand(self) -> bool {
+        self.finger() < 4
+    }
+
+    /// Returns 1 plus the number of keys the finger must travel from its home
+    /// position.
+    fn effort(self) -> usize {
+        1 + self.row.abs_diff(HOME_ROW) + self.column.abs_diff(FINGER_HOMES[self.finger()])
+    }
+}
@@ -0,0 +1,1148 @@
+//! Search for efficient placements of reorients in rotationless Rubik's cube
+//! algorithms, for executing them on a 4D puzzle via RKT.
+//!
+//! [`RktOptimizer`] is the entry point for other tools. It owns its
+//! settings, its pruning table, cost model, mask, and notation, so searches
+//! with different settings can run in the same process, and can be stopped
+//! from another thread with a [`CancelToken`].
+
+use cubesim::{Cube, FaceletCube, Move, MoveVariant};
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub mod affinity;
+pub mod anneal;
+pub mod beam;
+pub mod breakdown;
+pub mod calibrate;
+pub mod cancel;
+pub mod choices;
+pub mod compare;
+pub mod cost;
+pub mod csv;
+pub mod diagnose;
+pub mod diff;
+pub mod drill;
+pub mod heatmap;
+pub mod improve;
+pub mod interrupt;
+pub mod json;
+pub mod keymap;
+pub mod library;
+pub mod mask;
+pub mod meet;
+pub mod merge;
+pub mod meta;
+pub mod notation;
+pub mod optimizer;
+pub mod pdb;
+pub mod perm;
+pub mod plan;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod progress;
+pub mod rank;
+pub mod remote;
+pub mod selftest;
+pub mod stability;
+pub mod stats;
+pub mod sticker_cube;
+pub mod subgroup;
+pub mod suggest;
+pub mod symmetry;
+pub mod table;
+pub mod table_info;
+pub mod threads;
+pub mod tree;
+pub mod update;
+pub mod usage;
+pub mod variants;
+pub mod viz;
+pub mod warm;
+
+pub use cost::CostModel;
+pub use interrupt::CancelToken;
+pub use optimizer::{RktOptimizer, SearchResult};
+use rank::Objective;
+use sticker_cube::{MoveTable, StickerCube};
+use table::PruningTable;
+
+const SOLVER_FACES: &str = "RLUDBF";
+/// Bitmask of every face, as returned by [`parse_solver_faces()`].
+pub const ALL_SOLVER_FACES: u8 = 0b111111;
+
+/// Parses faces whose turns a pruning table is built from, such as `RUF`,
+/// into a bitmask where bit `i` stands for face `i` of `RLUDBF`. See
+/// [`RktOptimizer::with_solver_faces()`].
+pub fn parse_solver_faces(faces: &str) -> Result<u8, String> {
+    let mut mask = 0;
+    for c in faces.chars() {
+        let i = SOLVER_FACES
+            .find(c.to_ascii_uppercase())
+            .ok_or_else(|| format!("invalid face {c:?}"))?;
+        mask |= 1 << i;
+    }
+    if mask == 0 {
+        return Err("no faces".to_string());
+    }
+    Ok(mask)
+}
+
+/// Returns every turn of the faces in `faces`, a bitmask as returned by
+/// [`parse_solver_faces()`].
+pub fn face_moves(faces: u8) -> Vec<Move> {
+    use Move::{B, D, F, L, R, U};
+    use MoveVariant::*;
+
+    let all_faces = [R, L, U, D, B, F];
+    let variants = [Standard, Double, Inverse];
+
+    all_faces
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| faces & (1 << i) != 0)
+        .flat_map(|(_, f)| variants.into_iter().map(f))
+        .collect()
+}
+
+/// Returns whether `mv` turns a single face, as opposed to a wide turn or a
+/// whole-cube rotation.
+pub fn is_face_move(mv: Move) -> bool {
+    matches!(
+        mv,
+        Move::R(_) | Move::L(_) | Move::U(_) | Move::D(_) | Move::F(_) | Move::B(_)
+    )
+}
+
+/// Depth of the table used while the full one is built in the background.
+const SHALLOW_TABLE_DEPTH: u8 = 2;
+
+/// Returns every state that counts as solved: each rotation of the solved
+/// cube, followed by each turn of the U layer if `up_to_auf`.
+pub fn solved_states(up_to_auf: bool) -> Vec<StickerCube> {
+    let aufs: Vec<StickerCube> = match up_to_auf {
+        true => [
+            MoveVariant::Standard,
+            MoveVariant::Double,
+            MoveVariant::Inverse,
+        ]
+        .into_iter()
+        .map(|v| StickerCube::of_moves(&[Move::U(v)]))
+        .collect(),
+        false => vec![],
+    };
+    REORIENT_CUBES
+        .iter()
+        .flat_map(|cube| std::iter::once(*cube).chain(aufs.iter().map(|auf| cube.then(auf))))
+        .collect()
+}
+
+lazy_static! {
+    static ref COMPOSITION_TABLE: Vec<Vec<Reorient>> = make_composition_table();
+    static ref REORIENT_CUBES: Vec<StickerCube> = Reorient::ALL
+        .iter()
+        .map(|r| StickerCube::of_moves(r.equivalent_rkt_moves()))
+        .collect();
+    static ref REORIENT_TABLES: Vec<MoveTable> =
+        REORIENT_CUBES.iter().map(MoveTable::new).collect();
+}
+
+/// Returns a pruning table with depth `depth` that ignores the stickers in
+/// `mask` and is built from turns of `faces`, a bitmask as returned by
+/// [`parse_solver_faces()`], starting from the [`solved_states()`] for
+/// `up_to_auf`.
+///
+/// If `cache_dir` is given, the table is loaded from there if it was saved
+/// before, and saved there otherwise, and if `extend`, a table that isn't
+/// saved is built by deepening the deepest shallower one that is. If building
+/// the table would take more than `max_memory` bytes, it stops at a smaller
+/// depth with a warning, and isn't saved.
+fn make_table(
+    depth: u8,
+    mask: u64,
+    faces: u8,
+    up_to_auf: bool,
+    cache_dir: Option<&Path>,
+    extend: bool,
+    max_memory: usize,
+) -> PruningTable {
+    let cache_path = cache_path(depth, mask, faces, up_to_auf, cache_dir);
+    if let Some(path) = cache_path.as_deref().filter(|path| path.exists()) {
+        match PruningTable::load(path, face_moves(faces)) {
+            Ok(table) if table.depth() == depth => return table,
+            Ok(_) => eprintln!("Warning: {} has the wrong depth", path.display()),
+            Err(e) => eprintln!("Warning: {e}"),
+        }
+    }
+
+    let extended = extend
+        .then(|| extend_cached_table(depth, mask, faces, up_to_auf, cache_dir?, max_memory))
+        .flatten();
+    let table = extended.unwrap_or_else(|| {
+        PruningTable::with_memory_limit(
+            &sticker_cube::colors(mask),
+            &solved_states(up_to_auf),
+            depth,
+            face_moves(faces),
+            max_memory,
+        )
+    });
+    if table.depth() < depth {
+        eprintln!(
+            "Warning: stopped the pruning table at depth {} to stay within the memory limit",
+            table.depth(),
+        );
+        return table;
+    }
+
+    if let Some(path) = &cache_path {
+        if let Err(e) = table.save(path) {
+            eprintln!("Warning: {e}");
+        }
+    }
+    table
+}
+
+/// Returns a pruning table like [`make_table()`] built by deepening the
+/// deepest shallower one saved in `cache_dir`, or `None` if none is. Like
+/// [`PruningTable::with_memory_limit()`], this stops at a smaller depth if
+/// the next one is projected to take more than `max_memory` bytes.
+fn extend_cached_table(
+    depth: u8,
+    mask: u64,
+    faces: u8,
+    up_to_auf: bool,
+    cache_dir: &Path,
+    max_memory: usize,
+) -> Option<PruningTable> {
+    let mut table = (1..depth).rev().find_map(|shallower| {
+        let path = cache_path(shallower, mask, faces, up_to_auf, Some(cache_dir))?;
+        let table = PruningTable::load(&path, face_moves(faces)).ok()?;
+        (table.depth() == shallower).then_some(table)
+    })?;
+    let (colors, initial_states) = (sticker_cube::colors(mask), solved_states(up_to_auf));
+    while table.depth() < depth {
+        let projected =
+            table::projected_memory(initial_states.len(), table.depth() + 1, table.moves.len());
+        if projected > max_memory {
+            break;
+        }
+        table.deepen(&colors, &initial_states);
+    }
+    Some(table)
+}
+
+/// Returns the file in `cache_dir` that a pruning table with these settings is
+/// saved to, if there is a cache directory.
+fn cache_path(
+    depth: u8,
+    mask: u64,
+    faces: u8,
+    up_to_auf: bool,
+    cache_dir: Option<&Path>,
+) -> Option<PathBuf> {
+    let auf = if up_to_auf { "-auf" } else { "" };
+    cache_dir.map(|dir| {
+        dir.join(format!(
+            "table-3x3-d{depth}-f{faces:02x}-m{mask:016x}{auf}.bin"
+        ))
+    })
+}
+
+/// Returns a table where `table[a][b]` is the orientation reached by doing
+/// reorient `a` followed by reorient `b`.
+fn make_composition_table() -> Vec<Vec<Reorient>> {
+    let rotate = |reorients: &[Reorient]| {
+        let mut cube = FaceletCube::new(3);
+        for r in reorients {
+            cube = cube.apply_moves(r.equivalent_rkt_moves());
+        }
+        cube.state()
+    };
+    let states: Vec<_> = Reorient::ALL.iter().map(|&r| rotate(&[r])).collect();
+
+    Reorient::ALL
+        .iter()
+        .map(|&a| {
+            Reorient::ALL
+                .iter()
+                .map(|&b| {
+                    let state = rotate(&[a, b]);
+                    let i = states.iter().position(|s| *s == state).unwrap();
+                    Reorient::ALL[i]
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Settings for [`RktOptimizer::optimize()`].
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Maximum number of reorients to insert.
+    pub max_depth: usize,
+    /// Orientation that the algorithm is started from.
+    pub start: Reorient,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            max_depth: 3,
+            start: Reorient::None,
+        }
+    }
+}
+
+/// Reorientations between each move.
+pub type Solution = Vec<Reorient>;
+
+/// Returns the number of ETM added by a solution under `cost_model`.
+pub fn solution_cost(cost_model: &CostModel, solution: &Solution) -> usize {
+    solution.iter().map(|&r| cost_model.cost(r)).sum()
+}
+
+/// Returns the number of different reorients used by a solution.
+pub fn distinct_reorients(solution: &Solution) -> usize {
+    solution
+        .iter()
+        .filter(|r| !r.is_none())
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Rotationless algorithm to insert reorients into, along with information
+/// about the gaps between its moves.
+#[derive(Debug, Default, Clone)]
+pub struct Alg {
+    pub moves: Vec<Move>,
+    /// Gaps in the middle of a double move that was split into two quarter
+    /// turns. Placing a reorient in one of these gaps costs an extra move.
+    pub split_gaps: HashSet<usize>,
+    /// Labeled sections of the algorithm, in order.
+    pub chunks: Vec<Chunk>,
+    /// Named gaps of the algorithm, in order.
+    pub anchors: Vec<Anchor>,
+    /// Orientation that the algorithm is started from.
+    pub start: Reorient,
+    /// Range of moves within which reorients may be placed, if the rest of the
+    /// algorithm is fixed. Gap `i` is allowed if `window.start <= i` and
+    /// `i < window.end`.
+    pub window: Option<Range<usize>>,
+}
+/// Named gap of an algorithm, written `@name` in the algorithm so that the
+/// reorient placed there can be referred to regardless of notation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    pub name: String,
+    /// Number of moves before the anchor. The anchor names gap
+    /// `position - 1`, unless it is at the start or end of the algorithm.
+    pub position: usize,
+}
+/// Labeled section of an algorithm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub label: String,
+    /// Index of the first move after the start of the chunk. This may be equal
+    /// to the number of moves for a chunk at the end of the algorithm.
+    pub start: usize,
+    /// Orientation required at the start of the chunk.
+    pub orientation: Option<Reorient>,
+}
+impl Alg {
+    pub fn new(moves: Vec<Move>) -> Self {
+        Self {
+            moves,
+            ..Self::default()
+        }
+    }
+
+    /// Parses an algorithm, which may be split into chunks using headers of
+    /// the form `[label]` or `[label=orientation]`. For example,
+    /// `[setup] R U R' [insertion=Oy] D R D' [teardown=O] R U' R'` requires
+    /// the orientation to be `Oy` between the setup and the insertion and to
+    /// be back to the starting orientation before the teardown.
+    ///
+    /// Gaps may be named with anchors of the form `@name`, as in
+    /// `R U R' @pair1 U'`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::parse_with(s, &CostModel::default())
+    }
+
+    /// Parses an algorithm like [`Alg::parse()`], but the orientations in
+    /// chunk headers may also use the cell names configured in `cost_model`.
+    pub fn parse_with(s: &str, cost_model: &CostModel) -> Result<Self, String> {
+        let mut ret = Self::default();
+        for token in s.split_whitespace() {
+            if let Some(header) = token.strip_prefix('[') {
+                let header = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| format!("unterminated chunk header {token:?}"))?;
+                let (label, orientation) = match header.split_once('=') {
+                    Some((label, orientation)) => {
+                        (label, Some(cost_model.parse_reorient(orientation)?))
+                    }
+                    None => (header, None),
+                };
+                ret.chunks.push(Chunk {
+                    label: label.to_string(),
+                    start: ret.moves.len(),
+                    orientation,
+                });
+            } else if let Some(name) = token.strip_prefix('@') {
+                ret.push_anchor(name, ret.moves.len())?;
+            } else {
+                ret.moves.push(cost::parse_alg_move(token)?);
+            }
+        }
+        Ok(ret)
+    }
+
+    fn push_anchor(&mut self, name: &str, position: usize) -> Result<(), String> {
+        if name.is_empty() {
+            return Err("anchor without a name".to_string());
+        }
+        if self.anchors.iter().any(|a| a.name == name) {
+            return Err(format!("duplicate anchor @{name}"));
+        }
+        self.anchors.push(Anchor {
+            name: name.to_string(),
+            position,
+        });
+        Ok(())
+    }
+
+    /// Returns the name of the anchor at gap `i`, between move `i` and move
+    /// `i + 1`, if there is one.
+    pub fn anchor_at(&self, gap: usize) -> Option<&str> {
+        self.anchors
+            .iter()
+            .find(|a| a.position == gap + 1)
+            .map(|a| a.name.as_str())
+    }
+
+    /// Parses an algorithm with reorients between its moves, as printed by
+    /// [`Alg::display()`] or [`RktOptimizer::display_pre_rotated()`].
+    /// Reorients may be written in any notation, with the cell names
+    /// configured in `cost_model`, or as its macros. Anything after a `#`,
+    /// such as a [solution id](Alg::solution_id), is ignored.
+    pub fn parse_solved(s: &str, cost_model: &CostModel) -> Result<(Self, Solution), String> {
+        let mut alg = Alg::default();
+        let mut solution = vec![];
+        let mut pending = Reorient::None;
+        let s = s.split_once('#').map_or(s, |(s, _)| s);
+        for token in s.split_whitespace() {
+            if token.starts_with('[') {
+                // Chunk headers don't matter once reorients are placed.
+                continue;
+            }
+            if let Some(name) = token.strip_prefix('@') {
+                alg.push_anchor(name, alg.moves.len())?;
+                continue;
+            }
+            if let Some(rotation) = token.strip_suffix(':') {
+                pending = pending.then(cost_model.parse_reorient(rotation)?);
+                continue;
+            }
+            let reorient = cost_model
+                .macro_named(token)
+                .map(cost::Macro::net)
+                .or_else(|| cost_model.parse_reorient(token).ok());
+            if let Some(r) = reorient {
+                pending = pending.then(r);
+                continue;
+            }
+            let mv = cost::parse_face_move(token)?;
+            match alg.moves.is_empty() {
+                true => alg.start = pending,
+                false => solution.push(pending),
+            }
+            alg.moves.push(mv);
+            pending = Reorient::None;
+        }
+        if alg.moves.is_empty() {
+            return Err("no moves".to_string());
+        }
+        if !pending.is_none() {
+            return Err("reorient after the last move".to_string());
+        }
+        Ok((alg, solution))
+    }
+
+    /// Returns whether the reorients from a solution make the algorithm
+    /// solve the cube, by the same criterion as searches with `optimizer`.
+    pub fn solves(&self, optimizer: &RktOptimizer, solution: &Solution) -> bool {
+        let mut state = optimizer
+            .solved_cube()
+            .apply_moves(self.start.equivalent_rkt_moves());
+        for (i, &mv) in self.moves.iter().enumerate() {
+            state = state.apply_move(mv);
+            if let Some(r) = solution.get(i) {
+                state = state.apply_moves(r.equivalent_rkt_moves());
+            }
+        }
+        optimizer.table().within_one_move(&state)
+    }
+
+    /// Splits every double move into two quarter turns, so that a reorient may
+    /// be placed between them.
+    pub fn split_doubles(&mut self) {
+        let mut moves = vec![];
+        let mut new_indices = vec![];
+        for &mv in &self.moves {
+            new_indices.push(moves.len());
+            if mv.get_variant() == MoveVariant::Double {
+                let quarter_turn = mv.with_variant(MoveVariant::Standard);
+                self.split_gaps.insert(moves.len());
+                moves.push(quarter_turn);
+                moves.push(quarter_turn);
+            } else {
+                moves.push(mv);
+            }
+        }
+        new_indices.push(moves.len());
+        for chunk in &mut self.chunks {
+            chunk.start = new_indices[chunk.start];
+        }
+        for anchor in &mut self.anchors {
+            anchor.position = new_indices[anchor.position];
+        }
+        if let Some(window) = &mut self.window {
+            *window = new_indices[window.start]..new_indices[window.end];
+        }
+        self.moves = moves;
+    }
+
+    /// Returns whether a reorient may be placed in gap `i`, between move `i`
+    /// and move `i + 1`.
+    pub fn allows_reorient(&self, gap: usize) -> bool {
+        self.window.as_ref().is_none_or(|w| w.contains(&gap))
+    }
+
+    /// Returns the orientation required before each move, plus the
+    /// orientation required at the end of the algorithm. Orientations are
+    /// relative to the solved cube, not to the starting orientation.
+    pub fn boundaries(&self) -> Vec<Option<Reorient>> {
+        let mut ret = vec![None; self.moves.len() + 1];
+        ret[0] = Some(self.start);
+        for chunk in &self.chunks {
+            if chunk.orientation.is_some() {
+                ret[chunk.start] = chunk.orientation;
+            }
+        }
+        ret
+    }
+
+    /// Returns the number of moves in the algorithm before splitting any double
+    /// moves.
+    pub fn original_len(&self) -> usize {
+        self.moves.len() - self.split_gaps.len()
+    }
+
+    /// Returns the number of moves added by splitting double moves in a
+    /// solution.
+    pub fn extra_moves(&self, solution: &Solution) -> usize {
+        self.split_gaps
+            .iter()
+            .filter(|&&gap| !solution[gap].is_none())
+            .count()
+    }
+
+    /// Returns the number of ETM added by a solution under `cost_model`,
+    /// including moves added by splitting double moves.
+    pub fn cost(&self, cost_model: &CostModel, solution: &Solution) -> usize {
+        solution_cost(cost_model, solution) + self.extra_moves(solution)
+    }
+
+    /// Returns the estimated time to execute the algorithm with the reorients
+    /// from a solution under `cost_model`, in milliseconds.
+    pub fn time_ms(&self, cost_model: &CostModel, solution: &Solution) -> u64 {
+        let moves = (self.original_len() + self.extra_moves(solution)) as f64;
+        let reorients: f64 = solution.iter().map(|&r| cost_model.reorient_time(r)).sum();
+        (moves * cost_model.move_time() + reorients).round() as u64
+    }
+
+    /// Returns the value of a solution under an objective and `cost_model`.
+    /// Lower is better.
+    pub fn score(&self, cost_model: &CostModel, objective: Objective, solution: &Solution) -> u64 {
+        match objective {
+            Objective::Etm => self.cost(cost_model, solution) as u64,
+            Objective::Time => self.time_ms(cost_model, solution),
+        }
+    }
+
+    /// Formats the algorithm as the RKT moves that the executor performs,
+    /// with each reorient expanded into whole-cube rotations by
+    /// `cost_model`.
+    pub fn display_rkt(&self, cost_model: &CostModel, solution: &Solution) -> String {
+        let moves: Vec<String> = self
+            .rkt_moves(cost_model, solution)
+            .into_iter()
+            .map(display_move)
+            .collect();
+        moves.join(" ")
+    }
+
+    /// Returns the RKT moves that the executor performs, with each reorient
+    /// expanded into whole-cube rotations by `cost_model`.
+    pub fn rkt_moves(&self, cost_model: &CostModel, solution: &Solution) -> Vec<Move> {
+        let mut moves = cost_model.expansion(self.start);
+        let mut i = 0;
+        while i < self.moves.len() {
+            if i > 0 {
+                moves.extend(cost_model.expansion(solution[i - 1]));
+            }
+            if self.split_gaps.contains(&i) && solution[i].is_none() {
+                moves.push(self.moves[i].with_variant(MoveVariant::Double));
+                i += 2;
+            } else {
+                moves.push(self.moves[i]);
+                i += 1;
+            }
+        }
+        moves
+    }
+
+    /// Returns a short hash of the algorithm with the reorients from a
+    /// solution, such as `a3f9c1`, for referring to the solution. It is the
+    /// same on every run, in any notation, with any macros, and whether or
+    /// not double moves are split, as long as the reorients are the same.
+    pub fn solution_id(&self, solution: &Solution) -> String {
+        let mut tokens = vec![];
+        if !self.start.is_none() {
+            tokens.push(self.start.xyz_name().to_string());
+        }
+        let mut i = 0;
+        while i < self.moves.len() {
+            if i > 0 && !solution[i - 1].is_none() {
+                tokens.push(solution[i - 1].xyz_name().to_string());
+            }
+            if self.split_gaps.contains(&i) && solution[i].is_none() {
+                tokens.push(display_move(
+                    self.moves[i].with_variant(MoveVariant::Double),
+                ));
+                i += 2;
+            } else {
+                tokens.push(display_move(self.moves[i]));
+                i += 1;
+            }
+        }
+        // The top 24 bits, which are plenty to tell apart the solutions of
+        // one algorithm.
+        format!("{:06x}", meta::hash(&tokens.join(" ")) >> 40)
+    }
+
+    /// Formats the algorithm with the reorients from a solution inserted
+    /// between its moves, in XYZ notation. Split double moves without a
+    /// reorient between their halves are merged back together. See
+    /// [`RktOptimizer::display()`] for other notations.
+    pub fn display(&self, solution: &Solution) -> String {
+        self.display_with(solution, |r| r.to_string())
+    }
+
+    /// Formats the algorithm like [`Alg::display()`], writing each reorient
+    /// with `name`, which returns the same padding as [`Reorient`]'s
+    /// `Display` implementation.
+    pub fn display_with(&self, solution: &Solution, name: impl Fn(Reorient) -> String) -> String {
+        let mut return_string = String::new();
+        if !self.start.is_none() {
+            return_string += name(self.start).trim_start();
+        }
+        let display_chunks = |return_string: &mut String, i: usize| {
+            for chunk in self.chunks.iter().filter(|c| c.start == i) {
+                if !return_string.is_empty() && !return_string.ends_with(' ') {
+                    *return_string += " ";
+                }
+                *return_string += &format!("[{}] ", chunk.label);
+            }
+        };
+        let display_anchors = |return_string: &mut String, i: usize| {
+            for anchor in self.anchors.iter().filter(|a| a.position == i) {
+                if !return_string.is_empty() && !return_string.ends_with(' ') {
+                    *return_string += " ";
+                }
+                *return_string += &format!("@{}", anchor.name);
+            }
+        };
+        let mut i = 0;
+        while i < self.moves.len() {
+            display_anchors(&mut return_string, i);
+            if i > 0 {
+                return_string += &name(solution[i - 1]);
+            } else if !return_string.is_empty() && !return_string.ends_with(' ') {
+                return_string += " ";
+            }
+            display_chunks(&mut return_string, i);
+            if self.split_gaps.contains(&i) && solution[i].is_none() {
+                return_string += &display_move(self.moves[i].with_variant(MoveVariant::Double));
+                i += 2;
+            } else {
+                return_string += &display_move(self.moves[i]);
+                i += 1;
+            }
+        }
+        display_anchors(&mut return_string, self.moves.len());
+        display_chunks(&mut return_string, self.moves.len());
+        let mut return_string = return_string.trim_end().to_string();
+
+        let mut splits: Vec<usize> = self
+            .split_gaps
+            .iter()
+            .copied()
+            .filter(|&gap| !solution[gap].is_none())
+            .collect();
+        splits.sort_unstable();
+        if !splits.is_empty() {
+            let descriptions: Vec<String> = splits
+                .into_iter()
+                .map(|gap| {
+                    let double = display_move(self.moves[gap].with_variant(MoveVariant::Double));
+                    let half = display_move(self.moves[gap]);
+                    let original_index = gap - self.split_gaps.iter().filter(|&&g| g < gap).count();
+                    format!("{double} (move {}) into {half} {half}", original_index + 1)
+                })
+                .collect();
+            return_string += &format!("  (splits {})", descriptions.join(", "));
+        }
+
+        return_string
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Reorient {
+    #[default]
+    None = 0,
+
+    R = 1,
+    L = 2,
+    U = 3,
+    D = 4,
+    F = 5,
+    B = 6,
+
+    R2 = 7,
+    U2 = 8,
+    F2 = 9,
+
+    UF = 10,
+    UR = 11,
+    FR = 12,
+    DF = 13,
+    UL = 14,
+    BR = 15,
+
+    UFR = 16,
+    DBL = 17,
+    UFL = 18,
+    DBR = 19,
+    DFR = 20,
+    UBL = 21,
+    UBR = 22,
+    DFL = 23,
+}
+/// Writes the reorient in XYZ notation with a space on each side, or a
+/// single space for no reorient, so that reorients can be written between the
+/// moves of an algorithm. See [`RktOptimizer::reorient_name()`] for other
+/// notations.
+impl fmt::Display for Reorient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_none() {
+            write!(f, " ")
+        } else {
+            write!(f, " {} ", self.xyz_name())
+        }
+    }
+}
+impl FromStr for Reorient {
+    type Err = String;
+
+    /// Parses a reorient in XYZ notation (with or without the leading `O`),
+    /// sticker notation, or hypercubing notation, with the standard cell
+    /// names. [`CostModel::parse_reorient()`] also accepts configured ones.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Reorient::ALL
+            .iter()
+            .copied()
+            .find(|r| {
+                s == r.xyz_name()
+                    || Some(s) == r.xyz_name().strip_prefix('O')
+                    || s == r.sticker_name()
+            })
+            .or_else(|| Reorient::from_hypercubing_name(s))
+            .ok_or_else(|| format!("unknown reorient {s:?}"))
+    }
+}
+impl Reorient {
+    pub const ALL: &'static [Self] = &[
+        Self::None,
+        Self::R,
+        Self::L,
+        Self::U,
+        Self::D,
+        Self::F,
+        Self::B,
+        Self::R2,
+        Self::U2,
+        Self::F2,
+        Self::UF,
+        Self::UR,
+        Self::FR,
+        Self::DF,
+        Self::UL,
+        Self::BR,
+        Self::UFR,
+        Self::DBL,
+        Self::UFL,
+        Self::DBR,
+        Self::DFR,
+        Self::UBL,
+        Self::UBR,
+        Self::DFL,
+    ];
+
+    pub fn equivalent_rkt_moves(self) -> &'static [Move] {
+        use Move::{X, Y, Z};
+        use MoveVariant::*;
+        use Reorient::*;
+
+        match self {
+            None => &[],
+
+            R => &[X(Standard)],
+            L => &[X(Inverse)],
+            U => &[Y(Standard)],
+            D => &[Y(Inverse)],
+            F => &[Z(Standard)],
+            B => &[Z(Inverse)],
+
+            R2 => &[X(Double)],
+            U2 => &[Y(Double)],
+            F2 => &[Z(Double)],
+
+            UF => &[X(Standard), Y(Double)],
+            UR => &[Z(Standard), X(Double)],
+            FR => &[Y(Standard), Z(Double)],
+            DF => &[X(Standard), Z(Double)],
+            UL => &[Z(Standard), Y(Double)],
+            BR => &[Y(Standard), X(Double)],
+
+            UFR => &[X(Standard), Y(Standard)],
+            DBL => &[Y(Inverse), X(Inverse)],
+            UFL => &[Z(Standard), Y(Standard)],
+            DBR => &[X(Standard), Y(Inverse)],
+            DFR => &[X(Standard), Z(Standard)],
+            UBL => &[Y(Standard), Z(Inverse)],
+            UBR => &[Y(Standard), X(Standard)],
+            DFL => &[Z(Standard), X(Inverse)],
+        }
+    }
+
+    /// Returns the name of the reorient in XYZ notation.
+    pub fn xyz_name(self) -> &'static str {
+        use Reorient::*;
+
+        match self {
+            None => "O",
+
+            R => "Ox",
+            L => "Ox'",
+            U => "Oy",
+            D => "Oy'",
+            F => "Oz",
+            B => "Oz'",
+
+            R2 => "Ox2",
+            U2 => "Oy2",
+            F2 => "Oz2",
+
+            UF => "Oxy2",
+            UR => "Ozx2",
+            FR => "Oyz2",
+            DF => "Oxz2",
+            UL => "Ozy2",
+            BR => "Oyx2",
+
+            UFR => "Oxy",
+            DBL => "Oy'x'",
+            UFL => "Ozy",
+            DBR => "Oxy'",
+            DFR => "Oxz",
+            UBL => "Oyz'",
+            UBR => "Oyx",
+            DFL => "Ozx'",
+        }
+    }
+
+    /// Returns the name of the whole-cube rotation that puts the cube in this
+    /// orientation, as written before an algorithm (e.g., `y` in `y: R U R'`).
+    pub fn pre_rotation_name(self) -> &'static str {
+        &self.xyz_name()[1..]
+    }
+
+    /// Returns the name of the reorient in sticker notation.
+    pub fn sticker_name(self) -> &'static str {
+        use Reorient::*;
+
+        match self {
+            None => "23I",
+
+            R => "23I:L",
+            L => "23I:R",
+            U => "23I:D",
+            D => "23I:U",
+            F => "23I:B",
+            B => "23I:F",
+
+            R2 => "23I:R2",
+            U2 => "23I:U2",
+            F2 => "23I:F2",
+
+            UF => "23I:UF",
+            UR => "23I:UR",
+            FR => "23I:FR",
+            DF => "23I:DF",
+            UL => "23I:UL",
+            BR => "23I:BR",
+
+            UFR => "23I:DBL",
+            DBL => "23I:UFR",
+            UFL => "23I:DBR",
+            DBR => "23I:UFL",
+            DFR => "23I:UBL",
+            UBL => "23I:DFR",
+            UBR => "23I:DFL",
+            DFL => "23I:UBR",
+        }
+    }
+
+    /// Returns the move that turns the same face as `mv` does when `mv` is
+    /// performed while the cube is in this orientation.
+    ///
+    /// Panics if `mv` isn't a face turn; see [`is_face_move()`].
+    pub fn physical_move(self, mv: Move) -> Move {
+        let rotated = FaceletCube::new(3).apply_moves(self.equivalent_rkt_moves());
+        let state = rotated
+            .apply_move(mv)
+            .apply_moves(self.inverse().equivalent_rkt_moves())
+            .state();
+        face_moves(ALL_SOLVER_FACES)
+            .into_iter()
+            .find(|&m| FaceletCube::new(3).apply_move(m).state() == state)
+            .unwrap_or_else(|| panic!("unsupported move {:?}", mv))
+    }
+
+    /// Returns the cube turned by [`Reorient::equivalent_rkt_moves()`],
+    /// which turns any cube the same way with [`StickerCube::then()`].
+    pub fn sticker_cube(self) -> &'static StickerCube {
+        &REORIENT_CUBES[self as usize]
+    }
+
+    /// Returns the table for turning cubes by this reorient with
+    /// [`StickerCube::apply()`].
+    pub fn move_table(self) -> &'static MoveTable {
+        &REORIENT_TABLES[self as usize]
+    }
+
+    /// Returns the orientation reached by doing `self` followed by `next`.
+    pub fn then(self, next: Self) -> Self {
+        COMPOSITION_TABLE[self as usize][next as usize]
+    }
+
+    pub fn is_none(self) -> bool {
+        self == Self::None
+    }
+
+    pub fn inverse(self) -> Self {
+        use Reorient::*;
+
+        match self {
+            R => L,
+            L => R,
+            U => D,
+            D => U,
+            F => B,
+            B => F,
+
+            UFR => DBL,
+            DBL => UFR,
+            UFL => DBR,
+            DBR => UFL,
+            DFR => UBL,
+            UBL => DFR,
+            UBR => DFL,
+            DFL => UBR,
+
+            // Everything else is its own inverse.
+            other => other,
+        }
+    }
+
+    /// Returns the reorient mirrored across the M slice, which turns `x` into
+    /// `x` and `y` and `z` into `y'` and `z'`.
+    pub fn mirror(self) -> Self {
+        use Reorient::*;
+
+        match self {
+            U => D,
+            D => U,
+            F => B,
+            B => F,
+
+            UR => UL,
+            UL => UR,
+            FR => BR,
+            BR => FR,
+
+            UFR => DBR,
+            DBR => UFR,
+            DBL => UFL,
+            UFL => DBL,
+            DFR => UBR,
+            UBR => DFR,
+            UBL => DFL,
+            DFL => UBL,
+
+            // Everything else is its own mirror.
+            other => other,
+        }
+    }
+
+    /// Returns whether `self` rotates around the same axis as `other` but in
+    /// the opposite direction, as in `Ox ... Ox'`.
+    pub fn reverses(self, other: Self) -> bool {
+        self != other && self == other.inverse()
+    }
+}
+
+/// Returns the moves that undo a sequence of moves.
+pub fn invert_moves(moves: &[Move]) -> Vec<Move> {
+    moves
+        .iter()
+        .rev()
+        .map(|mv| {
+            mv.with_variant(match mv.get_variant() {
+                MoveVariant::Standard => MoveVariant::Inverse,
+                MoveVariant::Double => MoveVariant::Double,
+                MoveVariant::Inverse => MoveVariant::Standard,
+            })
+        })
+        .collect()
+}
+
+pub fn display_move(mv: Move) -> String {
+    match mv {
+        Move::U(v) => "U".to_string() + display_move_variant(v),
+        Move::L(v) => "L".to_string() + display_move_variant(v),
+        Move::F(v) => "F".to_string() + display_move_variant(v),
+        Move::R(v) => "R".to_string() + display_move_variant(v),
+        Move::B(v) => "B".to_string() + display_move_variant(v),
+        Move::D(v) => "D".to_string() + display_move_variant(v),
+        Move::Uw(2, v) => "Uw".to_string() + display_move_variant(v),
+        Move::Lw(2, v) => "Lw".to_string() + display_move_variant(v),
+        Move::Fw(2, v) => "Fw".to_string() + display_move_variant(v),
+        Move::Rw(2, v) => "Rw".to_string() + display_move_variant(v),
+        Move::Bw(2, v) => "Bw".to_string() + display_move_variant(v),
+        Move::Dw(2, v) => "Dw".to_string() + display_move_variant(v),
+        Move::X(v) => "x".to_string() + display_move_variant(v),
+        Move::Y(v) => "y".to_string() + display_move_variant(v),
+        Move::Z(v) => "z".to_string() + display_move_variant(v),
+        _ => panic!("unsupported move {:?}", mv),
+    }
+}
+pub fn display_move_variant(v: MoveVariant) -> &'static str {
+    match v {
+        MoveVariant::Standard => "",
+        MoveVariant::Double => "2",
+        MoveVariant::Inverse => "'",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cubesim::{Move, MoveVariant};
+
+    use super::*;
+
+    const R: Move = Move::R(MoveVariant::Standard);
+    const R2: Move = Move::R(MoveVariant::Double);
+    const U: Move = Move::U(MoveVariant::Standard);
+    const U_PRIME: Move = Move::U(MoveVariant::Inverse);
+
+    #[test]
+    fn parse_reads_chunks_and_anchors() {
+        let alg = Alg::parse("[setup] R U @pair1 U' [insert=Oy] R2' @end").unwrap();
+        assert_eq!(alg.moves, [R, U, U_PRIME, R2]);
+        assert_eq!(
+            alg.chunks,
+            [
+                Chunk {
+                    label: "setup".to_string(),
+                    start: 0,
+                    orientation: None,
+                },
+                Chunk {
+                    label: "insert".to_string(),
+                    start: 3,
+                    orientation: Some(Reorient::U),
+                },
+            ],
+        );
+        assert_eq!(alg.anchor_at(1), Some("pair1"));
+        assert_eq!(alg.anchors[1].position, 4);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_tokens() {
+        for s in ["R [setup U", "R @ U", "R @a U @a", "R Q", "[x=Oq] R"] {
+            assert!(Alg::parse(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn parse_solved_reads_reorients_between_moves() {
+        let cost_model = CostModel::default();
+        let (alg, solution) = Alg::parse_solved("Oy R Ox U Ox' Oz U' # id", &cost_model).unwrap();
+        assert_eq!(alg.moves, [R, U, U_PRIME]);
+        assert_eq!(alg.start, Reorient::U);
+        assert_eq!(solution, [Reorient::R, Reorient::L.then(Reorient::F)]);
+
+        for s in ["", "Oy", "R Oy", "R Q"] {
+            assert!(Alg::parse_solved(s, &cost_model).is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn parse_solved_round_trips_display() {
+        let cost_model = CostModel::default();
+        let mut alg = Alg::parse("R U R2 U'").unwrap();
+        alg.start = Reorient::F;
+        let solution = vec![Reorient::None, Reorient::UF, Reorient::None];
+        let (parsed, parsed_solution) =
+            Alg::parse_solved(&alg.display(&solution), &cost_model).unwrap();
+        assert_eq!(parsed.moves, alg.moves);
+        assert_eq!(parsed.start, alg.start);
+        assert_eq!(parsed_solution, solution);
+    }
+
+    #[test]
+    fn split_doubles_moves_gaps_after_the_split() {
+        let mut alg = Alg::parse("[a] R R2 @mid U [b] U'").unwrap();
+        alg.window = Some(1..3);
+        alg.split_doubles();
+        assert_eq!(alg.moves, [R, R, R, U, U_PRIME]);
+        assert_eq!(alg.split_gaps, HashSet::from([1]));
+        assert_eq!(
+            alg.chunks.iter().map(|c| c.start).collect::<Vec<_>>(),
+            [0, 4],
+        );
+        assert_eq!(alg.anchor_at(2), Some("mid"));
+        assert_eq!(alg.window, Some(1..4));
+        assert_eq!(alg.extra_moves(&vec![Reorient::R; 4]), 1);
+        assert_eq!(alg.extra_moves(&vec![Reorient::None; 4]), 0);
+    }
+}
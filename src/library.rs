@@ -0,0 +1,333 @@
+//! A personal library of chosen solutions, kept as one JSON object per line.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::meta;
+use crate::rank::Ranking;
+use crate::{Alg, CostModel, RktOptimizer};
+
+/// Optimize an algorithm and add one of its solutions to the personal library.
+#[derive(clap::Args, Debug, Clone)]
+pub struct SaveArgs {
+    /// Name of the case, such as `Tperm`.
+    #[clap(long)]
+    case: String,
+
+    /// Which solution to save: its number, counting from 1 in the order they
+    /// are printed, or its id, such as `a3f9c1`.
+    #[clap(long, default_value = "1")]
+    solution: String,
+
+    /// Library file. Defaults to `$XDG_DATA_HOME/rocket/library.jsonl` or
+    /// `~/.local/share/rocket/library.jsonl`.
+    #[clap(long)]
+    library: Option<PathBuf>,
+
+    /// Rotationless algorithm.
+    #[clap(required = true, allow_hyphen_values = true)]
+    alg: Vec<String>,
+}
+
+/// Query the personal library of saved solutions.
+#[derive(clap::Args, Debug, Clone)]
+pub struct LibraryArgs {
+    /// Library file. Defaults to `$XDG_DATA_HOME/rocket/library.jsonl` or
+    /// `~/.local/share/rocket/library.jsonl`.
+    #[clap(long)]
+    library: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    command: LibraryCommand,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+enum LibraryCommand {
+    /// Print every saved solution.
+    List,
+    /// Print the saved solutions whose case or algorithm contains some text,
+    /// ignoring case.
+    Search { query: String },
+    /// Solve every saved algorithm again and report the saved solutions that
+    /// are no longer the cheapest, such as after switching executors.
+    Reoptimize {
+        /// Executor profile to solve with: a config file, or the name of one
+        /// in `$XDG_CONFIG_HOME/rocket/profiles` or
+        /// `~/.config/rocket/profiles`. Defaults to the current config.
+        #[clap(long)]
+        profile: Option<String>,
+    },
+}
+impl LibraryArgs {
+    /// Returns whether the command searches, and so needs the pruning table
+    /// and the search settings.
+    pub fn searches(&self) -> bool {
+        matches!(self.command, LibraryCommand::Reoptimize { .. })
+    }
+}
+
+/// Saves a solution of `args.alg` found by `optimizer` to the library, along
+/// with the date and a [`meta::record()`] of `settings`, which describe the
+/// search settings and the executor.
+pub fn save(
+    args: &SaveArgs,
+    optimizer: &RktOptimizer,
+    ranking: &Ranking,
+    max_depth: usize,
+    settings: Value,
+) -> Result<(), String> {
+    let path = library_path(args.library.as_deref())?;
+    let input = args.alg.join(" ");
+    let alg = Alg::parse(&input)?;
+    let start = Instant::now();
+    let result = optimizer.search_until(&alg, max_depth, false, None, &mut None, None);
+    let elapsed = start.elapsed();
+    let (reorient_count, mut solutions) = (result.reorient_count, result.solutions);
+    if solutions.is_empty() {
+        return Err(format!("no solutions with at most {max_depth} reorients"));
+    }
+    let cost_model = optimizer.cost_model();
+    ranking.sort(cost_model, &alg, &mut solutions);
+    let solution = match args.solution.parse::<usize>() {
+        Ok(n) => n
+            .checked_sub(1)
+            .and_then(|i| solutions.get(i))
+            .ok_or_else(|| format!("asked for solution {n}, but there are {}", solutions.len()))?,
+        Err(_) => {
+            let id = args.solution.trim_start_matches('#');
+            solutions
+                .iter()
+                .find(|s| alg.solution_id(s) == id)
+                .ok_or_else(|| format!("no solution has the id {id}"))?
+        }
+    };
+
+    // Only the cheapest solutions of a complete search are optimal.
+    let min_cost = solutions.iter().map(|s| alg.cost(cost_model, s)).min();
+    let optimal = result.optimal && min_cost == Some(alg.cost(cost_model, solution));
+
+    let entry = json!({
+        "case": args.case,
+        "id": alg.solution_id(solution),
+        "alg": optimizer.display_pre_rotated(&alg, solution),
+        "input": input.trim(),
+        "etm": alg.original_len() + alg.cost(cost_model, solution),
+        "reorient_count": reorient_count,
+        "optimal": optimal,
+        "date": meta::today(),
+        "meta": meta::record(&settings, cost_model, Some(elapsed), optimizer.deterministic()),
+    });
+    let append = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        writeln!(file, "{entry}")
+    };
+    append().map_err(|e| format!("error writing {}: {e}", path.display()))?;
+    println!(
+        "Saved {}: {}  #{} to {}",
+        args.case,
+        optimizer.display_pre_rotated(&alg, solution),
+        alg.solution_id(solution),
+        path.display(),
+    );
+    Ok(())
+}
+
+/// Runs a library command that doesn't search, parsing saved algorithms with
+/// the macros of `cost_model`. Commands that search go through
+/// [`run_search()`].
+pub fn run(args: &LibraryArgs, cost_model: &CostModel) -> Result<(), String> {
+    let path = library_path(args.library.as_deref())?;
+    let entries = read(&path)?;
+    let matches: Vec<&Value> = match &args.command {
+        LibraryCommand::Reoptimize { .. } => unreachable!("reoptimizing searches"),
+        LibraryCommand::List => entries.iter().collect(),
+        LibraryCommand::Search { query } => {
+            let query = query.to_lowercase();
+            entries
+                .iter()
+                .filter(|entry| {
+                    ["case", "alg", "input"].iter().any(|field| {
+                        entry[field]
+                            .as_str()
+                            .is_some_and(|s| s.to_lowercase().contains(&query))
+                    }) || entry_id(cost_model, entry)
+                        .is_some_and(|id| id == query.trim_start_matches('#'))
+                })
+                .collect()
+        }
+    };
+    if matches.is_empty() {
+        match args.command {
+            LibraryCommand::Search { .. } => println!("No matching solutions."),
+            _ => println!("No saved solutions."),
+        }
+    }
+    for entry in matches {
+        // Entries saved before optimality was recorded don't say.
+        let confidence = match entry["optimal"].as_bool() {
+            Some(false) => ", not proven optimal",
+            _ => "",
+        };
+        let id = entry_id(cost_model, entry).map_or(String::new(), |id| format!("  #{id}"));
+        println!(
+            "{}: {}{id} ({} ETM{confidence}, saved {})",
+            entry["case"].as_str().unwrap_or("?"),
+            entry["alg"].as_str().unwrap_or("?"),
+            entry["etm"],
+            entry["date"].as_str().unwrap_or("?"),
+        );
+    }
+    Ok(())
+}
+
+/// Runs a library command that searches, with `optimizer` and up to
+/// `max_depth` reorients.
+pub fn run_search(
+    args: &LibraryArgs,
+    optimizer: &RktOptimizer,
+    max_depth: usize,
+) -> Result<(), String> {
+    let path = library_path(args.library.as_deref())?;
+    let entries = read(&path)?;
+    match &args.command {
+        LibraryCommand::Reoptimize { profile } => {
+            reoptimize(&entries, optimizer, profile.as_deref(), max_depth)
+        }
+        LibraryCommand::List | LibraryCommand::Search { .. } => run(args, optimizer.cost_model()),
+    }
+}
+
+/// Returns the [`Alg::solution_id()`] of the solution in a library entry.
+/// Entries saved before ids were recorded get theirs from the saved
+/// algorithm, if it still parses with the macros of `cost_model`.
+fn entry_id(cost_model: &CostModel, entry: &Value) -> Option<String> {
+    if let Some(id) = entry["id"].as_str() {
+        return Some(id.to_string());
+    }
+    let (alg, solution) = Alg::parse_solved(entry["alg"].as_str()?, cost_model).ok()?;
+    Some(alg.solution_id(&solution))
+}
+
+/// Solves every entry again with `optimizer`, using the cost model of the
+/// executor profile `profile` if it is given, and reports the entries that
+/// have cheaper solutions now.
+fn reoptimize(
+    entries: &[Value],
+    optimizer: &RktOptimizer,
+    profile: Option<&str>,
+    max_depth: usize,
+) -> Result<(), String> {
+    // Saved solutions may use macros of the current config, so they are read
+    // and costed with it rather than the profile.
+    let current = optimizer.cost_model();
+    let saved = entries
+        .iter()
+        .map(|entry| {
+            let case = entry["case"].as_str().unwrap_or("?");
+            let input = entry["input"].as_str().unwrap_or("");
+            let stored = entry["alg"].as_str().unwrap_or("");
+            let (stored_alg, stored_solution) =
+                Alg::parse_solved(stored, current).map_err(|e| format!("{case}: {e}"))?;
+            let alg = Alg::parse_with(input, current).map_err(|e| format!("{case}: {e}"))?;
+            Ok((case, alg, stored_alg, stored_solution))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let optimizer = match profile {
+        Some(profile) => {
+            let path = profile_path(profile)?;
+            let mut cost_model = CostModel::default();
+            std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| cost_model.load_config(&contents))
+                .map_err(|e| format!("error loading profile {}: {e}", path.display()))?;
+            optimizer.clone().with_cost_model(cost_model)
+        }
+        None => optimizer.clone(),
+    };
+    let cost_model = optimizer.cost_model();
+
+    let mut stale = 0;
+    for (case, alg, stored_alg, stored_solution) in &saved {
+        let stored_etm = stored_alg.original_len() + stored_alg.cost(cost_model, stored_solution);
+        let (_, solutions) = optimizer.search(alg, max_depth, false, None, &mut None);
+        let Some(best) = solutions.iter().min_by_key(|s| alg.cost(cost_model, s)) else {
+            println!("{case}: no solutions with at most {max_depth} reorients");
+            continue;
+        };
+        let best_etm = alg.original_len() + alg.cost(cost_model, best);
+        if best_etm < stored_etm {
+            stale += 1;
+            println!(
+                "{case}: {} ({stored_etm} ETM) -> {} ({best_etm} ETM)",
+                optimizer.display_pre_rotated(stored_alg, stored_solution),
+                optimizer.display_pre_rotated(alg, best),
+            );
+        }
+    }
+    println!(
+        "{stale} of {} saved solutions are no longer optimal.",
+        saved.len(),
+    );
+    Ok(())
+}
+
+/// Returns the config file for an executor profile, which is either a path
+/// or the name of a file in the profiles directory.
+fn profile_path(profile: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(profile);
+    if path.exists() {
+        return Ok(path);
+    }
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or("can't find the home directory")?;
+    let path = dir.join("rocket").join("profiles").join(profile);
+    match path.exists() {
+        true => Ok(path),
+        false => Err(format!(
+            "no profile named {profile:?} in {}",
+            path.display()
+        )),
+    }
+}
+
+/// Returns `path`, or the default library file if it is `None`.
+fn library_path(path: Option<&Path>) -> Result<PathBuf, String> {
+    if let Some(path) = path {
+        return Ok(path.to_path_buf());
+    }
+    let dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })
+        .ok_or("can't find the home directory; use --library")?;
+    Ok(dir.join("rocket").join("library.jsonl"))
+}
+
+/// Reads every entry of a library file, which is empty if it doesn't exist.
+fn read(path: &Path) -> Result<Vec<Value>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("error reading {}: {e}", path.display())),
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| format!("{}:{}: {e}", path.display(), i + 1))
+        })
+        .collect()
+}
@@ -1,53 +1,44 @@
 use clap::Parser;
-use cubesim::{parse_scramble, Cube, FaceletCube, Move, MoveVariant, PruningTable, Solver};
-use lazy_static::lazy_static;
-use std::collections::HashSet;
-use std::fmt;
-use std::io::Write;
-use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering::SeqCst};
-
-static PRUNING_TABLE_DEPTH: AtomicI32 = AtomicI32::new(0);
-static STICKER_NOTATION: AtomicBool = AtomicBool::new(false);
-static CHEAP_MOVES: AtomicU32 = AtomicU32::new(0);
-
-lazy_static! {
-    static ref NAIVE_SOLVER: Solver = make_naive_solver();
-}
-
-fn make_naive_solver() -> Solver {
-    use Move::{B, D, F, L, R, U};
-    use MoveVariant::*;
-
-    let faces = [R, L, U, D, B, F];
-    let variants = [Standard, Double, Inverse];
-
-    let move_set: Vec<Move> = faces
-        .into_iter()
-        .flat_map(|f| variants.into_iter().map(f))
-        .collect();
-
-    let initial_states: Vec<FaceletCube> = Reorient::ALL
-        .iter()
-        .map(|r| FaceletCube::new(3).apply_moves(r.equivalent_rkt_moves()))
-        .collect();
-
-    let pruning_table =
-        PruningTable::new(&initial_states, PRUNING_TABLE_DEPTH.load(SeqCst), &move_set);
-
-    Solver::new(move_set, pruning_table)
-}
+use cubesim::Move;
+use rocket::cost::CostModel;
+use rocket::notation::Notation;
+use rocket::plan::Strategy;
+#[cfg(feature = "plugins")]
+use rocket::plugin;
+use rocket::rank::{self, Objective, Ranking};
+use rocket::tree::SearchTree;
+use rocket::{
+    affinity, anneal, beam, breakdown, calibrate, cancel, choices, compare, csv, diagnose, diff,
+    distinct_reorients, drill, heatmap, improve, json, keymap, library, mask, meet, merge, meta,
+    plan, remote, selftest, stability, stats, suggest, symmetry, table, table_info, threads,
+    update, usage, variants, viz, warm, Alg, Reorient, RktOptimizer, SearchResult, Solution,
+};
+use std::borrow::Cow;
+use std::io::{BufRead, IsTerminal, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[clap(author, version, about, long_about = None)]
 pub struct Args {
-    /// Depth of pruning table (must be at least 2).
-    #[clap(short, long, default_value_t = 2)]
-    depth: u8,
+    /// Depth of pruning table (must be at least 2), or `auto` to pick one
+    /// from the length of the algorithms, `--max-depth`, and the free memory.
+    #[clap(short, long, default_value = "2")]
+    depth: String,
 
     /// Use sticker notation instead of XYZ notation for reorientations.
-    #[clap(short, long)]
+    /// Shorthand for `--notation sticker`.
+    #[clap(short, long, conflicts_with = "notation")]
     stickers: bool,
 
+    /// Notation for reorientations.
+    #[clap(long, arg_enum, default_value = "xyz")]
+    notation: Notation,
+
+    /// Size of the 4D puzzle, for `--notation 4d`.
+    #[clap(long, default_value_t = 3, possible_values = ["2", "3"])]
+    puzzle_size: u8,
+
     /// Output all STM-optimal algorithms instead of just the ETM-optimal
     /// subset.
     #[clap(short, long)]
@@ -61,335 +52,1962 @@ pub struct Args {
     /// Maximum depth to search.
     #[clap(short, long, default_value_t = 3)]
     max_depth: usize,
+
+    /// After finding solutions, keep searching with more reorients, up to
+    /// `--max-depth`, for as long as they could still add fewer ETM. This
+    /// finds the cheapest solutions even when they need more reorients than
+    /// the fewest.
+    #[clap(long, conflicts_with_all = &["anneal", "beam-width"])]
+    optimal_etm: bool,
+
+    /// What to minimize: added ETM, or estimated execution time using the
+    /// `tps` and `time` settings from the config file.
+    #[clap(long, arg_enum, default_value = "etm")]
+    objective: Objective,
+
+    /// Among ETM-optimal algorithms, output only those that use the fewest
+    /// distinct reorients.
+    #[clap(short = 'u', long)]
+    fewest_distinct: bool,
+
+    /// Output at most this many solutions with each cost, and say how many
+    /// more there are.
+    #[clap(long, value_name = "N", conflicts_with_all = &["choices", "group"])]
+    max_solutions: Option<usize>,
+
+    /// Prefer reorients that were already used by earlier algorithms, so that
+    /// a whole alg sheet converges on a small set of reorients.
+    #[clap(long)]
+    consistent: bool,
+
+    /// Rank solutions lower when consecutive reorients rotate around the same
+    /// axis in opposite directions.
+    #[clap(long)]
+    penalize_alternation: bool,
+
+    /// File assigning a comfort score to each reorient, one `<reorient>
+    /// <score>` pair per line. Higher scores are more comfortable. Scores are
+    /// only used to order solutions that are otherwise equally good.
+    #[clap(long)]
+    comfort: Option<PathBuf>,
+
+    /// Write the explored search tree of each query to a Graphviz file.
+    #[clap(long)]
+    dump_tree: Option<PathBuf>,
+
+    /// Maximum number of nodes to record with `--dump-tree`.
+    #[clap(long, default_value_t = 10_000)]
+    dump_tree_nodes: usize,
+
+    /// After each search, compare the pruning table's lower bounds against the
+    /// true distance from solved along the solutions that were found.
+    #[clap(long)]
+    diagnose_heuristic: bool,
+
+    /// Maximum true distance to search for with `--diagnose-heuristic`.
+    #[clap(long, default_value_t = 6)]
+    diagnose_depth: usize,
+
+    /// Print one solution per family of solutions whose reorient patterns are
+    /// related by mirroring or inversion, along with the size of the family.
+    #[clap(short, long)]
+    group: bool,
+
+    /// Print only the first of each set of solutions that differ only in a
+    /// reorient right after the first move that the first move's symmetry
+    /// makes interchangeable, such as `R Ox ...` and `R Ox' ...`.
+    #[clap(long)]
+    collapse_symmetric: bool,
+
+    /// Print the solutions as patterns with the reorients that can be
+    /// chosen independently at each gap, such as `R U {Ox|Oy2} R' U'`,
+    /// instead of one line per solution.
+    #[clap(long, conflicts_with = "group")]
+    choices: bool,
+
+    /// Solve from every starting orientation and report which one is
+    /// cheapest.
+    #[clap(short, long)]
+    neutral: bool,
+
+    /// Allow splitting a double move into two quarter turns to insert a
+    /// reorient between them, when that saves more than the extra move costs.
+    #[clap(long)]
+    split_doubles: bool,
+
+    /// After solving each algorithm, print a grid of which reorients are part
+    /// of some solution at each gap, and how many of the solutions use them
+    /// there.
+    #[clap(long, conflicts_with_all = &["anneal", "csv", "dry-run", "json"])]
+    heatmap: bool,
+
+    /// Before solving each algorithm, print its length in several metrics
+    /// and how often it turns each face.
+    #[clap(long)]
+    breakdown: bool,
+
+    /// After solving each algorithm, look for an algorithm for the same case
+    /// that is cheaper in total, including its moves. Candidates come from a
+    /// bundled PLL library or from `--candidates`.
+    #[clap(long)]
+    suggest: bool,
+
+    /// After solving each algorithm, rank its solutions again under this many
+    /// random perturbations of the reorient costs and report how often each
+    /// one is optimal.
+    #[clap(long)]
+    stability: Option<usize>,
+
+    /// Largest relative change to each reorient cost for `--stability`.
+    #[clap(long, default_value_t = 0.2)]
+    perturbation: f64,
+
+    /// File of candidate algorithms for `--suggest`, one `<case>:
+    /// <algorithm>` per line.
+    #[clap(long, requires = "suggest")]
+    candidates: Option<PathBuf>,
+
+    /// Print the planned search iterations and an estimate of how long each
+    /// would take, instead of searching.
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Ask before starting a search iteration that is projected to take
+    /// longer than this many seconds. If the pruning table barely prunes the
+    /// algorithm, offer to deepen it first.
+    #[clap(long, default_value_t = 30.0)]
+    confirm_after: f64,
+
+    /// Treat the algorithms as consecutive steps of one solve: each one starts
+    /// in the orientation that the previous one's best solution ended in.
+    #[clap(long, conflicts_with = "neutral")]
+    chain: bool,
+
+    /// Pieces whose final position doesn't matter, such as `U-edges` for an
+    /// algorithm that only needs to solve the last-layer corners. Pieces are
+    /// named by their faces (`UF`, `UFR`, or `U` for a center) or in groups
+    /// (`<face>-edges`, `<face>-corners`, or `<face>-layer`).
+    #[clap(long)]
+    mask: Option<String>,
+
+    /// Accept the cube as solved when one turn of the U layer would solve
+    /// it, for last-layer algorithms whose AUF is decided when they are
+    /// executed.
+    #[clap(long)]
+    up_to_auf: bool,
+
+    /// Build the pruning table from turns of only these faces, such as `RUF`
+    /// for ⟨R, U, F⟩ algorithms. The table is more precise for algorithms
+    /// that stay in the subgroup, but solutions in which reorients move the
+    /// rest of the algorithm onto other faces may be missed.
+    #[clap(long)]
+    solver_moves: Option<String>,
+
+    /// Only place reorients between the given moves, such as `5..12` to
+    /// place them anywhere after move 5 and before move 12, keeping the rest
+    /// of the algorithm free of reorients. Moves are numbered from 1.
+    #[clap(long)]
+    window: Option<String>,
+
+    /// Instead of searching exhaustively, spend this many seconds on
+    /// simulated annealing over reorient placements. This is meant for
+    /// algorithms, such as whole reconstructions, that are too long to search.
+    /// With `--strategy auto`, this is how long to anneal for when it picks
+    /// annealing.
+    #[clap(long)]
+    anneal: Option<f64>,
+
+    /// How to look for solutions. `auto` picks one for each algorithm from
+    /// its length and how quickly the search grows. Defaults to
+    /// `transposition`, or `anneal` with `--anneal`, or `beam` with
+    /// `--beam-width`.
+    #[clap(long, arg_enum)]
+    strategy: Option<Strategy>,
+
+    /// Instead of searching exhaustively, place reorients one gap at a time,
+    /// keeping this many of the most promising placements at each gap. Wider
+    /// beams are slower but find cheaper solutions more often. With
+    /// `--strategy beam`, this defaults to 1000.
+    #[clap(long, value_name = "K", conflicts_with_all = &["anneal", "dump-tree", "neutral"])]
+    beam_width: Option<usize>,
+
+    /// Dynamic library providing reorient costs or a search heuristic. See
+    /// `src/plugin.rs` for the interface.
+    #[cfg(feature = "plugins")]
+    #[clap(long)]
+    plugin: Option<PathBuf>,
+
+    /// Never ask before starting long search iterations.
+    #[clap(short, long)]
+    yes: bool,
+
+    /// Number of threads to search with. Defaults to the number of physical
+    /// cores.
+    #[clap(short = 'j', long)]
+    threads: Option<usize>,
+
+    /// Make the output the same on every run, regardless of the number of
+    /// threads: leave timings out of progress messages and use a fixed seed
+    /// for `--stability`.
+    #[clap(long, conflicts_with = "anneal")]
+    deterministic: bool,
+
+    /// Run at low priority so that long searches don't slow down everything
+    /// else on the machine.
+    #[clap(long)]
+    nice: bool,
+
+    /// Write a non-default starting orientation as a pre-rotation of the
+    /// whole algorithm, such as `y: R U R' U'`, instead of as a leading
+    /// reorient.
+    #[clap(long)]
+    pre_rotation: bool,
+
+    /// Also print each solution as the RKT moves to perform, with reorients
+    /// expanded into whole-cube rotations.
+    #[clap(long)]
+    rkt: bool,
+
+    /// Config file describing the executor: reorient macros and how each
+    /// reorient is performed. Defaults to `$XDG_CONFIG_HOME/rocket/config` or
+    /// `~/.config/rocket/config`, if it exists.
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// Directory to save pruning tables in, so that they are only built
+    /// once. Defaults to `$XDG_CACHE_HOME/rocket` or `~/.cache/rocket`.
+    #[clap(long)]
+    table_cache: Option<PathBuf>,
+
+    /// Most memory to use for building the pruning table, such as `2G` or
+    /// `512M`. If the requested depth would take more, the table stops at a
+    /// smaller depth.
+    #[clap(long)]
+    max_memory: Option<String>,
+
+    /// Also bound searches with pattern databases of the corners and of each
+    /// half of the edges, which prune far more than a shallow pruning table
+    /// for long algorithms. They take about 80 MiB and 20 seconds to build
+    /// the first time, and are saved in the table cache.
+    #[clap(long)]
+    pattern_databases: bool,
+
+    /// Always build the pruning table instead of loading a saved one.
+    #[clap(long, conflicts_with = "table-cache")]
+    no_table_cache: bool,
+
+    /// Optimize this algorithm, print the results, and exit instead of
+    /// prompting for algorithms. A link to alg.cubing.net or Twizzle works
+    /// too, and optimizes the algorithm in it.
+    #[clap(long, allow_hyphen_values = true)]
+    alg: Option<String>,
+
+    /// Print the results for each algorithm as one line of JSON instead of
+    /// text.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "anneal", "breakdown", "chain", "choices", "dry-run", "dump-tree", "group",
+            "stability", "suggest",
+        ],
+    )]
+    json: bool,
+
+    /// Print one CSV row per solution instead of text, after a header row.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "anneal", "breakdown", "chain", "choices", "dry-run", "dump-tree", "group", "json",
+            "stability", "suggest",
+        ],
+    )]
+    csv: bool,
+
+    /// Optimize every algorithm in this file, or in stdin if it is `-`, or at
+    /// this URL, one per line, and exit. Blank lines and lines starting with
+    /// `#` are ignored, and a line can also be a link to alg.cubing.net or
+    /// Twizzle.
+    ///
+    /// Lines starting with `{` are JSON requests, with the algorithm as
+    /// `alg` and optionally any of `all`, `fewest_distinct`, `max_depth`,
+    /// `max_solutions`, `objective`, `per_alg_timeout`, `split_doubles`, and
+    /// `window`
+    /// to override those options for that algorithm. An `id` is copied to
+    /// the JSON results.
+    #[clap(long, conflicts_with = "alg")]
+    input: Option<PathBuf>,
+
+    /// Only optimize one share of the algorithms in the input file, such as
+    /// `2/8` for the second of every eight, so that several processes can
+    /// split a batch between them.
+    #[clap(long, requires = "input")]
+    shard: Option<String>,
+
+    /// Stop after this long in total, such as `30s` or `5m`. The search that
+    /// is running reports the solutions found so far, or how many reorients
+    /// it got to if there are none, and the rest of a batch is skipped.
+    #[clap(long, conflicts_with_all = &["anneal", "neutral"])]
+    timeout: Option<String>,
+
+    /// Stop searching each algorithm after this long, such as `30s` or `5m`,
+    /// and report the solutions found so far, or how many reorients the
+    /// search got to if there are none. In a batch, the search then moves on
+    /// to the next algorithm.
+    #[clap(long, conflicts_with_all = &["anneal", "neutral"])]
+    per_alg_timeout: Option<String>,
+
+    /// After optimizing every algorithm in the input file, print how often
+    /// each reorient appears in the chosen solutions and how much ETM it adds
+    /// in total. With `--json`, this is printed as one more line of JSON.
+    #[clap(long, requires = "input", conflicts_with_all = &["csv", "dry-run"])]
+    usage: bool,
+
+    /// After optimizing every algorithm in the input file, print up to this
+    /// many reorients that would save the most ETM across the batch if they
+    /// were cheap moves, such as ones to bind better keys for. With `--json`,
+    /// this is printed as one more line of JSON.
+    #[clap(
+        long,
+        value_name = "N",
+        requires = "input",
+        conflicts_with_all = &["csv", "dry-run"],
+    )]
+    suggest_cheap: Option<usize>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    Affinity(affinity::AffinityArgs),
+    Calibrate(calibrate::CalibrateArgs),
+    DiffResults(diff::DiffResultsArgs),
+    Drill(drill::DrillArgs),
+    Improve(improve::ImproveArgs),
+    ImportKeymap(keymap::ImportKeymapArgs),
+    Library(library::LibraryArgs),
+    Merge(merge::MergeArgs),
+    Save(library::SaveArgs),
+    Selftest(selftest::SelftestArgs),
+    SelfUpdate(update::SelfUpdateArgs),
+    Stats(stats::StatsArgs),
+    TableInfo(table_info::TableInfoArgs),
+    Variants(variants::VariantsArgs),
+    Viz(viz::VizArgs),
+    Warm(warm::WarmArgs),
+}
+
+impl Args {
+    /// Returns whether results are printed for programs instead of people,
+    /// without any progress messages.
+    fn machine_readable(&self) -> bool {
+        self.json || self.csv
+    }
+
+    /// Returns the strategy given with `--strategy` or implied by
+    /// `--anneal` or `--beam-width`.
+    fn strategy(&self) -> Strategy {
+        match (self.strategy, self.anneal, self.beam_width) {
+            (Some(strategy), _, _) => strategy,
+            (None, Some(_), _) => Strategy::Anneal,
+            (None, None, Some(_)) => Strategy::Beam,
+            (None, None, None) => Strategy::Transposition,
+        }
+    }
+
+    /// Returns the settings that affect which solutions are found by
+    /// `optimizer`, for recording alongside them.
+    fn settings(&self, optimizer: &RktOptimizer) -> serde_json::Value {
+        serde_json::json!({
+            "depth": optimizer.depth(),
+            "max_depth": self.max_depth,
+            "objective": format!("{:?}", self.objective).to_lowercase(),
+            "mask": self.mask,
+            "solver_moves": self.solver_moves,
+            "up_to_auf": self.up_to_auf,
+            "optimal_etm": self.optimal_etm,
+            "executor": self.config.clone().or_else(default_config_path),
+        })
+    }
+
+    /// Returns whether annealing works with the other options, which are
+    /// the ones that `--anneal` conflicts with.
+    fn can_anneal(&self) -> bool {
+        !(self.breakdown
+            || self.chain
+            || self.choices
+            || self.dry_run
+            || self.dump_tree.is_some()
+            || self.group
+            || self.heatmap
+            || self.stability.is_some()
+            || self.suggest
+            || self.json
+            || self.csv
+            || self.deterministic
+            || self.neutral
+            || self.optimal_etm
+            || self.timeout.is_some()
+            || self.per_alg_timeout.is_some())
+    }
+}
+
+/// Returns the number of moves in the longest algorithm given with `--alg` or
+/// `--input`, if any. `downloaded` is the contents of `--input` if it is a
+/// URL.
+fn longest_alg(args: &Args, downloaded: Option<&str>) -> Option<usize> {
+    let contents = match (&args.alg, &args.input, downloaded) {
+        (Some(alg), _, _) => alg.clone(),
+        (None, _, Some(contents)) => contents.to_string(),
+        (None, Some(path), None) => std::fs::read_to_string(path).ok()?,
+        (None, None, None) => return None,
+    };
+    contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .map(|line| match line.trim_start().starts_with('{') {
+            true => parse_request(args, line).map_or(String::new(), |request| request.alg),
+            false => line.to_string(),
+        })
+        .map(|alg| match remote::alg_from_link(&alg) {
+            Some(linked) => linked.unwrap_or_default(),
+            None => alg,
+        })
+        .map(|line| {
+            line.split_whitespace()
+                .filter(|token| !token.starts_with(['[', '@']))
+                .count()
+        })
+        .max()
+}
+
+/// Parses an amount of memory like `512M` or `2G` into bytes. Suffixes are
+/// powers of 1024, and a number without a suffix is bytes.
+fn parse_memory(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let (number, scale) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let power = match c.to_ascii_uppercase() {
+                'K' => 1,
+                'M' => 2,
+                'G' => 3,
+                'T' => 4,
+                _ => return Err(format!("invalid suffix in {s:?}")),
+            };
+            (&s[..i], 1024_f64.powi(power))
+        }
+        _ => (s, 1.0),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid amount {s:?}"))?;
+    if number.is_nan() || number <= 0.0 {
+        return Err(format!("{s:?} is not positive"));
+    }
+    Ok((number * scale) as usize)
+}
+
+/// Parses a duration like `30s`, `5m`, or `1h` into seconds. A number
+/// without a suffix is seconds.
+fn parse_duration(s: &str) -> Result<f64, String> {
+    let s = s.trim();
+    let (number, scale) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let scale = match c.to_ascii_lowercase() {
+                's' => 1.0,
+                'm' => 60.0,
+                'h' => 3600.0,
+                _ => return Err(format!("invalid unit in {s:?}")),
+            };
+            (&s[..i], scale)
+        }
+        _ => (s, 1.0),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration {s:?}"))?;
+    if number.is_nan() || number <= 0.0 {
+        return Err(format!("{s:?} is not positive"));
+    }
+    Ok(number * scale)
+}
+
+/// Parses a shard like `2/8` into the index of the shard, starting from 0,
+/// and the number of shards.
+fn parse_shard(s: &str) -> Result<(usize, usize), String> {
+    let (index, count) = s
+        .split_once('/')
+        .ok_or_else(|| format!("expected `<shard>/<shard count>`, got {s:?}"))?;
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n >= 1)
+            .ok_or_else(|| format!("invalid number {n:?}"))
+    };
+    let (index, count) = (parse(index)?, parse(count)?);
+    if index > count {
+        return Err(format!("there are only {count} shards"));
+    }
+    Ok((index - 1, count))
+}
+
+/// Parses a window like `5..12` into the range of gaps after move 5 and
+/// before move 12.
+fn parse_window(s: &str) -> Result<Range<usize>, String> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| format!("expected `<first move>..<last move>`, got {s:?}"))?;
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n >= 1)
+            .ok_or_else(|| format!("invalid move number {n:?}"))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start >= end {
+        return Err(format!("{s:?} is empty"));
+    }
+    Ok(start - 1..end - 1)
+}
+
+/// Returns the default directory to save pruning tables in.
+fn default_cache_dir() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(dir.join("rocket"))
+}
+
+/// Returns the path of the default config file, whether or not it exists.
+fn config_location() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("rocket").join("config"))
+}
+
+/// Returns the path of the default config file, if it exists.
+fn default_config_path() -> Option<PathBuf> {
+    config_location().filter(|path| path.exists())
 }
 
 fn main() {
     let args = Args::parse();
 
-    let cheap_move_set: HashSet<_> = args
+    if let Some(Command::Viz(viz_args)) = &args.command {
+        if let Err(e) = viz::run(viz_args) {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+        return;
+    }
+    if let Some(Command::ImportKeymap(keymap_args)) = &args.command {
+        if let Err(e) = keymap::run(keymap_args) {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+        return;
+    }
+    if let Some(Command::DiffResults(diff_args)) = &args.command {
+        if let Err(e) = diff::run(diff_args) {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+        return;
+    }
+    if let Some(Command::Library(library_args)) = &args.command {
+        if !library_args.searches() {
+            // Saved algorithms are parsed without the config's macros.
+            if let Err(e) = library::run(library_args, &CostModel::default()) {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+    }
+    if let Some(Command::Merge(merge_args)) = &args.command {
+        if let Err(e) = merge::run(merge_args) {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+        return;
+    }
+    if let Some(Command::SelfUpdate(update_args)) = &args.command {
+        if let Err(e) = update::run(update_args) {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+        return;
+    }
+
+    let mut cost_model = CostModel::default();
+    if let Some(path) = args.config.clone().or_else(default_config_path) {
+        let result = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| cost_model.load_config(&contents));
+        if let Err(e) = result {
+            eprintln!("Error loading config {}: {}", path.display(), e);
+            std::process::exit(1)
+        }
+    }
+    // Cheap moves may use cell names from the config.
+    let cheap_moves: Result<Vec<Reorient>, _> = args
         .cheap_moves
-        .into_iter()
-        .map(|s| format!(" O{} ", s))
+        .iter()
+        .map(|s| cost_model.parse_reorient(s))
         .collect();
-    let mut cheap_move_set_mask = 0;
-    for (i, r) in Reorient::ALL.iter().enumerate() {
-        if cheap_move_set.contains(&r.to_string()) {
-            cheap_move_set_mask |= 1 << i;
+    let cheap_moves = match cheap_moves {
+        Ok(cheap_moves) => cheap_moves,
+        Err(e) => {
+            eprintln!("Invalid cheap move: {}", e);
+            std::process::exit(1)
+        }
+    };
+    cost_model.make_cheap(&cheap_moves);
+    #[cfg(feature = "plugins")]
+    let plugin = args.plugin.as_ref().map(|path| {
+        let plugin = plugin::load(path).unwrap_or_else(|e| {
+            eprintln!("Error loading plugin {}: {}", path.display(), e);
+            std::process::exit(1)
+        });
+        plugin.apply_costs(&mut cost_model);
+        std::sync::Arc::new(plugin)
+    });
+    // The checks include the config, but not the pruning table.
+    if let Some(Command::Selftest(selftest_args)) = &args.command {
+        if let Err(e) = selftest::run(selftest_args, &cost_model) {
+            eprintln!("{}", e);
+            std::process::exit(1)
         }
+        return;
+    }
+    // Reorients may be named with cell names from the config.
+    if let Some(Command::Calibrate(calibrate_args)) = &args.command {
+        let config = args.config.clone().or_else(config_location);
+        if let Err(e) = calibrate::run(calibrate_args, &cost_model, config) {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+        return;
     }
-    CHEAP_MOVES.store(cheap_move_set_mask, SeqCst);
 
-    PRUNING_TABLE_DEPTH.store(args.depth as i32, SeqCst);
-    STICKER_NOTATION.store(args.stickers, SeqCst);
+    let mask = match &args.mask {
+        Some(mask_string) => mask::parse(mask_string).unwrap_or_else(|e| {
+            eprintln!("Invalid mask: {}", e);
+            std::process::exit(1)
+        }),
+        None => 0,
+    };
 
-    println!("Initializing pruning table to depth {} ...", args.depth);
+    if let Some(Err(e)) = args.window.as_deref().map(parse_window) {
+        eprintln!("Invalid window: {}", e);
+        std::process::exit(1)
+    }
+    if let Some(Err(e)) = args.shard.as_deref().map(parse_shard) {
+        eprintln!("Invalid shard: {}", e);
+        std::process::exit(1)
+    }
+    for timeout in [&args.timeout, &args.per_alg_timeout] {
+        if let Some(Err(e)) = timeout.as_deref().map(parse_duration) {
+            eprintln!("Invalid timeout: {}", e);
+            std::process::exit(1)
+        }
+    }
+    let name = match args.strategy() {
+        Strategy::Beam => Some("beam"),
+        Strategy::Cost => Some("cost"),
+        Strategy::MeetInTheMiddle => Some("meet-in-the-middle"),
+        _ => None,
+    };
+    if let Some(name) = name.filter(|_| args.neutral || args.dump_tree.is_some()) {
+        eprintln!("Invalid strategy: `{name}` can't be combined with `--neutral` or `--dump-tree`");
+        std::process::exit(1)
+    }
+    let name = match args.strategy() {
+        Strategy::Beam => Some("beam"),
+        Strategy::MeetInTheMiddle => Some("meet-in-the-middle"),
+        _ => None,
+    };
+    if let Some(name) = name.filter(|_| args.optimal_etm) {
+        eprintln!("Invalid strategy: `{name}` can't be combined with `--optimal-etm`");
+        std::process::exit(1)
+    }
+    if args.beam_width == Some(0) {
+        eprintln!("Beam width must be at least 1");
+        std::process::exit(1)
+    }
+    if args.strategy == Some(Strategy::Anneal) && !args.can_anneal() {
+        eprintln!("Invalid strategy: `anneal` can't be combined with the options that `--anneal` conflicts with");
+        std::process::exit(1)
+    }
+    if !(0.0..1.0).contains(&args.perturbation) {
+        eprintln!("Perturbation must be at least 0 and less than 1");
+        std::process::exit(1)
+    }
+
+    let thread_count = threads::check_count(args.threads.unwrap_or_else(threads::default_count));
+    if args.nice {
+        threads::be_nice();
+    }
+    let max_memory = args.max_memory.as_deref().map(|max_memory| {
+        parse_memory(max_memory).unwrap_or_else(|e| {
+            eprintln!("Invalid memory limit: {}", e);
+            std::process::exit(1)
+        })
+    });
+    let table_cache = match args.no_table_cache {
+        true => None,
+        false => args.table_cache.clone().or_else(default_cache_dir),
+    };
+    let extend_cached_tables = match &args.command {
+        Some(Command::Warm(warm_args)) => {
+            if args.no_table_cache {
+                eprintln!("Invalid command: `warm` saves to the table cache, which `--no-table-cache` turns off");
+                std::process::exit(1)
+            }
+            warm_args.extend
+        }
+        _ => false,
+    };
+    let solver_faces = match &args.solver_moves {
+        Some(faces) => rocket::parse_solver_faces(faces).unwrap_or_else(|e| {
+            eprintln!("Invalid solver moves: {}", e);
+            std::process::exit(1)
+        }),
+        None => rocket::ALL_SOLVER_FACES,
+    };
+    // An input file at a URL is downloaded once, before it is needed to pick
+    // the table depth.
+    let downloaded = args
+        .input
+        .as_ref()
+        .and_then(|path| path.to_str())
+        .filter(|path| remote::is_url(path))
+        .map(|url| {
+            if !args.machine_readable() {
+                println!("Downloading {url} ...");
+            }
+            remote::fetch(url).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            })
+        });
+    let depth = match args.depth.as_str() {
+        "auto" => {
+            let memory = max_memory.unwrap_or_else(plan::memory_budget);
+            let depth = plan::auto_depth(
+                longest_alg(&args, downloaded.as_deref()),
+                args.max_depth,
+                solver_faces,
+                memory,
+            );
+            if !args.machine_readable() {
+                println!("Picked pruning table depth {depth}.");
+            }
+            depth
+        }
+        depth => match depth.parse::<u8>() {
+            Ok(depth) if (2..=table::MAX_DEPTH).contains(&depth) => depth,
+            _ => {
+                eprintln!(
+                    "Invalid depth: expected `auto` or a number from 2 to {}",
+                    table::MAX_DEPTH,
+                );
+                std::process::exit(1)
+            }
+        },
+    };
+    let notation = match args.stickers {
+        true => Notation::Sticker,
+        false => args.notation,
+    };
 
-    let _ = &*NAIVE_SOLVER;
+    let mut ranking = Ranking::default();
+    ranking.prefer_familiar = args.consistent;
+    ranking.penalize_alternation = args.penalize_alternation;
+    ranking.objective = args.objective;
+    if let Some(path) = &args.comfort {
+        let result = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| ranking.load_comfort(&cost_model, &contents));
+        if let Err(e) = result {
+            eprintln!("Error reading {}: {}", path.display(), e);
+            std::process::exit(1)
+        }
+    }
 
-    println!("Ready!");
-    println!();
+    // Algorithms piped into stdin are read without prompts or banners.
+    let piped = args.command.is_none()
+        && args.alg.is_none()
+        && args.input.is_none()
+        && !std::io::stdin().is_terminal();
 
-    loop {
-        let mut alg_string = String::new();
+    // Prompting for algorithms doesn't have to wait for a deep pruning
+    // table.
+    let interactive_session = args.command.is_none() && args.alg.is_none() && args.input.is_none();
+    let optimizer = RktOptimizer::new(depth, mask, cost_model)
+        .with_notation(notation, args.puzzle_size)
+        .with_solver_faces(solver_faces)
+        .with_up_to_auf(args.up_to_auf)
+        .with_pattern_databases(args.pattern_databases)
+        .with_table_cache_dir(table_cache)
+        .with_max_table_memory(max_memory.unwrap_or(usize::MAX))
+        .with_extend_cached_tables(extend_cached_tables)
+        .with_lazy_tables(interactive_session)
+        .with_optimal_etm(args.optimal_etm)
+        .with_thread_count(thread_count)
+        .with_deterministic(args.deterministic)
+        .with_nice(args.nice);
+    #[cfg(feature = "plugins")]
+    let optimizer = match plugin {
+        Some(plugin) => optimizer.with_plugin(plugin),
+        None => optimizer,
+    };
+    if !piped && !args.machine_readable() {
+        match interactive_session {
+            true => println!("Initializing pruning table to depth {depth} in the background ..."),
+            false => println!("Initializing pruning table to depth {depth} ..."),
+        }
+        if args.pattern_databases {
+            println!("Initializing pattern databases ...");
+        }
+    }
 
-        print!("Enter rotationless algorithm: ");
-        std::io::stdout().flush().unwrap();
-        match std::io::stdin().read_line(&mut alg_string) {
-            Ok(0) => std::process::exit(0),
-            Err(e) => {
+    optimizer.init_tables();
+
+    match &args.command {
+        Some(Command::Affinity(affinity_args)) => {
+            if let Err(e) = affinity::run(affinity_args, &optimizer, args.max_depth) {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+        }
+        Some(Command::Drill(drill_args)) => {
+            if let Err(e) = drill::run(drill_args, &optimizer) {
                 eprintln!("{}", e);
                 std::process::exit(1)
             }
-            _ => (),
         }
+        Some(Command::Improve(improve_args)) => {
+            if let Err(e) = improve::run(improve_args, &optimizer, args.max_depth) {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+        }
+        Some(Command::Library(library_args)) => {
+            if let Err(e) = library::run_search(library_args, &optimizer, args.max_depth) {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+        }
+        Some(Command::Save(save_args)) => {
+            let settings = args.settings(&optimizer);
+            if let Err(e) = library::save(save_args, &optimizer, &ranking, args.max_depth, settings)
+            {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+        }
+        Some(Command::Stats(stats_args)) => stats::run(stats_args, &optimizer, args.max_depth),
+        Some(Command::TableInfo(table_info_args)) => {
+            if let Err(e) = table_info::run(table_info_args, &optimizer) {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+        }
+        Some(Command::Variants(variants_args)) => {
+            if let Err(e) = variants::run(variants_args, &optimizer, args.max_depth) {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+        }
+        Some(Command::Warm(_)) => {
+            if let Err(e) = warm::run(&optimizer) {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+        }
+        Some(
+            Command::Calibrate(_)
+            | Command::DiffResults(_)
+            | Command::ImportKeymap(_)
+            | Command::Merge(_)
+            | Command::SelfUpdate(_)
+            | Command::Selftest(_)
+            | Command::Viz(_),
+        ) => {
+            unreachable!()
+        }
+        None => match (&args.alg, &args.input) {
+            (Some(alg), _) => {
+                if let Err(e) = Session::new(&args, optimizer, ranking).optimize(alg, None) {
+                    eprintln!("{}", e);
+                    std::process::exit(1)
+                }
+            }
+            (None, Some(path)) => {
+                if let Err(e) = batch(&args, optimizer, ranking, path, downloaded) {
+                    eprintln!("{}", e);
+                    std::process::exit(1)
+                }
+            }
+            (None, None) => interactive(&args, optimizer, ranking, piped),
+        },
+    }
+}
 
-        let alg = parse_scramble(alg_string);
+/// State carried from one algorithm to the next.
+struct Session<'a> {
+    // Settings for the current algorithm, which requests in the input file
+    // can override.
+    args: Cow<'a, Args>,
+    optimizer: RktOptimizer,
+    ranking: Ranking,
+    nodes_per_sec: Option<f64>,
+    confirm_after: Option<f64>,
+    /// Whether to print progress while searching.
+    verbose: bool,
+    // Orientation at the end of the previous algorithm, for `--chain`.
+    chain_orientation: Reorient,
+    // Moves of the previous algorithm, for finding cancellations with
+    // `--chain`.
+    chain_moves: Vec<Move>,
+    // ETM of the whole chain so far, and how much of it cancellations saved.
+    chain_etm: usize,
+    chain_saved: usize,
+    candidates: Vec<suggest::Candidate>,
+    // Reorients in the chosen solutions, for `--usage`.
+    usage: usage::Usage,
+    // Previous algorithm and the solutions printed for it, for `compare`.
+    shown: Option<(Alg, Vec<Solution>)>,
+    // ID of the current request from the input file, for its JSON results.
+    request_id: Option<serde_json::Value>,
+    // When the whole run stops and how long `--timeout` is.
+    deadline: Option<(std::time::Instant, std::time::Duration)>,
+}
+impl<'a> Session<'a> {
+    fn new(args: &'a Args, optimizer: RktOptimizer, ranking: Ranking) -> Self {
+        let candidates = match args.suggest {
+            true => match suggest::load(args.candidates.as_deref()) {
+                Ok(candidates) => candidates,
+                Err(e) => {
+                    eprintln!("Error loading candidates: {}", e);
+                    std::process::exit(1)
+                }
+            },
+            false => vec![],
+        };
+        if args.csv {
+            println!("{}", csv::HEADER);
+        }
+        Session {
+            args: Cow::Borrowed(args),
+            nodes_per_sec: args.dry_run.then(|| plan::calibrate(&optimizer)),
+            optimizer,
+            ranking,
+            // There is nobody to ask when optimizing a single algorithm.
+            confirm_after: (!args.yes && args.alg.is_none() && args.input.is_none())
+                .then_some(args.confirm_after),
+            verbose: !args.machine_readable(),
+            chain_orientation: Reorient::None,
+            chain_moves: vec![],
+            chain_etm: 0,
+            chain_saved: 0,
+            candidates,
+            usage: usage::Usage::default(),
+            shown: None,
+            request_id: None,
+            deadline: args.timeout.as_deref().map(|t| {
+                let timeout = std::time::Duration::from_secs_f64(parse_duration(t).unwrap());
+                (std::time::Instant::now() + timeout, timeout)
+            }),
+        }
+    }
 
-        let (reorient_count, mut solutions) = iddfs(&alg, args.max_depth);
-        let solution_count = solutions.len();
-        if solution_count == 0 {
-            println!("No solutions?");
-        } else {
-            let stm = alg.len() + reorient_count;
+    /// Returns the solutions that would be printed, from most to least
+    /// preferred, along with how many were left out by `--max-solutions` with
+    /// each score.
+    fn select(
+        &mut self,
+        alg: &Alg,
+        mut solutions: Vec<Solution>,
+    ) -> (Vec<Solution>, Vec<(u64, usize)>) {
+        let args = &self.args;
+        let cost_model = self.optimizer.cost_model();
+        if solutions.is_empty() {
+            return (solutions, vec![]);
+        }
+        self.ranking.sort(cost_model, alg, &mut solutions);
+        if !args.all {
+            let min_score = alg.score(cost_model, args.objective, &solutions[0]);
+            solutions
+                .retain(|solution| alg.score(cost_model, args.objective, solution) == min_score);
+            if args.fewest_distinct {
+                let min_distinct = distinct_reorients(&solutions[0]);
+                solutions.retain(|solution| distinct_reorients(solution) == min_distinct);
+            }
+        }
+        self.ranking.record(&solutions[0]);
+        self.usage.record(cost_model, &solutions[0]);
+        let omitted = match args.max_solutions {
+            Some(max) => rank::cap_per_score(cost_model, alg, args.objective, &mut solutions, max),
+            None => vec![],
+        };
+        (solutions, omitted)
+    }
+
+    /// Prints two of the solutions printed for the previous algorithm side by
+    /// side, numbered from 1 in the order they were printed.
+    fn compare(&self, first: usize, second: usize) -> Result<(), String> {
+        let Some((alg, shown)) = &self.shown else {
+            return Err("There are no solutions to compare yet.".to_string());
+        };
+        let get = |n: usize| {
+            n.checked_sub(1).and_then(|i| shown.get(i)).ok_or_else(|| {
+                format!(
+                    "There is no solution {n}; the previous algorithm has {}.",
+                    shown.len(),
+                )
+            })
+        };
+        let labels = [format!("#{first}"), format!("#{second}")];
+        compare::print(
+            &self.optimizer,
+            alg,
+            [&labels[0], &labels[1]],
+            [get(first)?, get(second)?],
+        );
+        println!();
+        Ok(())
+    }
+
+    /// Optimizes one algorithm and prints the results, or returns an error
+    /// if the algorithm is invalid. `line` is the line of the input file that
+    /// the algorithm is from, if any.
+    fn optimize(&mut self, alg_string: &str, line: Option<usize>) -> Result<(), String> {
+        let start = std::time::Instant::now();
+        self.shown = None;
+        let linked = remote::alg_from_link(alg_string).transpose()?;
+        let mut alg = Alg::parse_with(
+            linked.as_deref().unwrap_or(alg_string),
+            self.optimizer.cost_model(),
+        )?;
+        if let Some(window) = self.args.window.as_deref() {
+            let window = parse_window(window).unwrap();
+            if window.end > alg.moves.len() {
+                return Err("The window ends after the last move.".to_string());
+            }
+            alg.window = Some(window);
+        }
+        let original_moves = alg.moves.clone();
+        if self.args.breakdown {
+            breakdown::print(&original_moves);
+        }
+        if self.args.split_doubles {
+            alg.split_doubles();
+        }
+        if self.args.chain {
+            alg.start = self.chain_orientation;
+        }
+        if let Some(nodes_per_sec) = self.nodes_per_sec {
+            plan::print(&alg, self.args.max_depth, self.args.neutral, nodes_per_sec);
+            println!();
+            return Ok(());
+        }
+
+        let strategy = match self.args.strategy() {
+            Strategy::Auto => {
+                let strategy = plan::pick_strategy(
+                    &self.optimizer,
+                    &alg,
+                    self.args.max_depth,
+                    self.args.can_anneal(),
+                );
+                if self.verbose {
+                    println!(
+                        "Picked strategy {}.",
+                        format!("{strategy:?}").to_lowercase()
+                    );
+                }
+                strategy
+            }
+            strategy => strategy,
+        };
+        let optimizer = self
+            .optimizer
+            .clone()
+            .with_transpositions(strategy != Strategy::Exhaustive);
+        let cost_model = optimizer.cost_model();
+
+        if strategy == Strategy::Anneal {
+            let budget = self
+                .args
+                .anneal
+                .map_or(anneal::DEFAULT_BUDGET, std::time::Duration::from_secs_f64);
             println!(
-                "Found {solution_count} solutions with {reorient_count} reorients ({stm} STM)."
+                "Annealing for {}",
+                plan::format_duration(budget.as_secs_f64())
+            );
+            let result = anneal::optimize(&optimizer, &alg, budget);
+            println!("Tried {} placements.", result.steps);
+            match result.best {
+                Some(best) => {
+                    if self.args.suggest_cheap.is_some() {
+                        self.usage.record_found(&alg, std::slice::from_ref(&best));
+                    }
+                    let reorient_count = best.iter().filter(|r| !r.is_none()).count();
+                    // Annealing never proves that nothing is cheaper.
+                    let shown = report_solutions(
+                        &self.args,
+                        &optimizer,
+                        &mut self.ranking,
+                        &alg,
+                        reorient_count,
+                        vec![best],
+                        false,
+                    );
+                    if let Some(chosen) = shown.first() {
+                        self.usage.record(cost_model, chosen);
+                    }
+                }
+                None if result.fewest_unsolved == usize::MAX => {
+                    println!("No placement met the constraints.")
+                }
+                None => println!(
+                    "No placement solved the cube; the closest left {} stickers unsolved.",
+                    result.fewest_unsolved,
+                ),
+            }
+            println!();
+            return Ok(());
+        }
+
+        let mut tree = self
+            .args
+            .dump_tree
+            .as_ref()
+            .map(|_| SearchTree::new(self.args.dump_tree_nodes));
+        // Whichever of the run's and this algorithm's time limits comes
+        // first, and how long it is.
+        let per_alg_deadline = self.args.per_alg_timeout.as_deref().map(|t| {
+            let timeout = std::time::Duration::from_secs_f64(parse_duration(t).unwrap());
+            (std::time::Instant::now() + timeout, timeout)
+        });
+        let limit = [self.deadline, per_alg_deadline]
+            .into_iter()
+            .flatten()
+            .min_by_key(|&(deadline, _)| deadline);
+        let deadline = limit.map(|(deadline, _)| deadline);
+        let timeout = limit.map(|(_, timeout)| timeout);
+        // The recommended pre-rotation and the ETM it saves, with --neutral.
+        let mut neutral = None;
+        let (reorient_count, solutions, timed_out, interrupted, optimal) = if self.args.neutral {
+            let (start, result, savings) = solve_neutral(
+                &optimizer,
+                &alg,
+                self.args.max_depth,
+                &mut tree,
+                self.args.machine_readable(),
+            );
+            alg.start = start;
+            neutral = Some((start, savings));
+            (
+                result.reorient_count,
+                result.solutions,
+                false,
+                result.interrupted,
+                result.optimal,
+            )
+        } else {
+            let result = match strategy {
+                Strategy::Beam => {
+                    let width = self.args.beam_width.unwrap_or(beam::DEFAULT_WIDTH);
+                    if self.verbose {
+                        println!("Beam searching, keeping {width} placements at each gap");
+                    }
+                    let result = beam::search(&optimizer, &alg, width, self.args.max_depth);
+                    if self.verbose {
+                        println!("Tried {} placements.", result.nodes[0]);
+                    }
+                    result
+                }
+                Strategy::Cost => {
+                    optimizer.search_cost(&alg, self.args.max_depth, self.verbose, deadline)
+                }
+                Strategy::MeetInTheMiddle => meet::search(
+                    &optimizer,
+                    &alg,
+                    self.args.max_depth,
+                    self.verbose,
+                    deadline,
+                ),
+                _ => optimizer.search_until(
+                    &alg,
+                    self.args.max_depth,
+                    self.verbose,
+                    self.confirm_after,
+                    &mut tree,
+                    deadline,
+                ),
+            };
+            (
+                result.reorient_count,
+                result.solutions,
+                result.timed_out,
+                result.interrupted,
+                result.optimal,
+            )
+        };
+        if let (Some(path), Some(tree)) = (&self.args.dump_tree, &tree) {
+            let result = std::fs::File::create(path)
+                .and_then(|f| tree.write_dot(std::io::BufWriter::new(f)));
+            match result {
+                Ok(()) => println!(
+                    "Wrote {} search tree nodes to {} ({} more were not recorded).",
+                    tree.nodes().len(),
+                    path.display(),
+                    tree.skipped(),
+                ),
+                Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+            }
+        }
+        if self.args.suggest_cheap.is_some() {
+            self.usage.record_found(&alg, &solutions);
+        }
+        let stopped = match (timeout.filter(|_| timed_out), interrupted) {
+            (Some(timeout), _) => Some(format!(
+                "Timed out after {}",
+                plan::format_duration(timeout.as_secs_f64())
+            )),
+            (None, true) => Some("Interrupted".to_string()),
+            (None, false) => None,
+        };
+        let stopped_message = stopped.map(|stopped| match solutions.is_empty() {
+            true => format!("{stopped} while searching with {reorient_count} reorients."),
+            false => format!("{stopped}; these are the solutions found so far."),
+        });
+        if self.args.json {
+            let (solutions, omitted) = self.select(&alg, solutions);
+            let mut result = json::result(
+                &optimizer,
+                alg_string,
+                &alg,
+                reorient_count,
+                &solutions,
+                optimal,
+            );
+            if let Some(line) = line {
+                result["line"] = line.into();
+            }
+            if let Some(id) = &self.request_id {
+                result["id"] = id.clone();
+            }
+            if let Some((start, savings)) = neutral {
+                result["pre_rotation"] =
+                    (!start.is_none()).then(|| start.pre_rotation_name()).into();
+                result["savings"] = savings.into();
+            }
+            if timed_out {
+                result["timed_out"] = true.into();
+            }
+            if interrupted {
+                result["interrupted"] = true.into();
+            }
+            if (timed_out || interrupted) && solutions.is_empty() {
+                result["timed_out_at"] = reorient_count.into();
+            }
+            if self.args.max_solutions.is_some() {
+                let key = match self.args.objective {
+                    Objective::Etm => "added_etm",
+                    Objective::Time => "time_ms",
+                };
+                result["omitted"] = omitted
+                    .iter()
+                    .map(|&(score, count)| serde_json::json!({ key: score, "count": count }))
+                    .collect();
+            }
+            result["meta"] = meta::record(
+                &self.args.settings(&optimizer),
+                cost_model,
+                Some(start.elapsed()),
+                optimizer.deterministic(),
             );
-            if !args.all {
-                let min_cost = *solutions.iter().map(|(cost, _string)| cost).min().unwrap();
-                solutions.retain(|(cost, _string)| *cost == min_cost);
-                let good_solution_count = solutions.len();
-                println!("{good_solution_count} of them add only {min_cost} ETM.");
+            println!("{result}");
+            return Ok(());
+        }
+        if self.args.csv {
+            match (&stopped_message, line) {
+                (Some(message), Some(line)) => eprintln!("line {line}: {message}"),
+                (Some(message), None) => eprintln!("{message}"),
+                (None, _) => {}
             }
-            for (_cost, string) in solutions {
-                println!("{}", string);
+            let (solutions, omitted) = self.select(&alg, solutions);
+            for &(score, count) in &omitted {
+                let message = format!(
+                    "{count} more solutions that {} were left out.",
+                    describe_score(self.args.objective, score),
+                );
+                match line {
+                    Some(line) => eprintln!("line {line}: {message}"),
+                    None => eprintln!("{message}"),
+                }
             }
+            let meta = meta::record(
+                &self.args.settings(&optimizer),
+                cost_model,
+                Some(start.elapsed()),
+                optimizer.deterministic(),
+            );
+            for row in csv::rows(
+                &optimizer,
+                alg_string,
+                line,
+                &alg,
+                reorient_count,
+                &solutions,
+                neutral,
+                &meta,
+            ) {
+                println!("{row}");
+            }
+            return Ok(());
+        }
+        if let Some(message) = &stopped_message {
+            println!("{message}");
+            if solutions.is_empty() {
+                println!();
+                return Ok(());
+            }
+        }
+        let all_solutions =
+            (self.args.stability.is_some() || self.args.heatmap).then(|| solutions.clone());
+        let shown = if self.args.chain {
+            // The carried orientation is already there, so it isn't a
+            // rotation to perform.
+            let display_alg = Alg {
+                start: Reorient::None,
+                ..alg.clone()
+            };
+            let shown = report_solutions(
+                &self.args,
+                &optimizer,
+                &mut self.ranking,
+                &display_alg,
+                reorient_count,
+                solutions,
+                optimal,
+            );
+            if let Some(chosen) = shown.first() {
+                let end = chosen.iter().fold(alg.start, |o, &r| o.then(r));
+                let cancellation = cancel::find(
+                    &self.chain_moves,
+                    self.chain_orientation,
+                    &original_moves,
+                    alg.start,
+                );
+                let saved = cancellation.as_ref().map_or(0, |c| c.saved());
+                if let Some(cancellation) = cancellation {
+                    println!(
+                        "Cancels with the previous algorithm: {cancellation} (saves {saved} ETM)"
+                    );
+                }
+                self.chain_etm += alg.original_len() + alg.cost(cost_model, chosen) - saved;
+                self.chain_saved += saved;
+                println!(
+                    "Running total: {} ETM ({} saved by cancellations)",
+                    self.chain_etm, self.chain_saved,
+                );
+                self.chain_orientation = end;
+                self.chain_moves = original_moves;
+            }
+            match self.chain_orientation {
+                Reorient::None => println!("Orientation drift: none"),
+                o => println!("Orientation drift: {}", optimizer.reorient_name(o)),
+            }
+            shown
+        } else {
+            report_solutions(
+                &self.args,
+                &optimizer,
+                &mut self.ranking,
+                &alg,
+                reorient_count,
+                solutions,
+                optimal,
+            )
+        };
+        let chosen = shown.first();
+        if let Some(chosen) = chosen {
+            self.usage.record(cost_model, chosen);
+        }
+        self.shown = Some((alg.clone(), shown.clone()));
+        if let (Some(trials), Some(solutions)) = (self.args.stability, &all_solutions) {
+            let display_alg = Alg {
+                start: if self.args.chain {
+                    Reorient::None
+                } else {
+                    alg.start
+                },
+                ..alg.clone()
+            };
+            stability::print(
+                &optimizer,
+                &display_alg,
+                solutions,
+                trials,
+                self.args.perturbation,
+            );
+        }
+        if let (true, Some(solutions)) = (self.args.heatmap, &all_solutions) {
+            if !solutions.is_empty() {
+                println!();
+                heatmap::print(&optimizer, &alg, solutions);
+            }
+        }
+        if let (true, Some(chosen)) = (self.args.suggest, chosen) {
+            let etm = alg.original_len() + alg.cost(cost_model, chosen);
+            suggest::print(&optimizer, &alg, etm, &self.candidates, self.args.max_depth);
         }
         println!();
+        Ok(())
     }
 }
 
-fn iddfs(moves: &[Move], max_depth: usize) -> (usize, Vec<(usize, String)>) {
-    if moves.len() <= 1 {
-        return (
-            0,
-            vec![(
-                0,
-                moves.first().copied().map(display_move).unwrap_or_default(),
-            )],
-        );
+fn interactive(args: &Args, optimizer: RktOptimizer, ranking: Ranking, piped: bool) {
+    let mut session = Session::new(args, optimizer, ranking);
+    if piped {
+        // Answers to confirmation prompts would be read as algorithms.
+        session.confirm_after = None;
+        session.verbose = false;
+    } else {
+        rocket::interrupt::install();
+        println!("Ready! After solving an algorithm, enter `compare 1 2` to compare two of its solutions.");
+        println!();
     }
 
-    for max_reorients in 0..std::cmp::min(moves.len(), max_depth + 1) {
-        println!("Searching solutions with {} reorients", max_reorients);
-        let ret = dfs(&FaceletCube::new(3), moves, max_reorients);
-        if !ret.is_empty() {
-            let solutions = ret
-                .into_iter()
-                .map(|solution| {
-                    // Solutions are reversed, because reasons.
-                    let solution_iter = solution.iter().rev();
-
-                    let mut return_string = display_move(moves[0]);
-                    for (reorient, &mv) in solution_iter.zip(&moves[1..]) {
-                        return_string += &reorient.to_string();
-                        return_string += &display_move(mv);
-                    }
+    loop {
+        let mut alg_string = String::new();
 
-                    let cost = solution.iter().map(|r| r.cost()).sum();
+        if !piped {
+            print!("Enter rotationless algorithm: ");
+            std::io::stdout().flush().unwrap();
+        }
+        match std::io::stdin().read_line(&mut alg_string) {
+            Ok(0) => std::process::exit(0),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+            _ => (),
+        }
+        let comment = alg_string.trim().is_empty() || alg_string.trim_start().starts_with('#');
+        if piped && comment {
+            continue;
+        }
 
-                    (cost, return_string)
-                })
-                .collect();
-            return (max_reorients, solutions);
+        let result = match parse_compare(&alg_string) {
+            Some(Ok((first, second))) => session.compare(first, second),
+            Some(Err(e)) => Err(e),
+            None => session.optimize(&alg_string, None),
+        };
+        if let Err(e) = result {
+            match piped {
+                _ if args.json => println!("{}", json::error(&alg_string, &e)),
+                _ if args.csv => eprintln!("{}", e),
+                true => eprintln!("{}", e),
+                false => {
+                    println!("{}", e);
+                    println!();
+                }
+            }
         }
     }
+}
 
-    (0, vec![])
+/// Parses a `compare <i> <j>` command, or returns `None` if `s` isn't one.
+fn parse_compare(s: &str) -> Option<Result<(usize, usize), String>> {
+    let mut words = s.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("compare") {
+        return None;
+    }
+    let numbers: Vec<usize> = match words.map(str::parse).collect() {
+        Ok(numbers) => numbers,
+        Err(e) => return Some(Err(format!("Invalid solution number: {e}"))),
+    };
+    Some(match numbers[..] {
+        [first, second] => Ok((first, second)),
+        _ => Err("Usage: compare <solution> <solution>".to_string()),
+    })
 }
 
-fn dfs(state: &FaceletCube, moves: &[Move], max_reorients: usize) -> Vec<Solution> {
-    if moves.len() <= 1 || max_reorients == 0 {
-        // No more reorients allowed! Are we already solved?
-        let end_result = state.apply_moves(moves);
-        if NAIVE_SOLVER.lower_bound(&end_result) <= 1 {
-            // Success!
-            vec![vec![Reorient::None; moves.len().saturating_sub(1)]]
+/// Optimizes every algorithm in a file, or in stdin if `path` is `-`, or
+/// every algorithm in one shard of it, labeling the results by line.
+/// `downloaded` is the contents of the file if `path` is a URL.
+fn batch(
+    args: &Args,
+    optimizer: RktOptimizer,
+    ranking: Ranking,
+    path: &Path,
+    downloaded: Option<String>,
+) -> Result<(), String> {
+    let name = match path == Path::new("-") {
+        true => "stdin".to_string(),
+        false => path.display().to_string(),
+    };
+    // Stdin is read one line at a time, so that callers can read each result
+    // before sending the next request.
+    let reader: Box<dyn BufRead> = match (path == Path::new("-"), downloaded) {
+        (_, Some(contents)) => Box::new(std::io::Cursor::new(contents)),
+        (true, None) => Box::new(std::io::stdin().lock()),
+        (false, None) => Box::new(std::io::BufReader::new(
+            std::fs::File::open(path).map_err(|e| format!("error reading {name}: {e}"))?,
+        )),
+    };
+    let (shard, shard_count) = args
+        .shard
+        .as_deref()
+        .map_or((0, 1), |s| parse_shard(s).unwrap());
+    let mut session = Session::new(args, optimizer, ranking);
+    let mut failures = 0;
+    let lines = reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            line.as_ref().map_or(true, |line| {
+                !line.trim().is_empty() && !line.trim_start().starts_with('#')
+            })
+        })
+        .skip(shard)
+        .step_by(shard_count);
+    for (i, line) in lines {
+        if session
+            .deadline
+            .is_some_and(|(deadline, _)| std::time::Instant::now() >= deadline)
+        {
+            let message = format!("Timed out; skipped the rest of {name} from line {}.", i + 1);
+            match args.machine_readable() {
+                true => eprintln!("{message}"),
+                false => println!("{message}"),
+            }
+            break;
+        }
+        let line = line.map_err(|e| format!("error reading {name}: {e}"))?;
+        let request = match line.trim_start().starts_with('{') {
+            true => parse_request(args, &line),
+            false => Ok(Request {
+                alg: line.clone(),
+                id: None,
+                args: None,
+            }),
+        };
+        let (input, id, result) = match request {
+            Ok(request) => {
+                if !args.machine_readable() {
+                    println!("Line {}: {}", i + 1, request.alg.trim());
+                }
+                if let Some(request_args) = request.args {
+                    session.args = Cow::Owned(request_args);
+                }
+                session.request_id = request.id.clone();
+                let result = session.optimize(&request.alg, Some(i + 1));
+                session.args = Cow::Borrowed(args);
+                session.request_id = None;
+                (request.alg, request.id, result)
+            }
+            Err(e) => (line, None, Err(e)),
+        };
+        if let Err(e) = result {
+            if args.json {
+                let mut error = json::error(&input, &e);
+                error["line"] = (i + 1).into();
+                if let Some(id) = id {
+                    error["id"] = id;
+                }
+                println!("{error}");
+            } else if args.csv {
+                eprintln!("line {}: {}", i + 1, e);
+            } else {
+                println!("{}", e);
+                println!();
+            }
+            failures += 1;
+        }
+    }
+    let optimizer = &session.optimizer;
+    if args.usage {
+        if args.json {
+            println!("{}", json::usage(optimizer, &session.usage));
         } else {
-            // Fail!
-            vec![]
+            session.usage.print(optimizer);
         }
-    } else if NAIVE_SOLVER.lower_bound(state) as usize > moves.len() + 1 {
-        // Fail!
-        vec![]
-    } else {
-        let mut ret = vec![];
-
-        // Try not reorienting right now.
-        let new_state = state.apply_move(moves[0]);
-
-        // Try every possible reorient, including the null reorient.
-        for &reorient in Reorient::ALL {
-            let remaining_reorients = max_reorients - 1 + reorient.is_none() as usize;
-            ret.extend(
-                dfs(
-                    &new_state.apply_moves(reorient.equivalent_rkt_moves()),
-                    &moves[1..],
-                    remaining_reorients,
-                )
-                .into_iter()
-                .map(|mut solution| {
-                    solution.push(reorient);
-                    solution
-                }),
+    }
+    if let Some(n) = args.suggest_cheap {
+        if args.json {
+            let suggestions = session.usage.suggest_cheap(optimizer.cost_model(), n);
+            println!(
+                "{}",
+                json::cheap_suggestions(optimizer, &session.usage, &suggestions)
             );
+        } else {
+            session.usage.print_cheap_suggestions(optimizer, n);
         }
-
-        ret
+    }
+    match failures {
+        0 => Ok(()),
+        n => Err(format!("{n} algorithms in {name} are invalid")),
     }
 }
 
-/// Reorientations between each move.
-pub type Solution = Vec<Reorient>;
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub enum Reorient {
-    None = 0,
-
-    R = 1,
-    L = 2,
-    U = 3,
-    D = 4,
-    F = 5,
-    B = 6,
-
-    R2 = 7,
-    U2 = 8,
-    F2 = 9,
-
-    UF = 10,
-    UR = 11,
-    FR = 12,
-    DF = 13,
-    UL = 14,
-    BR = 15,
-
-    UFR = 16,
-    DBL = 17,
-    UFL = 18,
-    DBR = 19,
-    DFR = 20,
-    UBL = 21,
-    UBR = 22,
-    DFL = 23,
+/// Algorithm from a line of the input file, with the settings to optimize it
+/// with if they differ from the command line's.
+struct Request {
+    alg: String,
+    id: Option<serde_json::Value>,
+    args: Option<Args>,
 }
-impl fmt::Display for Reorient {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use Reorient::*;
-
-        let s = STICKER_NOTATION.load(SeqCst);
-
-        match self {
-            None => write!(f, " "),
-
-            R => write!(f, " {} ", if s { "23I:L" } else { "Ox" }),
-            L => write!(f, " {} ", if s { "23I:R" } else { "Ox'" }),
-            U => write!(f, " {} ", if s { "23I:D" } else { "Oy" }),
-            D => write!(f, " {} ", if s { "23I:U" } else { "Oy'" }),
-            F => write!(f, " {} ", if s { "23I:B" } else { "Oz" }),
-            B => write!(f, " {} ", if s { "23I:F" } else { "Oz'" }),
-
-            R2 => write!(f, " {} ", if s { "23I:R2" } else { "Ox2" }),
-            U2 => write!(f, " {} ", if s { "23I:U2" } else { "Oy2" }),
-            F2 => write!(f, " {} ", if s { "23I:F2" } else { "Oz2" }),
-
-            UF => write!(f, " {} ", if s { "23I:UF" } else { "Oxy2" }),
-            UR => write!(f, " {} ", if s { "23I:UR" } else { "Ozx2" }),
-            FR => write!(f, " {} ", if s { "23I:FR" } else { "Oyz2" }),
-            DF => write!(f, " {} ", if s { "23I:DF" } else { "Oxz2" }),
-            UL => write!(f, " {} ", if s { "23I:UL" } else { "Ozy2" }),
-            BR => write!(f, " {} ", if s { "23I:BR" } else { "Oyx2" }),
-
-            UFR => write!(f, " {} ", if s { "23I:DBL" } else { "Oxy" }),
-            DBL => write!(f, " {} ", if s { "23I:UFR" } else { "Oy'x'" }),
-            UFL => write!(f, " {} ", if s { "23I:DBR" } else { "Ozy" }),
-            DBR => write!(f, " {} ", if s { "23I:UFL" } else { "Oxy'" }),
-            DFR => write!(f, " {} ", if s { "23I:UBL" } else { "Oxz" }),
-            UBL => write!(f, " {} ", if s { "23I:DFR" } else { "Oyz'" }),
-            UBR => write!(f, " {} ", if s { "23I:DFL" } else { "Oyx" }),
-            DFL => write!(f, " {} ", if s { "23I:UBR" } else { "Ozx'" }),
+
+/// Parses a JSON request from the input file, applying its overrides to
+/// `args`.
+fn parse_request(args: &Args, line: &str) -> Result<Request, String> {
+    use serde_json::Value;
+
+    let request: Value = serde_json::from_str(line).map_err(|e| format!("Invalid request: {e}"))?;
+    let fields = request
+        .as_object()
+        .ok_or("Invalid request: expected a JSON object")?;
+    let mut alg = None;
+    let mut id = None;
+    let mut args = args.clone();
+    for (key, value) in fields {
+        let invalid = || format!("Invalid {key} in request: {value}");
+        let number = || value.as_u64().map(|n| n as usize).ok_or_else(invalid);
+        match key.as_str() {
+            "alg" => alg = Some(value.as_str().ok_or_else(invalid)?.to_string()),
+            "id" => id = Some(value.clone()),
+            "all" => args.all = value.as_bool().ok_or_else(invalid)?,
+            "fewest_distinct" => args.fewest_distinct = value.as_bool().ok_or_else(invalid)?,
+            "split_doubles" => args.split_doubles = value.as_bool().ok_or_else(invalid)?,
+            "max_depth" => args.max_depth = number()?,
+            "max_solutions" => {
+                args.max_solutions = match value {
+                    Value::Null => None,
+                    _ => Some(number()?),
+                }
+            }
+            "objective" => {
+                let objective = value.as_str().ok_or_else(invalid)?;
+                args.objective = clap::ArgEnum::from_str(objective, true)
+                    .map_err(|e| format!("Invalid objective in request: {e}"))?;
+            }
+            "per_alg_timeout" => {
+                if args.neutral {
+                    return Err(
+                        "Invalid request: per_alg_timeout can't be combined with `--neutral`"
+                            .into(),
+                    );
+                }
+                let timeout = match value {
+                    Value::Number(n) => n.to_string(),
+                    _ => value.as_str().ok_or_else(invalid)?.to_string(),
+                };
+                parse_duration(&timeout).map_err(|e| format!("Invalid timeout in request: {e}"))?;
+                args.per_alg_timeout = Some(timeout);
+            }
+            "window" => {
+                let window = value.as_str().ok_or_else(invalid)?;
+                parse_window(window).map_err(|e| format!("Invalid window in request: {e}"))?;
+                args.window = Some(window.to_string());
+            }
+            _ => return Err(format!("Invalid request: unknown setting `{key}`")),
+        }
+    }
+    Ok(Request {
+        alg: alg.ok_or("Invalid request: missing `alg`")?,
+        id,
+        args: Some(args),
+    })
+}
+
+/// Prints the solutions found for an algorithm and returns the ones printed
+/// on their own lines, from most to least preferred, so the first is the best
+/// one. With `--choices`, only the best one is returned, since the patterns
+/// don't list the solutions. `optimal` is whether the solutions are proven to
+/// include the cheapest. They are costed and displayed with `optimizer`.
+fn report_solutions(
+    args: &Args,
+    optimizer: &RktOptimizer,
+    ranking: &mut Ranking,
+    alg: &Alg,
+    reorient_count: usize,
+    mut solutions: Vec<Solution>,
+    optimal: bool,
+) -> Vec<Solution> {
+    let solution_count = solutions.len();
+    if solution_count == 0 {
+        println!("No solutions?");
+        return vec![];
+    }
+
+    let stm = alg.original_len() + reorient_count;
+    let confidence = match optimal {
+        true => "proven optimal",
+        false => "best found, not proven optimal",
+    };
+    println!(
+        "Found {solution_count} solutions with {reorient_count} reorients ({stm} STM), {confidence}."
+    );
+    let cost_model = optimizer.cost_model();
+    ranking.sort(cost_model, alg, &mut solutions);
+    if !args.all {
+        let min_score = alg.score(cost_model, args.objective, &solutions[0]);
+        solutions.retain(|solution| alg.score(cost_model, args.objective, solution) == min_score);
+        let good_solution_count = solutions.len();
+        match args.objective {
+            Objective::Etm => println!("{good_solution_count} of them add only {min_score} ETM."),
+            Objective::Time => println!(
+                "{good_solution_count} of them take only {:.2}s ({} ETM).",
+                min_score as f64 / 1000.0,
+                alg.cost(cost_model, &solutions[0]),
+            ),
+        }
+        if args.fewest_distinct {
+            let min_distinct = distinct_reorients(&solutions[0]);
+            solutions.retain(|solution| distinct_reorients(solution) == min_distinct);
+            let good_solution_count = solutions.len();
+            println!("{good_solution_count} of them use only {min_distinct} distinct reorients.");
         }
     }
+    if args.collapse_symmetric {
+        let forbidden = cost_model.forbidden_moves().to_vec();
+        symmetry::collapse(alg, optimizer.mask(), &forbidden, &mut solutions);
+        println!(
+            "{} of them are different up to the symmetry of the first move.",
+            solutions.len(),
+        );
+    }
+    ranking.record(&solutions[0]);
+    let display = |solution: &Solution| match args.pre_rotation {
+        true => optimizer.display_pre_rotated(alg, solution),
+        false => optimizer.display(alg, solution),
+    };
+    let shown = if args.group {
+        let families = rank::group_families(&solutions);
+        println!("They form {} mirror/inverse families.", families.len());
+        for family in &families {
+            let id = alg.solution_id(&family[0]);
+            match family.len() {
+                1 => println!("{}  #{id}", display(&family[0])),
+                n => println!("{}  #{id}  (+{} related)", display(&family[0]), n - 1),
+            }
+            if args.rkt {
+                println!("  RKT: {}", alg.display_rkt(cost_model, &family[0]));
+            }
+        }
+        families
+            .into_iter()
+            .map(|family| family[0].clone())
+            .collect()
+    } else if args.choices {
+        let patterns = choices::patterns(&solutions);
+        println!(
+            "They form {} patterns; every combination of the choices in braces works.",
+            patterns.len(),
+        );
+        for pattern in patterns {
+            match pattern.len() {
+                1 => println!("{}", choices::display(optimizer, alg, &pattern)),
+                n => println!(
+                    "{}  ({n} solutions)",
+                    choices::display(optimizer, alg, &pattern)
+                ),
+            }
+        }
+        vec![solutions[0].clone()]
+    } else {
+        let omitted = match args.max_solutions {
+            Some(max) => rank::cap_per_score(cost_model, alg, args.objective, &mut solutions, max),
+            None => vec![],
+        };
+        for (i, solution) in solutions.iter().enumerate() {
+            println!("{}  #{}", display(solution), alg.solution_id(solution));
+            if args.rkt {
+                println!("  RKT: {}", alg.display_rkt(cost_model, solution));
+            }
+            // Note how many were left out after the last one with each score.
+            let score = alg.score(cost_model, args.objective, solution);
+            let last = solutions
+                .get(i + 1)
+                .is_none_or(|next| alg.score(cost_model, args.objective, next) != score);
+            if let Some((_, count)) = omitted.iter().find(|&&(s, _)| s == score && last) {
+                println!(
+                    "... and {count} more that {}.",
+                    describe_score(args.objective, score),
+                );
+            }
+        }
+        solutions.clone()
+    };
+    if args.diagnose_heuristic {
+        println!();
+        diagnose::diagnose_heuristic(optimizer, alg, &solutions, args.diagnose_depth)
+            .print(args.diagnose_depth);
+    }
+    shown
 }
-impl Reorient {
-    pub const ALL: &'static [Self] = &[
-        Self::None,
-        Self::R,
-        Self::L,
-        Self::U,
-        Self::D,
-        Self::F,
-        Self::B,
-        Self::R2,
-        Self::U2,
-        Self::F2,
-        Self::UF,
-        Self::UR,
-        Self::FR,
-        Self::DF,
-        Self::UL,
-        Self::BR,
-        Self::UFR,
-        Self::DBL,
-        Self::UFL,
-        Self::DBR,
-        Self::DFR,
-        Self::UBL,
-        Self::UBR,
-        Self::DFL,
-    ];
-
-    pub fn cost(self) -> usize {
-        use Reorient::*;
-
-        if (CHEAP_MOVES.load(SeqCst) >> self as u32) & 1 != 0 && self != Self::None {
-            return 1;
-        }
-
-        match self {
-            None => 0,
-            R | L | U | D | F | B => 1,
-            R2 | U2 | F2 => 2,
-            UF | UR | FR | DF | UL | BR => 3,
-            UFR | DBL | UFL | DBR | DFR | UBL | UBR | DFL => 2,
-        }
-    }
-
-    pub fn equivalent_rkt_moves(self) -> &'static [Move] {
-        use Move::{X, Y, Z};
-        use MoveVariant::*;
-        use Reorient::*;
-
-        match self {
-            None => &[],
-
-            R => &[X(Standard)],
-            L => &[X(Inverse)],
-            U => &[Y(Standard)],
-            D => &[Y(Inverse)],
-            F => &[Z(Standard)],
-            B => &[Z(Inverse)],
-
-            R2 => &[X(Double)],
-            U2 => &[Y(Double)],
-            F2 => &[Z(Double)],
-
-            UF => &[X(Standard), Y(Double)],
-            UR => &[Z(Standard), X(Double)],
-            FR => &[Y(Standard), Z(Double)],
-            DF => &[X(Standard), Z(Double)],
-            UL => &[Z(Standard), Y(Double)],
-            BR => &[Y(Standard), X(Double)],
-
-            UFR => &[X(Standard), Y(Standard)],
-            DBL => &[Y(Inverse), X(Inverse)],
-            UFL => &[Z(Standard), Y(Standard)],
-            DBR => &[X(Standard), Y(Inverse)],
-            DFR => &[X(Standard), Z(Standard)],
-            UBL => &[Y(Standard), Z(Inverse)],
-            UBR => &[Y(Standard), X(Standard)],
-            DFL => &[Z(Standard), X(Inverse)],
-        }
-    }
-
-    pub fn is_none(self) -> bool {
-        self == Self::None
+
+/// Describes a score under `objective`, such as `add 6 ETM`.
+fn describe_score(objective: Objective, score: u64) -> String {
+    match objective {
+        Objective::Etm => format!("add {score} ETM"),
+        Objective::Time => format!("take {:.2}s", score as f64 / 1000.0),
     }
 }
 
-pub fn display_move(mv: Move) -> String {
-    match mv {
-        Move::U(v) => "U".to_string() + display_move_variant(v),
-        Move::L(v) => "L".to_string() + display_move_variant(v),
-        Move::F(v) => "F".to_string() + display_move_variant(v),
-        Move::R(v) => "R".to_string() + display_move_variant(v),
-        Move::B(v) => "B".to_string() + display_move_variant(v),
-        Move::D(v) => "D".to_string() + display_move_variant(v),
-        Move::Uw(2, v) => "Uw".to_string() + display_move_variant(v),
-        Move::Lw(2, v) => "Lw".to_string() + display_move_variant(v),
-        Move::Fw(2, v) => "Fw".to_string() + display_move_variant(v),
-        Move::Rw(2, v) => "Rw".to_string() + display_move_variant(v),
-        Move::Bw(2, v) => "Bw".to_string() + display_move_variant(v),
-        Move::Dw(2, v) => "Dw".to_string() + display_move_variant(v),
-        Move::X(v) => "x".to_string() + display_move_variant(v),
-        Move::Y(v) => "y".to_string() + display_move_variant(v),
-        Move::Z(v) => "z".to_string() + display_move_variant(v),
-        _ => panic!("unsupported move {:?}", mv),
+/// Solves an algorithm from every starting orientation, prints a summary unless
+/// `quiet`, and returns the starting orientation with the cheapest solutions
+/// along with those solutions and the ETM they save compared to the default
+/// orientation, which is `None` if either has no solutions. The solutions are
+/// only marked optimal if every orientation's search was, and interrupted if
+/// any of them was.
+fn solve_neutral(
+    optimizer: &RktOptimizer,
+    alg: &Alg,
+    max_depth: usize,
+    tree: &mut Option<SearchTree>,
+    quiet: bool,
+) -> (Reorient, SearchResult, Option<usize>) {
+    if !quiet {
+        println!("Searching solutions from every starting orientation");
+    }
+    let solve = |start: Reorient, tree: &mut Option<SearchTree>| {
+        let alg = Alg {
+            start,
+            ..alg.clone()
+        };
+        (
+            start,
+            optimizer.search_until(&alg, max_depth, false, None, tree, None),
+        )
+    };
+    // Recording the search tree needs every search on the same thread.
+    let mut results: Vec<(Reorient, SearchResult)> = if tree.is_some() {
+        Reorient::ALL
+            .iter()
+            .map(|&start| solve(start, tree))
+            .collect()
+    } else {
+        threads::map_on(optimizer.thread_count(), Reorient::ALL, |&start| {
+            solve(start, &mut None)
+        })
+    };
+
+    // Sort by ETM, then by number of reorients, putting orientations without
+    // any solutions last.
+    let cost_model = optimizer.cost_model();
+    let min_cost = |solutions: &[Solution]| solutions.iter().map(|s| alg.cost(cost_model, s)).min();
+    results.sort_by_key(|(_, result)| {
+        let cost = min_cost(&result.solutions);
+        (cost.is_none(), cost, result.reorient_count)
+    });
+
+    let summary = |(_, result): &(Reorient, SearchResult)| {
+        (result.reorient_count, min_cost(&result.solutions))
+    };
+    let best_start = results[0].0;
+    let best_cost = min_cost(&results[0].1.solutions);
+    let default_cost = summary(results.iter().find(|r| r.0.is_none()).unwrap()).1;
+    let savings = default_cost
+        .zip(best_cost)
+        .map(|(default, best)| default - best);
+    if !quiet {
+        if results.iter().all(|r| summary(r) == summary(&results[0])) {
+            println!("Every starting orientation is equally good.");
+        } else {
+            println!("Start  Reorients  ETM");
+            for result in &results {
+                let start = result.0.xyz_name();
+                match summary(result) {
+                    (reorient_count, Some(cost)) => {
+                        println!("{start:<5}  {reorient_count:>9}  {cost:>3}")
+                    }
+                    (_, None) => println!("{start:<5}  {:>9}  {:>3}", "-", "-"),
+                }
+            }
+        }
+
+        // Recommend a pre-rotation if it saves anything compared to the
+        // default orientation.
+        match (default_cost, best_cost) {
+            _ if best_start.is_none() => println!("No pre-rotation recommended."),
+            (_, None) => (),
+            (None, Some(_)) => println!(
+                "Recommended pre-rotation: {} (the default orientation has no solution)",
+                best_start.pre_rotation_name(),
+            ),
+            (Some(_), Some(_)) => println!(
+                "Recommended pre-rotation: {} (saves {} ETM compared to the default orientation)",
+                best_start.pre_rotation_name(),
+                savings.unwrap(),
+            ),
+        }
+        println!();
     }
+
+    let optimal = results.iter().all(|(_, result)| result.optimal);
+    let interrupted = results.iter().any(|(_, result)| result.interrupted);
+    let (start, mut result) = results.swap_remove(0);
+    result.optimal = optimal;
+    result.interrupted = interrupted;
+    (start, result, savings)
 }
-pub fn display_move_variant(v: MoveVariant) -> &'static str {
-    match v {
-        MoveVariant::Standard => "",
-        MoveVariant::Double => "2",
-        MoveVariant::Inverse => "'",
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_memory_amounts() {
+        assert_eq!(parse_memory("4096"), Ok(4096));
+        assert_eq!(parse_memory("512k"), Ok(512 << 10));
+        assert_eq!(parse_memory(" 1.5G "), Ok(3 << 29));
+        assert_eq!(parse_memory("2T"), Ok(2 << 40));
+        for s in ["", "0", "-1M", "12X", "M", "NaN"] {
+            assert!(parse_memory(s).is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Ok(90.0));
+        assert_eq!(parse_duration("30s"), Ok(30.0));
+        assert_eq!(parse_duration("1.5m"), Ok(90.0));
+        assert_eq!(parse_duration("2H"), Ok(7200.0));
+        for s in ["", "0s", "-5", "5d", "m"] {
+            assert!(parse_duration(s).is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn parses_shards() {
+        assert_eq!(parse_shard("1/1"), Ok((0, 1)));
+        assert_eq!(parse_shard("2/8"), Ok((1, 8)));
+        assert_eq!(parse_shard(" 8 / 8 "), Ok((7, 8)));
+        for s in ["2", "0/8", "9/8", "1/0", "a/8"] {
+            assert!(parse_shard(s).is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn parses_windows() {
+        assert_eq!(parse_window("5..12"), Ok(4..11));
+        assert_eq!(parse_window("1..2"), Ok(0..1));
+        for s in ["5", "0..3", "3..3", "4..3", "1..x"] {
+            assert!(parse_window(s).is_err(), "{s:?}");
+        }
     }
 }
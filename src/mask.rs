@@ -0,0 +1,104 @@
+//! Ignoring pieces that don't matter when checking whether the cube is solved.
+
+use cubesim::{Cube, Face, FaceletCube, Move, MoveVariant};
+
+const FACES: [Face; 6] = [Face::U, Face::L, Face::F, Face::R, Face::B, Face::D];
+
+/// Returns a solved cube whose stickers in `mask` are blank. Bit `i` of a
+/// mask is set if sticker `i` of the solved cube is ignored.
+pub fn solved_cube(mask: u64) -> FaceletCube {
+    FaceletCube::new(3).mask(&|i, face| match (mask >> i) & 1 {
+        0 => face,
+        _ => Face::X,
+    })
+}
+
+/// Parses a mask string into a bitmask of stickers to ignore.
+///
+/// The string is a list of pieces separated by spaces or commas. A piece is
+/// named by its faces in any order, such as `UF` or `RUF`, or a center by its
+/// face. `<face>-edges`, `<face>-corners`, and `<face>-layer` name several
+/// pieces at once, such as `U-edges` for the last-layer edges.
+pub fn parse(s: &str) -> Result<u64, String> {
+    let pieces = sticker_pieces();
+    let mut mask = 0;
+    for token in s.split([' ', ',']).filter(|t| !t.is_empty()) {
+        let matches: Box<dyn Fn(u8) -> bool> = match token.split_once('-') {
+            Some((face, group)) => {
+                let face = face_bits(face).filter(|f| f.count_ones() == 1);
+                let face = face.ok_or_else(|| format!("invalid face in {token:?}"))?;
+                let size = match group {
+                    "edges" => Some(2),
+                    "corners" => Some(3),
+                    "layer" => None,
+                    _ => return Err(format!("invalid piece group {token:?}")),
+                };
+                Box::new(move |piece| {
+                    piece & face != 0 && size.is_none_or(|n| piece.count_ones() == n)
+                })
+            }
+            None => {
+                let piece = face_bits(token).ok_or_else(|| format!("invalid piece {token:?}"))?;
+                if !pieces.contains(&piece) {
+                    return Err(format!("there is no piece {token:?}"));
+                }
+                Box::new(move |p| p == piece)
+            }
+        };
+        for (i, &piece) in pieces.iter().enumerate() {
+            if matches(piece) {
+                mask |= 1 << i;
+            }
+        }
+    }
+    Ok(mask)
+}
+
+/// Returns a set of faces as a bitmask, or `None` if a face is invalid or
+/// repeated.
+fn face_bits(s: &str) -> Option<u8> {
+    let mut ret = 0;
+    for c in s.chars() {
+        let i = "ULFRBD".find(c.to_ascii_uppercase())?;
+        if ret & (1 << i) != 0 {
+            return None;
+        }
+        ret |= 1 << i;
+    }
+    (ret != 0).then_some(ret)
+}
+
+/// Returns the piece that each sticker of the solved cube belongs to, as the
+/// bitmask of faces that the piece touches.
+pub(crate) fn sticker_pieces() -> Vec<u8> {
+    let solved = FaceletCube::new(3);
+    let colors = solved.state();
+    (0..colors.len())
+        .map(|i| {
+            // A sticker is on a face's layer exactly when turning that face
+            // moves it. Centers don't move, so use their color instead.
+            let only_i = solved.mask(&|j, face| if j as usize == i { face } else { Face::X });
+            let turned = FACES.iter().enumerate().filter(|&(_, &face)| {
+                let mv = face_move(face);
+                only_i.apply_move(mv).state()[i] == Face::X
+            });
+            let piece = turned.fold(0, |acc, (f, _)| acc | (1 << f));
+            match piece {
+                0 => 1 << FACES.iter().position(|&f| f == colors[i]).unwrap(),
+                _ => piece,
+            }
+        })
+        .collect()
+}
+
+fn face_move(face: Face) -> Move {
+    let v = MoveVariant::Standard;
+    match face {
+        Face::U => Move::U(v),
+        Face::L => Move::L(v),
+        Face::F => Move::F(v),
+        Face::R => Move::R(v),
+        Face::B => Move::B(v),
+        _ => Move::D(v),
+    }
+}
@@ -0,0 +1,410 @@
+//! Meet-in-the-middle search over reorient placements, for algorithms long
+//! enough that the exhaustive search can't reach the reorients they need.
+//!
+//! Each solution is split after the first half of its reorients. First
+//! halves are placed forward from the start and remembered by the state and
+//! orientation that they leave the cube in after their last reorient. Second
+//! halves are placed backward from the end of the algorithm, undoing the
+//! moves and reorients from each solved state, and every state they undo to
+//! is looked up among the first halves. Each half places only about half of
+//! the reorients, so a search that would visit `n` nodes visits about `√n`
+//! of them, but remembers every first half.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use cubesim::{Face, Move};
+
+use crate::optimizer::{step_tables, Tables};
+use crate::sticker_cube::{self, Colors, MoveTable, PackTable, StickerCube};
+use crate::{
+    interrupt, invert_moves, plan, solved_states, threads, Alg, Reorient, RktOptimizer,
+    SearchResult, Solution,
+};
+
+/// Number of nodes between checks of the deadline.
+const DEADLINE_INTERVAL: usize = 1024;
+
+/// First halves of solutions, by the gap after their last reorient and the
+/// packed state and orientation that they leave the cube in there.
+type Halves = HashMap<(usize, [u64; 3], Reorient), Vec<Solution>>;
+
+/// State shared by every node of one half of a search.
+struct Context<'a> {
+    /// Tables that bound the moves left.
+    tables: &'a Tables,
+    alg: &'a Alg,
+    /// Moves that can't be made in each orientation.
+    forbidden: &'a [Vec<Move>],
+    /// Orientation required before each move and at the end, if any.
+    boundaries: &'a [Option<Reorient>],
+    /// Table for each move of the algorithm.
+    steps: &'a [MoveTable],
+    /// Table undoing each move of the algorithm.
+    inverse_steps: &'a [MoveTable],
+    pack_table: &'a PackTable,
+    /// Whether any first halves end before each move, so that second halves
+    /// starting there are looked up.
+    split_before: &'a [bool],
+    /// Number of nodes visited so far.
+    nodes: usize,
+    /// Time to stop searching at, if any.
+    deadline: Option<Instant>,
+    /// Whether the deadline has passed or Ctrl-C was pressed.
+    timed_out: bool,
+    /// Whether to yield to other threads regularly.
+    nice: bool,
+}
+impl Context<'_> {
+    /// Counts a node and returns whether the search should stop.
+    fn visit(&mut self) -> bool {
+        self.nodes += 1;
+        threads::yield_point(self.nodes, self.nice);
+        if self.nodes.is_multiple_of(DEADLINE_INTERVAL)
+            && (interrupt::requested()
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline))
+        {
+            self.timed_out = true;
+        }
+        self.timed_out
+    }
+
+    /// Returns whether move `i` can be made in `orientation`.
+    fn allowed(&self, i: usize, orientation: Reorient) -> bool {
+        self.boundaries[i].is_none_or(|required| required == orientation)
+            && !self.forbidden[orientation as usize].contains(&self.alg.moves[i])
+    }
+}
+
+/// Searches `optimizer` for solutions with 0 reorients, then 1, and so on up
+/// to `max_depth`, like [`RktOptimizer::search_until()`], by joining the first
+/// and second halves of each solution. This finds the same solutions, and
+/// grows much more slowly with each reorient, but takes memory for every
+/// first half, and is slower for the first few reorients since it can't use
+/// the pruning table on second halves.
+///
+/// If `verbose`, progress is printed. The search stops at `deadline` if it is
+/// given, or if Ctrl-C is pressed, keeping the solutions found so far.
+pub fn search(
+    optimizer: &RktOptimizer,
+    alg: &Alg,
+    max_depth: usize,
+    verbose: bool,
+    deadline: Option<Instant>,
+) -> SearchResult {
+    let tables = optimizer.tables();
+    let mut ret = SearchResult {
+        reorient_count: 0,
+        solutions: vec![],
+        timed_out: false,
+        interrupted: false,
+        nodes: vec![],
+        optimal: false,
+    };
+    let moves = &alg.moves;
+    if moves.len() <= 1 {
+        ret.solutions = vec![vec![]];
+        ret.optimal = optimizer.admissible();
+        return ret;
+    }
+    let cost_model = optimizer.cost_model();
+    let boundaries = alg.boundaries();
+    let (steps, _) = step_tables(moves);
+    let inverse_steps: Vec<MoveTable> = moves
+        .iter()
+        .map(|&mv| MoveTable::of_moves(&invert_moves(&[mv])))
+        .collect();
+    let colors = sticker_cube::colors(optimizer.mask());
+    let pack_table = PackTable::new(&colors);
+    let solved = solved_by_orientation(&tables, &colors, &pack_table, optimizer.up_to_auf());
+    // Orientations that the algorithm can end in.
+    let ends: Vec<Reorient> = Reorient::ALL
+        .iter()
+        .copied()
+        .filter(|&o| boundaries[moves.len()].is_none_or(|required| required == o))
+        .collect();
+
+    // First halves with some number of reorients, which are the same for
+    // consecutive numbers of reorients in the whole solution.
+    let mut halves = Halves::new();
+    let mut half_reorients = None;
+    let _search = interrupt::Search::start();
+    for reorients in 0..std::cmp::min(moves.len(), max_depth + 1) {
+        if verbose {
+            println!("Searching solutions with {reorients} reorients");
+        }
+        let start_time = Instant::now();
+        let first = match reorients {
+            0 => 0,
+            _ => reorients.div_ceil(2),
+        };
+        let mut nodes = 0;
+        let mut timed_out = false;
+        if half_reorients != Some(first) {
+            halves = Halves::new();
+            let mut forward_ctx = Context {
+                tables: &tables,
+                alg,
+                forbidden: cost_model.forbidden_moves(),
+                boundaries: &boundaries,
+                steps: &steps,
+                inverse_steps: &inverse_steps,
+                pack_table: &pack_table,
+                split_before: &[],
+                nodes: 0,
+                deadline,
+                timed_out: false,
+                nice: optimizer.nice(),
+            };
+            match first {
+                0 => {
+                    let key = (
+                        0,
+                        alg.start.sticker_cube().pack_with(&pack_table),
+                        alg.start,
+                    );
+                    halves.insert(key, vec![vec![]]);
+                }
+                _ => forward(
+                    alg.start.sticker_cube(),
+                    0,
+                    alg.start,
+                    first,
+                    &mut vec![],
+                    &mut forward_ctx,
+                    &mut halves,
+                ),
+            }
+            nodes += forward_ctx.nodes;
+            timed_out |= forward_ctx.timed_out;
+            half_reorients = Some(first);
+        }
+        let mut split_before = vec![false; moves.len()];
+        for &(i, _, _) in halves.keys() {
+            split_before[i] = true;
+        }
+
+        // Second halves are placed from each orientation that the algorithm
+        // can end in on separate threads.
+        let results = threads::map_on(optimizer.thread_count(), &ends, |&end| {
+            let mut backward_ctx = Context {
+                tables: &tables,
+                alg,
+                forbidden: cost_model.forbidden_moves(),
+                boundaries: &boundaries,
+                steps: &steps,
+                inverse_steps: &inverse_steps,
+                pack_table: &pack_table,
+                split_before: &split_before,
+                nodes: 0,
+                deadline,
+                timed_out: false,
+                nice: optimizer.nice(),
+            };
+            let mut solutions = vec![];
+            let last = moves.len() - 1;
+            backward(
+                &StickerCube::solved().apply(&inverse_steps[last]),
+                last,
+                end,
+                reorients - first,
+                first,
+                &mut vec![],
+                &mut backward_ctx,
+                &mut |inverse, i, orientation, second_half| {
+                    for cube in &solved[end as usize] {
+                        let key = (i, cube.then(inverse).pack_with(&pack_table), orientation);
+                        for first_half in halves.get(&key).into_iter().flatten() {
+                            let mut solution = first_half.clone();
+                            solution.extend(second_half.iter().rev());
+                            solutions.push(solution);
+                        }
+                    }
+                },
+            );
+            (solutions, backward_ctx.nodes, backward_ctx.timed_out)
+        });
+        for (solutions, backward_nodes, backward_timed_out) in results {
+            ret.solutions.extend(solutions);
+            nodes += backward_nodes;
+            timed_out |= backward_timed_out;
+        }
+        ret.nodes.push(nodes);
+        ret.reorient_count = reorients;
+        if verbose {
+            let remembered: usize = halves.values().map(Vec::len).sum();
+            match optimizer.deterministic() {
+                true => println!("Searched {nodes} nodes, remembering {remembered} first halves."),
+                false => println!(
+                    "Searched {nodes} nodes in {}, remembering {remembered} first halves.",
+                    plan::format_duration(start_time.elapsed().as_secs_f64()),
+                ),
+            }
+        }
+        if timed_out {
+            ret.interrupted = interrupt::requested();
+            ret.timed_out = !ret.interrupted;
+            break;
+        }
+        if !ret.solutions.is_empty() {
+            break;
+        }
+    }
+
+    if ret.solutions.is_empty() && !ret.timed_out && !ret.interrupted {
+        ret.reorient_count = 0;
+    }
+    ret.solutions.sort();
+    ret.optimal = !ret.timed_out && !ret.interrupted && optimizer.admissible();
+    ret
+}
+
+/// Returns one cube for each packed state that the pruning table counts as
+/// solved, which is every state within one move of a solved state, grouped
+/// by the orientation that the algorithm must end in to reach it.
+///
+/// Reorients turn the centers and moves don't, so the centers of the end
+/// state are those of the orientation that the algorithm ends in.
+fn solved_by_orientation(
+    tables: &Tables,
+    colors: &Colors,
+    pack_table: &PackTable,
+    up_to_auf: bool,
+) -> Vec<Vec<StickerCube>> {
+    let turns: Vec<MoveTable> = tables
+        .table
+        .moves
+        .iter()
+        .map(|&mv| MoveTable::of_moves(&[mv]))
+        .collect();
+    let mut seen = HashSet::new();
+    let solved: Vec<StickerCube> = solved_states(up_to_auf)
+        .into_iter()
+        .flat_map(|cube| {
+            let turned: Vec<StickerCube> = turns.iter().map(|turn| cube.apply(turn)).collect();
+            std::iter::once(cube).chain(turned)
+        })
+        .filter(|cube| seen.insert(cube.pack_with(pack_table)))
+        .collect();
+    let centers =
+        |cube: &StickerCube| -> Vec<Face> { cube.facelets(colors).skip(4).step_by(9).collect() };
+    Reorient::ALL
+        .iter()
+        .map(|o| {
+            let end_centers = centers(o.sticker_cube());
+            solved
+                .iter()
+                .filter(|cube| centers(cube) == end_centers)
+                .copied()
+                .collect()
+        })
+        .collect()
+}
+
+/// Adds every placement of exactly `reorients` reorients from move `i` on,
+/// starting from `state` in `orientation` with the reorients in `solution`
+/// placed before it, to `halves`, stopping after the last reorient.
+fn forward(
+    state: &StickerCube,
+    i: usize,
+    orientation: Reorient,
+    reorients: usize,
+    solution: &mut Solution,
+    ctx: &mut Context,
+    halves: &mut Halves,
+) {
+    if ctx.visit() {
+        return;
+    }
+    let last = ctx.alg.moves.len() - 1;
+    if !ctx.allowed(i, orientation) || last - i < reorients {
+        return;
+    }
+    // Same pruning as the exhaustive search.
+    let moves_left = ctx.alg.moves.len() - i;
+    if ctx.tables.lower_bound(state) as usize > moves_left + 1 {
+        return;
+    }
+
+    let moved = state.apply(&ctx.steps[i]);
+    for &r in Reorient::ALL {
+        if !r.is_none() && !ctx.alg.allows_reorient(i) {
+            continue;
+        }
+        let state = moved.apply(r.move_table());
+        solution.push(r);
+        match (r.is_none(), reorients) {
+            (false, 1) => {
+                if ctx.tables.lower_bound(&state) as usize <= moves_left {
+                    let key = (i + 1, state.pack_with(ctx.pack_table), orientation.then(r));
+                    halves.entry(key).or_default().push(solution.clone());
+                }
+            }
+            (none, _) => forward(
+                &state,
+                i + 1,
+                orientation.then(r),
+                reorients - !none as usize,
+                solution,
+                ctx,
+                halves,
+            ),
+        }
+        solution.pop();
+    }
+}
+
+/// Calls `found` with every placement of exactly `reorients` reorients
+/// before move `i` and from gap `min_split` on, in reverse order, that the
+/// rest of the algorithm can be made after in `orientation`. `inverse` undoes
+/// the algorithm from move `i` on with the reorients in `solution`, which
+/// are also in reverse order.
+///
+/// `found` is called with the inverse of the algorithm from each move that
+/// first halves end before, the move, and the orientation there.
+#[allow(clippy::too_many_arguments)]
+fn backward(
+    inverse: &StickerCube,
+    i: usize,
+    orientation: Reorient,
+    reorients: usize,
+    min_split: usize,
+    solution: &mut Solution,
+    ctx: &mut Context,
+    found: &mut impl FnMut(&StickerCube, usize, Reorient, &Solution),
+) {
+    if ctx.visit() {
+        return;
+    }
+    if !ctx.allowed(i, orientation) || i < min_split + reorients {
+        return;
+    }
+    if reorients == 0 && ctx.split_before[i] {
+        found(inverse, i, orientation, solution);
+    }
+    if i == min_split {
+        return;
+    }
+
+    for &r in Reorient::ALL {
+        if !r.is_none() && (reorients == 0 || !ctx.alg.allows_reorient(i - 1)) {
+            continue;
+        }
+        solution.push(r);
+        backward(
+            &inverse
+                .apply(r.inverse().move_table())
+                .apply(&ctx.inverse_steps[i - 1]),
+            i - 1,
+            orientation.then(r.inverse()),
+            reorients - !r.is_none() as usize,
+            min_split,
+            solution,
+            ctx,
+            found,
+        );
+        solution.pop();
+    }
+}
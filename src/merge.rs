@@ -0,0 +1,138 @@
+//! Combining result files written with `--json`.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// Combine result files written with `--json`, such as the outputs of
+/// several shards, keeping the cheapest result for each algorithm.
+#[derive(clap::Args, Debug, Clone)]
+pub struct MergeArgs {
+    /// Result files with one JSON object per line.
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Print an alg sheet with the best solution for each algorithm instead
+    /// of JSON.
+    #[clap(long)]
+    sheet: bool,
+}
+
+pub fn run(args: &MergeArgs) -> Result<(), String> {
+    let mut results = vec![];
+    for path in &args.files {
+        results.extend(read(path)?);
+    }
+    for result in &merge(results) {
+        if !args.sheet {
+            println!("{result}");
+        } else if let Some(alg) = result["solutions"][0]["alg"].as_str() {
+            println!("{alg}");
+        } else {
+            println!("# No solutions: {}", result["input"].as_str().unwrap_or(""));
+        }
+    }
+    Ok(())
+}
+
+/// Combines `results`, keeping the first of the cheapest results for each
+/// algorithm and leaving out errors. Results from shards of the same input
+/// file are put back in the order of the file.
+pub fn merge(results: impl IntoIterator<Item = Value>) -> Vec<Value> {
+    let mut ret: Vec<Value> = vec![];
+    for result in results {
+        // Results for algorithms that couldn't be optimized have nothing
+        // worth keeping.
+        if result.get("error").is_some() {
+            continue;
+        }
+        match ret.iter_mut().find(|r| r["input"] == result["input"]) {
+            Some(existing) => {
+                if best_etm(&result) < best_etm(existing) {
+                    *existing = result;
+                }
+            }
+            None => ret.push(result),
+        }
+    }
+    if ret.iter().all(|r| r["line"].is_u64()) {
+        ret.sort_by_key(|r| r["line"].as_u64());
+    }
+    ret
+}
+
+/// Reads a result file written with `--json`.
+pub fn read(path: &Path) -> Result<Vec<Value>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("error reading {}: {e}", path.display()))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let result: Value = serde_json::from_str(line)
+                .map_err(|e| format!("{}:{}: {e}", path.display(), i + 1))?;
+            match result["input"].is_string() {
+                true => Ok(result),
+                false => Err(format!("{}:{}: not a result", path.display(), i + 1)),
+            }
+        })
+        .collect()
+}
+
+/// Returns the ETM of the cheapest solution in a result, or `u64::MAX` if it
+/// has no solutions.
+pub fn best_etm(result: &Value) -> u64 {
+    result["solutions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|s| s["etm"].as_u64())
+        .min()
+        .unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Returns a result for `input` from line `line` with solutions of the
+    /// given ETM.
+    fn result(input: &str, line: u64, etms: &[u64]) -> Value {
+        let solutions: Vec<Value> = etms.iter().map(|&etm| json!({ "etm": etm })).collect();
+        json!({ "input": input, "line": line, "solutions": solutions })
+    }
+
+    #[test]
+    fn merge_keeps_the_cheapest_result_in_file_order() {
+        let merged = merge([
+            result("R U", 2, &[9]),
+            json!({ "input": "R Q", "line": 3, "error": "unsupported move \"Q\"" }),
+            result("F R", 1, &[]),
+            result("R U", 2, &[8, 10]),
+            result("R U", 2, &[8]),
+            result("F R", 1, &[12]),
+        ]);
+        assert_eq!(
+            merged,
+            [result("F R", 1, &[12]), result("R U", 2, &[8, 10])],
+        );
+        assert_eq!(best_etm(&merged[1]), 8);
+        assert_eq!(best_etm(&result("R U", 2, &[])), u64::MAX);
+    }
+
+    #[test]
+    fn read_rejects_lines_that_arent_results() {
+        let path = std::env::temp_dir().join(format!("rocket-merge-{}.json", std::process::id()));
+        std::fs::write(&path, "{\"input\": \"R U\"}\n\n{\"input\": 1}\n").unwrap();
+        let error = read(&path).unwrap_err();
+        std::fs::write(&path, "{\"input\": \"R U\"}\n\n").unwrap();
+        let results = read(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(error.ends_with(":3: not a result"), "{error}");
+        assert_eq!(results, Ok(vec![json!({ "input": "R U" })]));
+    }
+}
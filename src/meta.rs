@@ -0,0 +1,88 @@
+//! Records of how results were produced, so that a published algorithm can
+//! be traced back to the version and settings that found it and reproduced.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::cost::CostModel;
+
+/// Version of rocket that produced the results.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Returns a JSON object recording the version, `settings`, and
+/// `cost_model` that produced a result, along with when it was produced and
+/// how long it took if `elapsed` is known. The time and duration are left out
+/// if the output is `deterministic`, meaning the same on every run.
+pub fn record(
+    settings: &Value,
+    cost_model: &CostModel,
+    elapsed: Option<Duration>,
+    deterministic: bool,
+) -> Value {
+    let mut meta = json!({
+        "version": VERSION,
+        "settings": settings,
+        "cost_model": cost_model.fingerprint(),
+    });
+    if !deterministic {
+        meta["timestamp"] = timestamp().into();
+        if let Some(elapsed) = elapsed {
+            meta["elapsed_ms"] = (elapsed.as_millis() as u64).into();
+        }
+    }
+    meta
+}
+
+/// Returns the 64-bit FNV-1a hash of `s`, which is the same on every run,
+/// unlike the hashers in `std`.
+pub fn hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Returns the current time in UTC, such as `2024-03-09T14:05:00Z`.
+pub fn timestamp() -> String {
+    let secs = now();
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        date(secs),
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+/// Returns today's date in UTC, such as `2024-03-09`.
+pub fn today() -> String {
+    date(now())
+}
+
+/// Returns the number of seconds since 1970-01-01 in UTC.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Returns the date `secs` seconds after 1970-01-01 in UTC.
+fn date(secs: u64) -> String {
+    // Convert days since 1970-01-01 to a date, counting years from March so
+    // that leap days come last.
+    let days = (secs / 86400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
+}
@@ -0,0 +1,88 @@
+//! Notations for writing reorients.
+
+use cubesim::Move;
+
+use crate::{display_move, CostModel, Reorient};
+
+/// Notation for writing reorients.
+#[derive(clap::ArgEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Notation {
+    /// Twists of the O cell by 3D rotations, such as `Oxy`.
+    Xyz,
+    /// Hyperspeedcube sticker keybinds, such as `23I:DBL`.
+    Sticker,
+    /// The 4D twists that the executor performs, such as `{2-3}Ixy`.
+    #[clap(name = "4d")]
+    Twist,
+    /// Standard hypercubing notation for sticker twists, such as
+    /// `{2-3}IDBL`.
+    Hypercubing,
+}
+/// Returns the layer mask that selects every layer except the I cell.
+fn outer_layers(puzzle_size: u8) -> &'static str {
+    match puzzle_size {
+        2 => "{2}",
+        _ => "{2-3}",
+    }
+}
+
+impl Reorient {
+    /// Returns the name of the reorient in standard hypercubing notation on a
+    /// 4D puzzle of size `puzzle_size`: the layer mask, then the I cell, then
+    /// the sticker to twist around.
+    pub fn hypercubing_name(self, puzzle_size: u8) -> String {
+        let sticker = self.sticker_name().split_once(':').map_or("", |(_, s)| s);
+        format!("{}I{sticker}", outer_layers(puzzle_size))
+    }
+
+    /// Returns the name of the reorient in a notation, or the name of the
+    /// macro that performs it if that is cheaper under `cost_model`.
+    pub fn name(self, notation: Notation, puzzle_size: u8, cost_model: &CostModel) -> String {
+        if let Some(m) = cost_model.macro_for(self) {
+            return m.name.clone();
+        }
+        match notation {
+            Notation::Xyz => self.xyz_name().to_string(),
+            Notation::Sticker => cost_model.localize(self.sticker_name()),
+            Notation::Twist => {
+                cost_model.localize(&twist_name(&cost_model.expansion(self), puzzle_size))
+            }
+            Notation::Hypercubing => cost_model.localize(&self.hypercubing_name(puzzle_size)),
+        }
+    }
+
+    /// Parses a reorient in standard hypercubing notation, for any puzzle
+    /// size.
+    pub fn from_hypercubing_name(s: &str) -> Option<Self> {
+        let sticker = ["{2-3}I", "{2}I"]
+            .iter()
+            .find_map(|layers| s.strip_prefix(layers))?;
+        Reorient::ALL[1..]
+            .iter()
+            .copied()
+            .find(|r| r.sticker_name().split_once(':').map(|(_, s)| s) == Some(sticker))
+    }
+}
+
+/// Returns the 4D twist that rotates every layer except the I cell by
+/// `rotation`.
+fn twist_name(rotation: &[Move], puzzle_size: u8) -> String {
+    let rotation: String = rotation.iter().copied().map(display_move).collect();
+    format!("{}I{rotation}", outer_layers(puzzle_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Reorient;
+
+    #[test]
+    fn hypercubing_names_round_trip() {
+        for size in [2, 3] {
+            for &r in &Reorient::ALL[1..] {
+                let name = r.hypercubing_name(size);
+                assert_eq!(Reorient::from_hypercubing_name(&name), Some(r), "{name}");
+                assert_eq!(name.parse(), Ok(r), "{name}");
+            }
+        }
+    }
+}
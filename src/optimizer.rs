@@ -0,0 +1,1971 @@
+//! Search settings gathered into one value, so that searches with different
+//! settings can run in the same process.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+use cubesim::{FaceletCube, Move};
+
+use crate::interrupt::{self, CancelToken};
+use crate::notation::Notation;
+use crate::pdb::PatternDatabases;
+#[cfg(feature = "plugins")]
+use crate::plugin::Plugin;
+use crate::progress::{Counts, Progress};
+#[cfg(feature = "plugins")]
+use crate::sticker_cube::Colors;
+use crate::sticker_cube::{self, MoveTable, PackTable, StickerCube};
+use crate::subgroup::{self, SubgroupTable};
+use crate::table::{self, PruningTable};
+use crate::tree::{self, Outcome, SearchTree};
+use crate::{
+    cache_path, display_move, make_table, mask, plan, solved_states, symmetry, threads, Alg,
+    CostModel, Options, Reorient, Solution, ALL_SOLVER_FACES, SHALLOW_TABLE_DEPTH,
+};
+
+/// Number of search nodes between checks of the deadline.
+const DEADLINE_INTERVAL: usize = 1024;
+/// Fraction of the growth of the unpruned search at which each reorient
+/// grows the search so much that the pruning table is too shallow to help.
+const WEAK_TABLE_GROWTH: f64 = 0.5;
+/// Deepest pruning table that searches offer to deepen to.
+const MAX_DEEPENED_DEPTH: u8 = table::MAX_DEPTH;
+/// Seconds that an iteration must be projected to take for a search that
+/// can't ask to deepen the pruning table to warn that it is too shallow.
+const WEAK_TABLE_WARNING_SECS: f64 = 60.0;
+/// Most failed nodes that a single search remembers, which takes a few dozen
+/// megabytes.
+const MEMO_CAPACITY: usize = 1 << 19;
+/// Number of reorients recorded in a [`Memo`] for nodes that have no
+/// solutions with any number of reorients.
+const REFUTED: usize = usize::MAX;
+
+/// Most reorients that each node, identified by its state, gap, and
+/// orientation, is known to have no solutions with. The same node is reached
+/// by many different reorients before it, so this saves searching it again.
+type Memo = HashMap<([u64; 3], usize, Reorient), usize>;
+
+/// Solutions found by [`RktOptimizer::search_until()`].
+pub struct SearchResult {
+    /// Number of reorients in each solution, or if the search timed out
+    /// without finding any, the number it was searching with.
+    pub reorient_count: usize,
+    pub solutions: Vec<Solution>,
+    /// Whether the search stopped at the deadline, in which case
+    /// `solutions` are the ones found before then with the fewest reorients
+    /// that could have any, and may be missing cheaper ones.
+    pub timed_out: bool,
+    /// Whether the search was stopped with Ctrl-C or a cancel token, in which
+    /// case `solutions` are the ones found before then, as if it had timed
+    /// out. See [`crate::interrupt`].
+    pub interrupted: bool,
+    /// Number of nodes visited in each iteration, starting from 0 reorients.
+    pub nodes: Vec<usize>,
+    /// Whether `solutions` are proven to be every solution with the fewest
+    /// reorients, which they aren't if the search timed out or was
+    /// interrupted or the bounds could have pruned solutions. See
+    /// [`RktOptimizer::admissible()`].
+    pub optimal: bool,
+}
+
+/// Pruning table and pattern databases that bound the moves left, along
+/// with what searches need to look states up in them.
+pub(crate) struct Tables {
+    pub(crate) table: Arc<PruningTable>,
+    /// Pattern databases to bound states with along with the pruning table,
+    /// if any.
+    pattern_databases: Option<Arc<PatternDatabases>>,
+    /// Table for packing states with the colors of the optimizer's mask.
+    pub(crate) pack_table: PackTable,
+    /// Tables of the turns of a few faces that have been built for these
+    /// tables, or `None` for faces whose table can't bound searches, by
+    /// faces. See [`crate::subgroup`].
+    subgroup_tables: Mutex<Vec<(u8, Option<Arc<SubgroupTable>>)>>,
+    /// Heuristic plugin to bound states with, if any.
+    #[cfg(feature = "plugins")]
+    plugin: Option<Arc<Plugin>>,
+    /// Colors of the solved cube, with the stickers in the mask blank, for
+    /// the heuristic plugin.
+    #[cfg(feature = "plugins")]
+    colors: Colors,
+    /// Whether the pruning table counts states solved up to a turn of the U
+    /// layer as solved, which the heuristic plugin doesn't know about.
+    #[cfg(feature = "plugins")]
+    up_to_auf: bool,
+}
+impl Tables {
+    /// Returns a lower bound on the number of moves needed to reach any
+    /// rotation of the solved cube, or any state that counts as solved, from
+    /// the pruning table, any pattern databases, and any heuristic plugin.
+    ///
+    /// This doesn't depend on the algorithm. Searches that can only turn a
+    /// few faces also take the bound of a table of those faces' turns; see
+    /// [`crate::subgroup`].
+    pub(crate) fn lower_bound(&self, state: &StickerCube) -> i32 {
+        let bound = self
+            .table
+            .lower_bound_packed(state.pack_with(&self.pack_table));
+        let bound = match &self.pattern_databases {
+            Some(databases) => bound.max(databases.lower_bound(state)),
+            None => bound,
+        };
+        // The plugin counts the AUF as one more move.
+        #[cfg(feature = "plugins")]
+        let bound = match &self.plugin {
+            Some(plugin) => {
+                bound.max(plugin.lower_bound(state.facelets(&self.colors)) - self.up_to_auf as i32)
+            }
+            None => bound,
+        };
+        bound
+    }
+}
+
+/// Settings, pruning table, cost model, mask, and notation for searching and
+/// printing solutions.
+#[derive(Clone)]
+pub struct RktOptimizer {
+    depth: u8,
+    mask: u64,
+    cost_model: CostModel,
+    notation: Notation,
+    puzzle_size: u8,
+    /// Faces whose turns the pruning table is built from, a bitmask as
+    /// returned by [`crate::parse_solver_faces()`].
+    solver_faces: u8,
+    /// Directory that tables are saved to and loaded from, if any.
+    table_cache_dir: Option<PathBuf>,
+    /// Most memory that building the pruning table may take, in bytes.
+    max_table_memory: usize,
+    /// Whether pruning tables that aren't in the table cache are built by
+    /// deepening shallower ones that are.
+    extend_cached_tables: bool,
+    /// Whether to start with a shallow pruning table and build the full
+    /// tables in the background.
+    lazy_tables: bool,
+    /// Whether the cube counts as solved when it is solved up to a turn of
+    /// the U layer.
+    up_to_auf: bool,
+    /// Whether searches also use pattern databases.
+    use_pattern_databases: bool,
+    /// Whether to remember nodes without solutions.
+    transpositions: bool,
+    /// Whether to search more reorients for cheaper solutions.
+    optimal_etm: bool,
+    /// Token that stops searches when cancelled, if any.
+    cancel_token: Option<CancelToken>,
+    /// Number of threads to search on.
+    thread_count: usize,
+    /// Whether output is reproducible.
+    deterministic: bool,
+    /// Whether searches yield to other threads regularly.
+    nice: bool,
+    /// Heuristic plugin whose bound searches also take, if any.
+    #[cfg(feature = "plugins")]
+    plugin: Option<Arc<Plugin>>,
+    /// Tables built from the settings above, or `None` until they are first
+    /// needed. Clones share them, and see the full tables once the ones
+    /// built in the background are ready, and the deeper pruning table once
+    /// a search deepens it.
+    tables: Arc<RwLock<Option<Arc<Tables>>>>,
+}
+impl RktOptimizer {
+    /// Returns an optimizer whose pruning table has depth `depth` and ignores
+    /// the stickers in `mask`, using XYZ notation. Its tables are built when
+    /// they are first needed, or by [`RktOptimizer::init_tables()`].
+    pub fn new(depth: u8, mask: u64, cost_model: CostModel) -> Self {
+        Self {
+            depth,
+            mask,
+            cost_model,
+            notation: Notation::Xyz,
+            puzzle_size: 3,
+            solver_faces: ALL_SOLVER_FACES,
+            table_cache_dir: None,
+            max_table_memory: usize::MAX,
+            extend_cached_tables: false,
+            lazy_tables: false,
+            up_to_auf: false,
+            use_pattern_databases: false,
+            transpositions: true,
+            optimal_etm: false,
+            cancel_token: None,
+            thread_count: 1,
+            deterministic: false,
+            nice: false,
+            #[cfg(feature = "plugins")]
+            plugin: None,
+            tables: Arc::default(),
+        }
+    }
+
+    /// Sets the notation used by [`RktOptimizer::display()`] and
+    /// [`RktOptimizer::reorient_name()`], and the size of the 4D puzzle,
+    /// which determines the twists in [`Notation::Twist`].
+    pub fn with_notation(mut self, notation: Notation, puzzle_size: u8) -> Self {
+        self.notation = notation;
+        self.puzzle_size = puzzle_size;
+        self
+    }
+
+    /// Replaces the cost model that searches minimize and that algorithms are
+    /// displayed with. The tables don't depend on it, so they are kept.
+    pub fn with_cost_model(mut self, cost_model: CostModel) -> Self {
+        self.cost_model = cost_model;
+        self
+    }
+
+    /// Makes searches stop when `token` is cancelled, returning the solutions
+    /// found so far with [`SearchResult::interrupted`] set.
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Searches on up to `count` threads instead of one. The solutions are
+    /// the same either way.
+    pub fn with_thread_count(mut self, count: usize) -> Self {
+        self.thread_count = count.max(1);
+        self
+    }
+
+    /// Sets whether output is reproducible: progress messages and records of
+    /// results leave out timings, and randomized analyses use a fixed seed.
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Sets whether searches yield to other threads regularly, as in
+    /// `--nice` mode. See also [`threads::be_nice()`].
+    pub fn with_nice(mut self, nice: bool) -> Self {
+        self.nice = nice;
+        self
+    }
+
+    /// Returns the number of threads that searches run on.
+    pub fn thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    /// Returns whether output is reproducible.
+    pub fn deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Returns whether searches yield to other threads regularly.
+    pub fn nice(&self) -> bool {
+        self.nice
+    }
+
+    /// Returns whether searches should stop, because Ctrl-C was pressed or
+    /// the cancel token was cancelled.
+    fn interrupted(&self) -> bool {
+        interrupt::requested()
+            || self
+                .cancel_token
+                .as_ref()
+                .is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Returns this optimizer with its settings changed by `change`, and
+    /// tables that are built for the new settings.
+    fn with_table_settings(mut self, change: impl FnOnce(&mut Self)) -> Self {
+        change(&mut self);
+        self.tables = Arc::default();
+        self
+    }
+
+    /// Makes searches also take the lower bound of `plugin`, if it has one.
+    /// Its costs aren't applied to the cost model; see
+    /// [`Plugin::apply_costs()`].
+    #[cfg(feature = "plugins")]
+    pub fn with_plugin(self, plugin: Arc<Plugin>) -> Self {
+        self.with_table_settings(|o| o.plugin = Some(plugin))
+    }
+
+    /// Builds the pruning table from turns of only the faces in `faces`, a
+    /// bitmask as returned by [`crate::parse_solver_faces()`], instead of all
+    /// six.
+    ///
+    /// The table then counts only turns of those faces, which gives tighter
+    /// bounds for algorithms that only turn them. A reorient changes which
+    /// faces the later moves turn relative to the stickers, though, so
+    /// solutions in which reorients turn the rest of the algorithm onto other
+    /// faces may be missed.
+    pub fn with_solver_faces(self, faces: u8) -> Self {
+        self.with_table_settings(|o| o.solver_faces = faces)
+    }
+
+    /// Sets the directory that tables are saved to after being built, and
+    /// loaded from instead of being built again.
+    pub fn with_table_cache_dir(self, dir: Option<PathBuf>) -> Self {
+        self.with_table_settings(|o| o.table_cache_dir = dir)
+    }
+
+    /// Limits the memory that building the pruning table may take, in bytes.
+    /// If the requested depth would take more, the table stops at a smaller
+    /// depth, and searches don't offer to deepen it past the limit.
+    pub fn with_max_table_memory(self, bytes: usize) -> Self {
+        self.with_table_settings(|o| o.max_table_memory = bytes)
+    }
+
+    /// Sets whether a pruning table that isn't saved in the table cache is
+    /// built by deepening the deepest shallower one that is, one move at a
+    /// time, instead of from scratch. This finds the same table either way.
+    pub fn with_extend_cached_tables(self, extend: bool) -> Self {
+        self.with_table_settings(|o| o.extend_cached_tables = extend)
+    }
+
+    /// Makes the tables available right away by starting with a shallow
+    /// pruning table, unless the full one is saved in the table cache, and
+    /// building the full tables in the background. Searches find the same
+    /// solutions either way, but searches that start before the full tables
+    /// are ready prune less.
+    pub fn with_lazy_tables(self, lazy: bool) -> Self {
+        self.with_table_settings(|o| o.lazy_tables = lazy)
+    }
+
+    /// Sets whether searches also bound the moves left with
+    /// [`PatternDatabases`] for the corners and each half of the edges. They
+    /// prune much more than the pruning table deep in long searches, but take
+    /// about 80 MiB and 20 seconds to build when they aren't in the table
+    /// cache, which is far longer than most searches take, so they are off by
+    /// default. They are built in the background with
+    /// [`with_lazy_tables()`](Self::with_lazy_tables()). Searches find the
+    /// same solutions either way.
+    pub fn with_pattern_databases(self, enabled: bool) -> Self {
+        self.with_table_settings(|o| o.use_pattern_databases = enabled)
+    }
+
+    /// Sets whether the cube counts as solved when one turn of the U layer
+    /// would solve it, for last-layer algorithms whose AUF is decided when
+    /// they are executed.
+    pub fn with_up_to_auf(self, up_to_auf: bool) -> Self {
+        self.with_table_settings(|o| o.up_to_auf = up_to_auf)
+    }
+
+    /// Sets whether searches remember nodes that have no solutions, so that
+    /// they aren't searched again when reached by other reorients. This finds
+    /// the same solutions either way, but remembering costs more than it
+    /// saves for short algorithms.
+    pub fn with_transpositions(mut self, transpositions: bool) -> Self {
+        self.transpositions = transpositions;
+        self
+    }
+
+    /// Sets whether searches that find solutions go on to search more
+    /// reorients for as long as they could still find cheaper solutions, so
+    /// that they find the cheapest solutions with at most the maximum number
+    /// of reorients instead of the cheapest with the fewest.
+    pub fn with_optimal_etm(mut self, optimal_etm: bool) -> Self {
+        self.optimal_etm = optimal_etm;
+        self
+    }
+
+    /// Builds the tables if they haven't been, or starts building them in the
+    /// background with [`RktOptimizer::with_lazy_tables()`], which otherwise
+    /// happens when they are first needed.
+    pub fn init_tables(&self) {
+        self.tables();
+    }
+
+    /// Returns the tables, building them if needed.
+    pub(crate) fn tables(&self) -> Arc<Tables> {
+        if let Some(tables) = &*self.tables.read().unwrap() {
+            return Arc::clone(tables);
+        }
+        let mut tables = self.tables.write().unwrap();
+        Arc::clone(tables.get_or_insert_with(|| Arc::new(self.build_tables())))
+    }
+
+    /// Returns tables built from this optimizer's settings, or if they are
+    /// built in the background, a shallow pruning table for now.
+    fn build_tables(&self) -> Tables {
+        // A table saved in the table cache is loaded right away.
+        let ready = self.depth <= SHALLOW_TABLE_DEPTH
+            || self.cache_path(self.depth).is_some_and(|p| p.exists());
+        if !self.lazy_tables || (ready && !self.use_pattern_databases) {
+            let table = Arc::new(self.make_table(self.depth));
+            return self.tables_with(table, self.make_pattern_databases());
+        }
+
+        let depth = match ready {
+            true => self.depth,
+            false => SHALLOW_TABLE_DEPTH,
+        };
+        let table = Arc::new(self.make_table(depth));
+        let this = self.clone();
+        let mut full_table = Arc::clone(&table);
+        std::thread::spawn(move || {
+            if !ready {
+                full_table = Arc::new(this.make_table(this.depth));
+                this.share_tables(Arc::clone(&full_table), None);
+            }
+            if let Some(databases) = this.make_pattern_databases() {
+                this.share_tables(full_table, Some(databases));
+            }
+        });
+        self.tables_with(table, None)
+    }
+
+    /// Makes clones use this pruning table and these pattern databases,
+    /// unless a search has [deepened](RktOptimizer::deepened) the pruning
+    /// table past `table` in the meantime, in which case they keep that one.
+    fn share_tables(
+        &self,
+        table: Arc<PruningTable>,
+        pattern_databases: Option<Arc<PatternDatabases>>,
+    ) {
+        let mut tables = self.tables.write().unwrap();
+        let table = match &*tables {
+            Some(current) if current.table.depth() > table.depth() => Arc::clone(&current.table),
+            _ => table,
+        };
+        *tables = Some(Arc::new(self.tables_with(table, pattern_databases)));
+    }
+
+    /// Returns tables with this pruning table and these pattern databases.
+    fn tables_with(
+        &self,
+        table: Arc<PruningTable>,
+        pattern_databases: Option<Arc<PatternDatabases>>,
+    ) -> Tables {
+        Tables {
+            table,
+            pattern_databases,
+            pack_table: PackTable::new(&sticker_cube::colors(self.mask)),
+            subgroup_tables: Mutex::new(vec![]),
+            #[cfg(feature = "plugins")]
+            plugin: self.plugin.clone(),
+            #[cfg(feature = "plugins")]
+            colors: sticker_cube::colors(self.mask),
+            #[cfg(feature = "plugins")]
+            up_to_auf: self.up_to_auf,
+        }
+    }
+
+    /// Returns a pruning table with depth `depth` and this optimizer's other
+    /// settings. Only tables of the full depth are saved to the table cache.
+    fn make_table(&self, depth: u8) -> PruningTable {
+        let cache_dir = self
+            .table_cache_dir
+            .as_deref()
+            .filter(|_| depth == self.depth);
+        make_table(
+            depth,
+            self.mask,
+            self.solver_faces,
+            self.up_to_auf,
+            cache_dir,
+            self.extend_cached_tables,
+            self.max_table_memory,
+        )
+    }
+
+    /// Returns the pattern databases if searches use them.
+    fn make_pattern_databases(&self) -> Option<Arc<PatternDatabases>> {
+        self.use_pattern_databases.then(|| {
+            Arc::new(PatternDatabases::new(
+                self.mask,
+                self.up_to_auf,
+                self.table_cache_dir.as_deref(),
+            ))
+        })
+    }
+
+    /// Returns the file in the table cache that the pruning table with depth
+    /// `depth` and this optimizer's other settings is saved to, if there is a
+    /// table cache.
+    fn cache_path(&self, depth: u8) -> Option<PathBuf> {
+        cache_path(
+            depth,
+            self.mask,
+            self.solver_faces,
+            self.up_to_auf,
+            self.table_cache_dir.as_deref(),
+        )
+    }
+
+    /// Returns the file in the table cache that the pruning table is saved
+    /// to, if there is a table cache.
+    pub fn table_cache_path(&self) -> Option<PathBuf> {
+        self.cache_path(self.depth)
+    }
+
+    /// Returns this optimizer with its pruning table one move deeper, saved
+    /// to the table cache if there is one. Clones of this optimizer share the
+    /// deeper table, so that later searches with them use it too.
+    pub fn deepened(&self) -> Self {
+        let tables = self.tables();
+        let mut table = PruningTable::clone(&tables.table);
+        table.deepen(
+            &sticker_cube::colors(self.mask),
+            &solved_states(self.up_to_auf),
+        );
+        let depth = table.depth();
+        if let Some(path) = self.cache_path(depth) {
+            if let Err(e) = table.save(&path) {
+                eprintln!("Warning: {e}");
+            }
+        }
+        // Pattern databases built in the background may have been shared
+        // since, so take the latest ones.
+        let pattern_databases = self.tables().pattern_databases.clone();
+        self.share_tables(Arc::new(table), pattern_databases);
+        Self {
+            depth,
+            ..self.clone()
+        }
+    }
+
+    /// Returns roughly how much memory deepening the pruning table by one
+    /// move takes, in bytes, or `None` if it can't be deepened.
+    fn deepening_memory(&self) -> Option<usize> {
+        let table = &self.tables().table;
+        let depth = table.depth() + 1;
+        let initial_states = solved_states(self.up_to_auf).len();
+        let memory = table::projected_memory(initial_states, depth, table.moves.len())
+            + table.memory_usage();
+        (depth <= MAX_DEEPENED_DEPTH && memory <= self.max_table_memory).then_some(memory)
+    }
+
+    /// Returns the depth of the pruning table that was asked for, which a
+    /// shallower table stands in for while it is built in the background, and
+    /// a deeper one once a search has deepened it.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Returns the pruning table, building it if needed.
+    pub fn table(&self) -> Arc<PruningTable> {
+        Arc::clone(&self.tables().table)
+    }
+
+    /// Returns the pattern databases if they are used and ready, building
+    /// them if needed.
+    pub fn pattern_databases(&self) -> Option<Arc<PatternDatabases>> {
+        self.tables().pattern_databases.clone()
+    }
+
+    pub fn cost_model(&self) -> &CostModel {
+        &self.cost_model
+    }
+
+    /// Returns the stickers that the pruning table ignores, where bit `i` is
+    /// set if sticker `i` of the solved cube is ignored.
+    pub fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    /// Returns whether the cube counts as solved up to a turn of the U layer.
+    pub fn up_to_auf(&self) -> bool {
+        self.up_to_auf
+    }
+
+    /// Returns a solved cube whose ignored stickers are blank.
+    pub fn solved_cube(&self) -> FaceletCube {
+        mask::solved_cube(self.mask)
+    }
+
+    /// Returns a lower bound on the number of moves needed to reach any
+    /// rotation of the solved cube, or any state that counts as solved, from
+    /// the pruning table, any pattern databases, and any heuristic plugin.
+    pub fn lower_bound(&self, state: &StickerCube) -> i32 {
+        self.tables().lower_bound(state)
+    }
+
+    /// Returns whether lower bounds are the same for states that differ only
+    /// by a rotation before them, so that searches can skip branches that the
+    /// first move's symmetry makes interchangeable. See [`crate::symmetry`].
+    fn symmetric(&self) -> bool {
+        // A heuristic plugin's bound might not be, but then the bounds
+        // aren't admissible either.
+        self.admissible()
+    }
+
+    /// Returns a table of the turns of the faces that searches of `alg` can
+    /// turn, if the forbidden moves leave them only a few, building it the
+    /// first time it is needed with `tables`. It takes about as much memory
+    /// to build as the pruning table. See [`crate::subgroup`].
+    fn subgroup_table(&self, tables: &Tables, alg: &Alg) -> Option<Arc<SubgroupTable>> {
+        // The table can't tell when some stickers are ignored.
+        if self.mask != 0 {
+            return None;
+        }
+        let faces = subgroup::faces(alg, self.cost_model.forbidden_moves())?;
+        let mut subgroup_tables = tables.subgroup_tables.lock().unwrap();
+        if let Some((_, table)) = subgroup_tables.iter().find(|(f, _)| *f == faces) {
+            return table.clone();
+        }
+        let table = &tables.table;
+        let initial_states = solved_states(self.up_to_auf).len();
+        let max_memory = table::projected_memory(initial_states, table.depth(), table.moves.len());
+        let subgroup_table =
+            SubgroupTable::new(faces, self.up_to_auf, &table.moves, max_memory).map(Arc::new);
+        subgroup_tables.push((faces, subgroup_table.clone()));
+        subgroup_table
+    }
+
+    /// Returns whether the bounds never prune a solution, which they may if
+    /// the pruning table is built from only some faces' turns, or if a
+    /// heuristic plugin's bound overestimates.
+    pub fn admissible(&self) -> bool {
+        #[cfg(feature = "plugins")]
+        if self.plugin.as_ref().is_some_and(|p| p.has_lower_bound()) {
+            return false;
+        }
+        self.solver_faces == ALL_SOLVER_FACES
+    }
+
+    /// Returns every way to insert the fewest reorients into a rotationless
+    /// algorithm so that it solves the cube, cheapest first. Returns no
+    /// solutions if more than `opts.max_depth` reorients are needed.
+    pub fn optimize(&self, alg: &[Move], opts: &Options) -> Vec<Solution> {
+        let alg = Alg {
+            start: opts.start,
+            ..Alg::new(alg.to_vec())
+        };
+        let (_, mut solutions) = self.search(&alg, opts.max_depth, false, None, &mut None);
+        solutions.sort_by_key(|s| {
+            s.iter().map(|&r| self.cost_model.cost(r)).sum::<usize>() + alg.extra_moves(s)
+        });
+        solutions
+    }
+
+    /// Returns the name of a reorient in this optimizer's notation, or the
+    /// name of the macro that performs it if that is cheaper.
+    pub fn reorient_name(&self, r: Reorient) -> String {
+        r.name(self.notation, self.puzzle_size, &self.cost_model)
+    }
+
+    /// Formats an algorithm like [`Alg::display()`], using this optimizer's
+    /// notation and macros.
+    pub fn display(&self, alg: &Alg, solution: &Solution) -> String {
+        alg.display_with(solution, |r| match r.is_none() {
+            true => " ".to_string(),
+            false => format!(" {} ", self.reorient_name(r)),
+        })
+    }
+
+    /// Formats an algorithm like [`RktOptimizer::display()`], but with a
+    /// non-default starting orientation written as a pre-rotation of the
+    /// whole algorithm, as in `y: R U R' U'`.
+    pub fn display_pre_rotated(&self, alg: &Alg, solution: &Solution) -> String {
+        let rest = Alg {
+            start: Reorient::None,
+            ..alg.clone()
+        };
+        let rest = self.display(&rest, solution);
+        match alg.start {
+            Reorient::None => rest,
+            start => format!("{}: {rest}", start.pre_rotation_name()),
+        }
+    }
+
+    /// Searches for solutions with 0 reorients, then 1, and so on up to
+    /// `max_depth`, and returns the first number of reorients that has any
+    /// solutions along with all of them.
+    ///
+    /// If `verbose`, progress is printed. If `confirm_after` is given, asks
+    /// before starting an iteration projected to take longer than that many
+    /// seconds.
+    pub fn search(
+        &self,
+        alg: &Alg,
+        max_depth: usize,
+        verbose: bool,
+        confirm_after: Option<f64>,
+        tree: &mut Option<SearchTree>,
+    ) -> (usize, Vec<Solution>) {
+        let result = self.search_until(alg, max_depth, verbose, confirm_after, tree, None);
+        (result.reorient_count, result.solutions)
+    }
+
+    /// Searches like [`RktOptimizer::search()`], but stops at `deadline` if
+    /// it is given, keeping the solutions found so far.
+    pub fn search_until(
+        &self,
+        alg: &Alg,
+        max_depth: usize,
+        verbose: bool,
+        confirm_after: Option<f64>,
+        tree: &mut Option<SearchTree>,
+        deadline: Option<Instant>,
+    ) -> SearchResult {
+        let mut nodes_by_iteration = vec![];
+        let found = |reorient_count, solutions, timed_out, interrupted, nodes| SearchResult {
+            reorient_count,
+            solutions,
+            timed_out,
+            interrupted,
+            nodes,
+            optimal: !timed_out && !interrupted && self.admissible(),
+        };
+        let moves = &alg.moves;
+        let boundaries = alg.boundaries();
+        if moves.len() <= 1 {
+            return found(0, vec![vec![]], false, false, vec![]);
+        }
+        let cost_model = &self.cost_model;
+        // Trying cheap reorients first finds cheap solutions first.
+        let branch_order: Vec<Reorient> = Reorient::by_cost(cost_model)
+            .into_iter()
+            .flat_map(|(_, group)| group)
+            .collect();
+
+        // Branches at the first gap that the first move's symmetry makes
+        // interchangeable with an earlier branch have the same solutions, so
+        // only the first of each is searched. Recorded trees show every
+        // branch instead.
+        let symmetries = match tree.is_none() && self.symmetric() {
+            true => symmetry::first_gap(alg, self.mask, cost_model.forbidden_moves()),
+            false => vec![Reorient::None],
+        };
+        let symmetric: Vec<Reorient> = branch_order
+            .iter()
+            .copied()
+            .filter(|&r| symmetry::representative(r, &symmetries, &branch_order) != r)
+            .collect();
+        if verbose && !symmetric.is_empty() {
+            println!(
+                "The first move is symmetric, so {} of {} first reorients are searched",
+                branch_order.len() - symmetric.len(),
+                branch_order.len(),
+            );
+        }
+
+        let iterations = std::cmp::min(moves.len(), max_depth + 1);
+        let mut prev_nodes = None;
+        // Branches at the first gap that can't have solutions with any
+        // number of reorients, so later iterations can skip them.
+        let mut refuted = vec![];
+        // Nodes without solutions with some number of reorients still have
+        // none in later iterations.
+        let mut memo = Memo::new();
+        let mut branch_memos = vec![];
+        let (steps, suffixes) = step_tables(moves);
+        // This optimizer with a deeper pruning table, if the search deepened
+        // it.
+        let mut deepened: Option<RktOptimizer> = None;
+        // Held across the prompts between iterations too, so that Ctrl-C
+        // while one is waiting for an answer stops the search instead of
+        // exiting.
+        let _search = interrupt::Search::start();
+        for max_reorients in 0..iterations {
+            let this = deepened.as_ref().unwrap_or(self);
+            if verbose {
+                println!("Searching solutions with {} reorients", max_reorients);
+            }
+            let start_time = Instant::now();
+            let progress = verbose.then(|| Progress::start(max_reorients, self.deterministic()));
+            let root = tree
+                .as_mut()
+                .and_then(|t| t.add(None, format!("start ({max_reorients})"), 0, max_reorients));
+            let skip: Vec<Reorient> = refuted.iter().chain(&symmetric).copied().collect();
+            let tables = this.tables();
+            let subgroup = this.subgroup_table(&tables, alg);
+            let mut ctx = SearchContext {
+                optimizer: this,
+                tables: &tables,
+                subgroup: subgroup.as_deref(),
+                alg,
+                branch_order: &branch_order,
+                forbidden: cost_model.forbidden_moves(),
+                tree,
+                nodes: 0,
+                deadline,
+                timed_out: false,
+                skip: &skip,
+                refuted: vec![],
+                cut_off: false,
+                memo: std::mem::take(&mut memo),
+                memo_capacity: MEMO_CAPACITY,
+                branch_memos: std::mem::take(&mut branch_memos),
+                steps: &steps,
+                suffixes: &suffixes,
+                progress: progress.as_ref().map(Progress::counts),
+            };
+            let ret = dfs(
+                alg.start.sticker_cube(),
+                moves,
+                &boundaries,
+                alg.start,
+                max_reorients,
+                &mut ctx,
+                root,
+            );
+            let interrupted = this.interrupted();
+            let (nodes, timed_out) = (ctx.nodes, ctx.timed_out && !interrupted);
+            memo = ctx.memo;
+            branch_memos = ctx.branch_memos;
+            nodes_by_iteration.push(nodes);
+            refuted.extend(ctx.refuted);
+            drop(progress);
+            if !ret.is_empty() {
+                let solutions = ret
+                    .into_iter()
+                    .map(|mut solution| {
+                        // Solutions are reversed, because reasons.
+                        solution.reverse();
+                        solution
+                    })
+                    .collect();
+                let solutions =
+                    symmetry::expand(solutions, &symmetries, &branch_order, max_reorients);
+                if self.optimal_etm && !timed_out && !interrupted {
+                    let (reorient_count, solutions, timed_out, interrupted) = this.search_cheaper(
+                        alg,
+                        max_reorients,
+                        solutions,
+                        iterations - 1,
+                        verbose,
+                        deadline,
+                        &mut nodes_by_iteration,
+                    );
+                    return found(
+                        reorient_count,
+                        solutions,
+                        timed_out,
+                        interrupted,
+                        nodes_by_iteration,
+                    );
+                }
+                return found(
+                    max_reorients,
+                    solutions,
+                    timed_out,
+                    interrupted,
+                    nodes_by_iteration,
+                );
+            }
+            if timed_out || interrupted {
+                return found(
+                    max_reorients,
+                    vec![],
+                    timed_out,
+                    interrupted,
+                    nodes_by_iteration,
+                );
+            }
+
+            if verbose && max_reorients + 1 < iterations {
+                // Extrapolate from how much this iteration grew over the last
+                // one. The first iteration is too small to measure, so fall back
+                // on the unpruned tree sizes.
+                let gaps = moves.len() - 1;
+                let unpruned_growth =
+                    plan::max_nodes(gaps, max_reorients + 1) / plan::max_nodes(gaps, max_reorients);
+                let measured_growth = prev_nodes
+                    .filter(|&prev| prev > 1)
+                    .map(|prev| nodes as f64 / prev as f64);
+                let growth = measured_growth.unwrap_or(unpruned_growth);
+                let projected = start_time.elapsed().as_secs_f64() * growth;
+                if self.deterministic() {
+                    println!("Searched {nodes} nodes.");
+                } else {
+                    println!(
+                        "Searched {nodes} nodes in {}; the next iteration should take about {}.",
+                        plan::format_duration(start_time.elapsed().as_secs_f64()),
+                        plan::format_duration(projected),
+                    );
+                }
+                let slow = confirm_after.is_some_and(|limit| projected > limit);
+                let weak_table =
+                    measured_growth.is_some_and(|g| g >= unpruned_growth * WEAK_TABLE_GROWTH);
+                if let Some(memory) = this.deepening_memory().filter(|_| slow && weak_table) {
+                    let depth = tables.table.depth() + 1;
+                    println!(
+                        "The pruning table barely prunes this algorithm: this iteration grew \
+                         {growth:.1}x over the last one, and {unpruned_growth:.1}x without pruning.",
+                    );
+                    let question = format!(
+                        "Deepen the pruning table to depth {depth}, which takes about {:.1} MiB?",
+                        memory as f64 / (1 << 20) as f64,
+                    );
+                    if confirm(&question) {
+                        println!("Deepening the pruning table to depth {depth} ...");
+                        deepened = Some(this.deepened());
+                        // The next iteration prunes more than this one did,
+                        // so it doesn't say how much the search grows.
+                        prev_nodes = None;
+                        continue;
+                    }
+                }
+                if weak_table
+                    && confirm_after.is_none()
+                    && projected > WEAK_TABLE_WARNING_SECS
+                    && !self.deterministic()
+                {
+                    eprintln!(
+                        "Warning: the pruning table barely prunes this algorithm; a deeper one \
+                         would search faster"
+                    );
+                }
+                if slow && !confirm("Continue?") {
+                    if this.interrupted() {
+                        return found(max_reorients + 1, vec![], false, true, nodes_by_iteration);
+                    }
+                    println!(
+                        "Stopped before searching with {} reorients.",
+                        max_reorients + 1
+                    );
+                    // Declining isn't a timeout or an interrupt, but the
+                    // search is just as incomplete.
+                    return SearchResult {
+                        optimal: false,
+                        ..found(0, vec![], false, false, nodes_by_iteration)
+                    };
+                }
+                prev_nodes = Some(nodes);
+            }
+        }
+
+        found(0, vec![], false, false, nodes_by_iteration)
+    }
+
+    /// Searches like [`RktOptimizer::search_until()`], but deepens on the
+    /// ETM that the reorients add instead of on how many there are, so that
+    /// the solutions found are the cheapest with at most `max_depth`
+    /// reorients, even if cheaper reorients need more of them. Of the
+    /// cheapest solutions, only the ones with the fewest reorients, which are
+    /// the shortest in STM, are returned.
+    ///
+    /// This is IDA*: each iteration searches every placement that adds at
+    /// most some number of ETM, pruning states that the pruning table says
+    /// the rest of the algorithm can't solve, and the next iteration allows
+    /// just enough more ETM to place one of the reorients that were skipped.
+    pub fn search_cost(
+        &self,
+        alg: &Alg,
+        max_depth: usize,
+        verbose: bool,
+        deadline: Option<Instant>,
+    ) -> SearchResult {
+        let mut nodes_by_iteration = vec![];
+        let found = |reorient_count, solutions, timed_out, interrupted, nodes| SearchResult {
+            reorient_count,
+            solutions,
+            timed_out,
+            interrupted,
+            nodes,
+            optimal: !timed_out && !interrupted && self.admissible(),
+        };
+        if alg.moves.len() <= 1 {
+            return found(0, vec![vec![]], false, false, vec![]);
+        }
+        let branch_order: Vec<Reorient> = Reorient::by_cost(&self.cost_model)
+            .into_iter()
+            .flat_map(|(_, group)| group)
+            .collect();
+        let boundaries = alg.boundaries();
+        let (steps, suffixes) = step_tables(&alg.moves);
+        let tables = self.tables();
+        let subgroup = self.subgroup_table(&tables, alg);
+        let mut ctx = CostContext {
+            optimizer: self,
+            tables: &tables,
+            subgroup: subgroup.as_deref(),
+            alg,
+            branch_order: &branch_order,
+            forbidden: self.cost_model.forbidden_moves(),
+            boundaries: &boundaries,
+            steps: &steps,
+            suffixes: &suffixes,
+            nodes: 0,
+            deadline,
+            timed_out: false,
+            shortfall: None,
+        };
+        // Each reorient adds at most its cost and a move split off a double
+        // move, so once every placement adding some ETM has been searched,
+        // so has every placement with few enough reorients.
+        let most_expensive = Reorient::ALL[1..]
+            .iter()
+            .map(|&r| self.cost_model.cost(r).saturating_add(1))
+            .max()
+            .unwrap_or(1);
+        // Number of reorients that every solution has at least, as far as
+        // the finished iterations show.
+        let mut reorients_reached = 0;
+
+        let mut budget = 0;
+        loop {
+            if verbose {
+                println!("Searching solutions adding {budget} ETM");
+            }
+            let start_time = Instant::now();
+            let (mut solutions, interrupted) = search_budget(&mut ctx, max_depth, budget);
+            let (nodes, timed_out) = (ctx.nodes, ctx.timed_out && !interrupted);
+            nodes_by_iteration.push(nodes);
+            if !solutions.is_empty() {
+                let fewest = solutions.iter().map(count_reorients).min().unwrap_or(0);
+                solutions.retain(|s| count_reorients(s) == fewest);
+                return found(
+                    fewest,
+                    solutions,
+                    timed_out,
+                    interrupted,
+                    nodes_by_iteration,
+                );
+            }
+            if timed_out || interrupted {
+                return found(
+                    reorients_reached,
+                    vec![],
+                    timed_out,
+                    interrupted,
+                    nodes_by_iteration,
+                );
+            }
+            reorients_reached = (budget / most_expensive + 1).min(max_depth);
+            // More ETM wouldn't help if no placement ran out of it.
+            let Some(shortfall) = ctx.shortfall else {
+                break;
+            };
+            if verbose {
+                match self.deterministic() {
+                    true => println!("Searched {nodes} nodes."),
+                    false => println!(
+                        "Searched {nodes} nodes in {}.",
+                        plan::format_duration(start_time.elapsed().as_secs_f64()),
+                    ),
+                }
+            }
+            budget += shortfall;
+        }
+        found(0, vec![], false, false, nodes_by_iteration)
+    }
+
+    /// Searches for solutions cheaper than `solutions`, which are every
+    /// solution with `reorient_count` reorients, with more reorients up to
+    /// `max_depth`, for as long as the cheapest reorients could still make a
+    /// cheaper one. Returns the number of reorients and the solutions with
+    /// the fewest reorients of the cheapest ones found, or `solutions` if
+    /// none are cheaper, and whether the search timed out or was
+    /// interrupted. Nodes visited are added to `nodes_by_iteration`.
+    #[allow(clippy::too_many_arguments)]
+    fn search_cheaper(
+        &self,
+        alg: &Alg,
+        reorient_count: usize,
+        solutions: Vec<Solution>,
+        max_depth: usize,
+        verbose: bool,
+        deadline: Option<Instant>,
+        nodes_by_iteration: &mut Vec<usize>,
+    ) -> (usize, Vec<Solution>, bool, bool) {
+        let cost = |s: &Solution| {
+            s.iter().map(|&r| self.cost_model.cost(r)).sum::<usize>() + alg.extra_moves(s)
+        };
+        let cheapest_reorient = Reorient::ALL[1..]
+            .iter()
+            .map(|&r| self.cost_model.cost(r))
+            .min()
+            .unwrap_or(0);
+        let branch_order: Vec<Reorient> = Reorient::by_cost(&self.cost_model)
+            .into_iter()
+            .flat_map(|(_, group)| group)
+            .collect();
+        let boundaries = alg.boundaries();
+        let (steps, suffixes) = step_tables(&alg.moves);
+        let tables = self.tables();
+        let subgroup = self.subgroup_table(&tables, alg);
+        let mut ctx = CostContext {
+            optimizer: self,
+            tables: &tables,
+            subgroup: subgroup.as_deref(),
+            alg,
+            branch_order: &branch_order,
+            forbidden: self.cost_model.forbidden_moves(),
+            boundaries: &boundaries,
+            steps: &steps,
+            suffixes: &suffixes,
+            nodes: 0,
+            deadline,
+            timed_out: false,
+            shortfall: None,
+        };
+
+        let mut best = solutions.iter().map(cost).min().unwrap_or(0);
+        let mut ret = (reorient_count, solutions);
+        for max_reorients in reorient_count + 1..=max_depth {
+            // Every solution with fewer reorients that is cheaper than the
+            // best has been found already.
+            if cheapest_reorient * max_reorients >= best {
+                break;
+            }
+            if verbose {
+                println!("Searching solutions with {max_reorients} reorients adding less than {best} ETM");
+            }
+            let start_time = Instant::now();
+            let (mut solutions, interrupted) = search_budget(&mut ctx, max_reorients, best - 1);
+            let (nodes, timed_out) = (ctx.nodes, ctx.timed_out && !interrupted);
+            nodes_by_iteration.push(nodes);
+            if let Some(cheapest) = solutions.iter().map(cost).min() {
+                solutions.retain(|s| cost(s) == cheapest);
+                let fewest = solutions.iter().map(count_reorients).min().unwrap_or(0);
+                solutions.retain(|s| count_reorients(s) == fewest);
+                best = cheapest;
+                ret = (fewest, solutions);
+            }
+            if timed_out || interrupted {
+                return (ret.0, ret.1, timed_out, interrupted);
+            }
+            if verbose {
+                match self.deterministic() {
+                    true => println!("Searched {nodes} nodes."),
+                    false => println!(
+                        "Searched {nodes} nodes in {}.",
+                        plan::format_duration(start_time.elapsed().as_secs_f64()),
+                    ),
+                }
+            }
+        }
+        (ret.0, ret.1, false, false)
+    }
+}
+
+/// Searches once for every solution with at most `max_reorients` reorients
+/// that add at most `budget` ETM, starting `ctx` over. Returns the solutions
+/// and whether the search was interrupted.
+fn search_budget(
+    ctx: &mut CostContext,
+    max_reorients: usize,
+    budget: usize,
+) -> (Vec<Solution>, bool) {
+    ctx.nodes = 0;
+    ctx.timed_out = false;
+    ctx.shortfall = None;
+    let alg = ctx.alg;
+    let search = interrupt::Search::start();
+    let ret = dfs_cost(
+        alg.start.sticker_cube(),
+        0,
+        alg.start,
+        max_reorients,
+        budget,
+        ctx,
+    );
+    let interrupted = ctx.optimizer.interrupted();
+    drop(search);
+    let solutions = ret
+        .into_iter()
+        .map(|mut solution| {
+            solution.reverse();
+            solution
+        })
+        .collect();
+    (solutions, interrupted)
+}
+
+fn count_reorients(solution: &Solution) -> usize {
+    solution.iter().filter(|r| !r.is_none()).count()
+}
+
+/// Returns a table for each move of an algorithm, and one for each suffix of
+/// it, from each gap on, including the empty suffix at the end.
+pub(crate) fn step_tables(moves: &[Move]) -> (Vec<MoveTable>, Vec<MoveTable>) {
+    let step_cubes: Vec<StickerCube> = moves
+        .iter()
+        .map(|&mv| StickerCube::of_moves(&[mv]))
+        .collect();
+    let steps = step_cubes.iter().map(MoveTable::new).collect();
+    // Every leaf makes the rest of the moves without reorients, so make each
+    // suffix of the algorithm in one step.
+    let mut suffix = StickerCube::solved();
+    let mut suffixes = vec![MoveTable::new(&suffix)];
+    for step in step_cubes.iter().rev() {
+        suffix = step.then(&suffix);
+        suffixes.push(MoveTable::new(&suffix));
+    }
+    suffixes.reverse();
+    (steps, suffixes)
+}
+
+/// Returns a lower bound like [`Tables::lower_bound()`] on the moves needed
+/// to solve `state`, reached in `orientation`, that is also at least the
+/// subgroup table's bound if there is one.
+fn lower_bound(
+    tables: &Tables,
+    subgroup: Option<&SubgroupTable>,
+    state: &StickerCube,
+    orientation: Reorient,
+) -> i32 {
+    let bound = tables.lower_bound(state);
+    match subgroup {
+        Some(subgroup) => bound.max(subgroup.lower_bound(state, orientation)),
+        None => bound,
+    }
+}
+
+/// Asks a yes/no question on the terminal and returns whether the answer was
+/// yes. Ctrl-C while a search is running answers no.
+fn confirm(question: &str) -> bool {
+    if interrupt::requested() {
+        return false;
+    }
+    print!("{question} [y/N] ");
+    std::io::stdout().flush().unwrap();
+    let answer = interrupt::read_line();
+    if answer.is_none() {
+        println!();
+    }
+    answer.is_some_and(|answer| matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// State shared by every node of a single search.
+struct SearchContext<'a> {
+    optimizer: &'a RktOptimizer,
+    /// Tables that bound the moves left, taken when the search started.
+    tables: &'a Tables,
+    /// Table of the few faces that the search can turn, if it can only turn
+    /// a few. See [`crate::subgroup`].
+    subgroup: Option<&'a SubgroupTable>,
+    alg: &'a Alg,
+    /// Reorients to try at each gap, in order.
+    branch_order: &'a [Reorient],
+    /// Moves that can't be made in each orientation.
+    forbidden: &'a [Vec<Move>],
+    /// Tree of explored nodes, if it is being recorded.
+    tree: &'a mut Option<SearchTree>,
+    /// Number of nodes visited so far.
+    nodes: usize,
+    /// Time to stop searching at, if any.
+    deadline: Option<Instant>,
+    /// Whether the deadline has passed or Ctrl-C was pressed.
+    timed_out: bool,
+    /// Reorients at the first gap whose branches are known to have no
+    /// solutions, or the same solutions as another branch.
+    skip: &'a [Reorient],
+    /// Reorients at the first gap whose branches turned out to have no
+    /// solutions with any number of reorients.
+    refuted: Vec<Reorient>,
+    /// Whether the search stopped anywhere because it ran out of reorients,
+    /// so that it might have found solutions with more of them.
+    cut_off: bool,
+    /// Nodes known to have no solutions.
+    memo: Memo,
+    /// Most nodes that `memo` may hold.
+    memo_capacity: usize,
+    /// Nodes known to have no solutions in each branch at the first gap, by
+    /// reorient, when the branches are searched on separate threads.
+    branch_memos: Vec<Memo>,
+    /// Table for each move of the algorithm.
+    steps: &'a [MoveTable],
+    /// Table for each suffix of the algorithm, from each gap on.
+    suffixes: &'a [MoveTable],
+    /// Counts to show in the progress line, if there is one.
+    progress: Option<&'a Counts>,
+}
+
+/// State shared by every node of a single iteration of
+/// [`RktOptimizer::search_cost()`].
+struct CostContext<'a> {
+    optimizer: &'a RktOptimizer,
+    /// Tables that bound the moves left, taken when the search started.
+    tables: &'a Tables,
+    /// Table of the few faces that the search can turn, if it can only turn
+    /// a few. See [`crate::subgroup`].
+    subgroup: Option<&'a SubgroupTable>,
+    alg: &'a Alg,
+    /// Reorients to try at each gap, in order.
+    branch_order: &'a [Reorient],
+    /// Moves that can't be made in each orientation.
+    forbidden: &'a [Vec<Move>],
+    /// Orientation required before each move and at the end, if any.
+    boundaries: &'a [Option<Reorient>],
+    /// Table for each move of the algorithm.
+    steps: &'a [MoveTable],
+    /// Table for each suffix of the algorithm, from each gap on.
+    suffixes: &'a [MoveTable],
+    /// Number of nodes visited so far.
+    nodes: usize,
+    /// Time to stop searching at, if any.
+    deadline: Option<Instant>,
+    /// Whether the deadline has passed or Ctrl-C was pressed.
+    timed_out: bool,
+    /// Fewest ETM more than was left that any skipped reorient cost, if any
+    /// were skipped, so that there might be solutions that add more ETM.
+    shortfall: Option<usize>,
+}
+
+/// Returns all ways to insert at most `max_reorients` reorients adding at
+/// most `budget` ETM between the moves of the algorithm from move `gap` on,
+/// starting from `state`, such that the cube ends up solved. Like [`dfs()`],
+/// the reorients of each solution are in reverse order.
+fn dfs_cost(
+    state: &StickerCube,
+    gap: usize,
+    orientation: Reorient,
+    max_reorients: usize,
+    budget: usize,
+    ctx: &mut CostContext,
+) -> Vec<Solution> {
+    ctx.nodes += 1;
+    threads::yield_point(ctx.nodes, ctx.optimizer.nice());
+    if ctx.nodes.is_multiple_of(DEADLINE_INTERVAL)
+        && (ctx.optimizer.interrupted()
+            || ctx
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline))
+    {
+        ctx.timed_out = true;
+    }
+    if ctx.timed_out {
+        return vec![];
+    }
+
+    let moves = &ctx.alg.moves[gap..];
+    let forbidden = &ctx.forbidden[orientation as usize];
+    if ctx.boundaries[gap].is_some_and(|required| required != orientation)
+        || forbidden.contains(&moves[0])
+    {
+        return vec![];
+    }
+    if moves.len() <= 1 {
+        // Is the end of the algorithm in the right orientation, and solved?
+        if ctx.boundaries[gap + 1].is_some_and(|required| required != orientation) {
+            return vec![];
+        }
+        let end_result = state.apply(&ctx.suffixes[gap]);
+        let solved = ctx
+            .tables
+            .table
+            .within_one_move_packed(end_result.pack_with(&ctx.tables.pack_table));
+        return match solved {
+            true => vec![vec![]],
+            false => vec![],
+        };
+    }
+    if lower_bound(ctx.tables, ctx.subgroup, state, orientation) as usize > moves.len() + 1 {
+        return vec![];
+    }
+
+    let new_state = state.apply(&ctx.steps[gap]);
+    let branches: Vec<(Reorient, usize)> = ctx
+        .branch_order
+        .iter()
+        .filter_map(|&r| {
+            if r.is_none() {
+                return Some((r, 0));
+            }
+            if max_reorients == 0 || !ctx.alg.allows_reorient(gap) {
+                return None;
+            }
+            let cost =
+                ctx.optimizer.cost_model.cost(r) + ctx.alg.split_gaps.contains(&gap) as usize;
+            if cost > budget {
+                let shortfall = cost - budget;
+                ctx.shortfall = Some(ctx.shortfall.map_or(shortfall, |s| s.min(shortfall)));
+                return None;
+            }
+            Some((r, cost))
+        })
+        .collect();
+    let search = |&(r, cost): &(Reorient, usize), ctx: &mut CostContext| {
+        let remaining_reorients = max_reorients - !r.is_none() as usize;
+        let solutions = dfs_cost(
+            &new_state.apply(r.move_table()),
+            gap + 1,
+            orientation.then(r),
+            remaining_reorients,
+            budget - cost,
+            ctx,
+        );
+        solutions.into_iter().map(move |mut solution| {
+            solution.push(r);
+            solution
+        })
+    };
+
+    let thread_count = ctx.optimizer.thread_count();
+    if gap == 0 && thread_count > 1 && !threads::in_map() {
+        // Search the branches from the first gap on separate threads, in
+        // order so that the solutions are the same as on one thread.
+        let results = threads::map_on(thread_count, &branches, |branch| {
+            let mut branch_ctx = CostContext {
+                nodes: 0,
+                timed_out: false,
+                shortfall: None,
+                ..*ctx
+            };
+            let solutions: Vec<Solution> = search(branch, &mut branch_ctx).collect();
+            (
+                solutions,
+                branch_ctx.nodes,
+                branch_ctx.timed_out,
+                branch_ctx.shortfall,
+            )
+        });
+        let mut ret = vec![];
+        for (solutions, nodes, timed_out, shortfall) in results {
+            ctx.nodes += nodes;
+            ctx.timed_out |= timed_out;
+            ctx.shortfall = ctx.shortfall.into_iter().chain(shortfall).min();
+            ret.extend(solutions);
+        }
+        return ret;
+    }
+    let mut ret = vec![];
+    for branch in &branches {
+        ret.extend(search(branch, ctx));
+    }
+    ret
+}
+
+/// Returns all ways to insert at most `max_reorients` reorients between
+/// `moves`, starting from `state`, such that the cube ends up solved.
+///
+/// `boundaries[i]` is the orientation required before `moves[i]`, if any;
+/// `orientation` is the current orientation.
+fn dfs(
+    state: &StickerCube,
+    moves: &[Move],
+    boundaries: &[Option<Reorient>],
+    orientation: Reorient,
+    max_reorients: usize,
+    ctx: &mut SearchContext,
+    node: Option<usize>,
+) -> Vec<Solution> {
+    ctx.nodes += 1;
+    threads::yield_point(ctx.nodes, ctx.optimizer.nice());
+    if ctx.nodes.is_multiple_of(DEADLINE_INTERVAL) {
+        if let Some(progress) = ctx.progress {
+            progress.add_nodes(DEADLINE_INTERVAL);
+        }
+        if ctx.optimizer.interrupted()
+            || ctx
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            ctx.timed_out = true;
+        }
+    }
+    if ctx.timed_out {
+        return vec![];
+    }
+
+    if boundaries[0].is_some_and(|required| required != orientation) {
+        // Fail!
+        tree::record(ctx.tree, node, Outcome::WrongOrientation, 0, 0, 0);
+        return vec![];
+    }
+    let forbidden = &ctx.forbidden[orientation as usize];
+    if forbidden.contains(&moves[0]) {
+        // Fail!
+        tree::record(ctx.tree, node, Outcome::Forbidden, 0, 0, 0);
+        return vec![];
+    }
+
+    if moves.len() <= 1 || max_reorients == 0 {
+        if moves.len() > 1 {
+            ctx.cut_off = true;
+        }
+        // No more reorients allowed! Are we in the right orientation for the
+        // rest of the algorithm?
+        if boundaries
+            .iter()
+            .any(|b| b.is_some_and(|required| required != orientation))
+        {
+            // Fail!
+            tree::record(ctx.tree, node, Outcome::WrongOrientation, 0, 0, 0);
+            return vec![];
+        }
+        // Can we make the rest of the moves in this orientation?
+        if moves.iter().any(|mv| forbidden.contains(mv)) {
+            // Fail!
+            tree::record(ctx.tree, node, Outcome::Forbidden, 0, 0, 0);
+            return vec![];
+        }
+
+        // Are we already solved?
+        let gap = ctx.alg.moves.len() - moves.len();
+        let end_result = state.apply(&ctx.suffixes[gap]);
+        let packed = end_result.pack_with(&ctx.tables.pack_table);
+        let bound = ctx.tables.table.lower_bound_packed(packed);
+        if ctx.tables.table.within_one_move_packed(packed) {
+            // Success!
+            tree::record(ctx.tree, node, Outcome::Solved, bound, 1, 1);
+            if let Some(progress) = ctx.progress {
+                progress.add_solutions(1);
+            }
+            vec![vec![Reorient::None; moves.len().saturating_sub(1)]]
+        } else {
+            // Fail!
+            tree::record(ctx.tree, node, Outcome::Unsolved, bound, 1, 0);
+            vec![]
+        }
+    } else {
+        let bound = lower_bound(ctx.tables, ctx.subgroup, state, orientation);
+        if bound as usize > moves.len() + 1 {
+            // Fail!
+            tree::record(ctx.tree, node, Outcome::Pruned, bound, moves.len() + 1, 0);
+            return vec![];
+        }
+
+        // Don't search the same node again with no more reorients than it
+        // failed with before. Recorded trees show every node instead.
+        let gap = ctx.alg.moves.len() - moves.len();
+        let remember = ctx.optimizer.transpositions && ctx.tree.is_none();
+        let key = (gap > 0 && remember)
+            .then(|| (state.pack_with(&ctx.tables.pack_table), gap, orientation));
+        if let Some(&failed) = key.as_ref().and_then(|key| ctx.memo.get(key)) {
+            if failed >= max_reorients {
+                if failed != REFUTED {
+                    ctx.cut_off = true;
+                }
+                return vec![];
+            }
+        }
+        let outer_cut_off = std::mem::take(&mut ctx.cut_off);
+
+        let mut ret = vec![];
+
+        // Try not reorienting right now.
+        let new_state = state.apply(&ctx.steps[gap]);
+
+        // Try every possible reorient, including the null reorient.
+        let thread_count = ctx.optimizer.thread_count();
+        if gap == 0 && ctx.tree.is_none() && thread_count > 1 && !threads::in_map() {
+            // Search the branches from the first gap on separate threads.
+            // `threads::map_on()` keeps them in order, so the solutions are the
+            // same as on one thread.
+            let branches: Vec<Reorient> = ctx
+                .branch_order
+                .iter()
+                .copied()
+                .filter(|r| r.is_none() || ctx.alg.allows_reorient(gap))
+                .filter(|r| !ctx.skip.contains(r))
+                .collect();
+            let (
+                optimizer,
+                tables,
+                subgroup,
+                alg,
+                branch_order,
+                forbidden,
+                deadline,
+                steps,
+                suffixes,
+                progress,
+            ) = (
+                ctx.optimizer,
+                ctx.tables,
+                ctx.subgroup,
+                ctx.alg,
+                ctx.branch_order,
+                ctx.forbidden,
+                ctx.deadline,
+                ctx.steps,
+                ctx.suffixes,
+                ctx.progress,
+            );
+            // Each branch keeps its own memo from one iteration to the next,
+            // and they share the memory that one search's memo may take.
+            ctx.branch_memos.resize_with(Reorient::ALL.len(), Memo::new);
+            let memo_capacity = MEMO_CAPACITY / branches.len().max(1);
+            let branches: Vec<(Reorient, Mutex<Memo>)> = branches
+                .into_iter()
+                .map(|r| {
+                    (
+                        r,
+                        Mutex::new(std::mem::take(&mut ctx.branch_memos[r as usize])),
+                    )
+                })
+                .collect();
+            let results = threads::map_on(thread_count, &branches, |(reorient, memo)| {
+                let reorient = *reorient;
+                let mut branch_ctx = SearchContext {
+                    optimizer,
+                    tables,
+                    subgroup,
+                    alg,
+                    branch_order,
+                    forbidden,
+                    tree: &mut None,
+                    nodes: 0,
+                    deadline,
+                    timed_out: false,
+                    skip: &[],
+                    refuted: vec![],
+                    cut_off: false,
+                    memo: std::mem::take(&mut *memo.lock().unwrap()),
+                    memo_capacity,
+                    branch_memos: vec![],
+                    steps,
+                    suffixes,
+                    progress,
+                };
+                let solutions = dfs(
+                    &new_state.apply(reorient.move_table()),
+                    &moves[1..],
+                    &boundaries[1..],
+                    orientation.then(reorient),
+                    max_reorients - 1 + reorient.is_none() as usize,
+                    &mut branch_ctx,
+                    None,
+                );
+                let SearchContext {
+                    nodes,
+                    timed_out,
+                    cut_off,
+                    memo,
+                    ..
+                } = branch_ctx;
+                (solutions, nodes, timed_out, cut_off, memo)
+            });
+            for ((reorient, _), (solutions, nodes, timed_out, cut_off, memo)) in
+                branches.into_iter().zip(results)
+            {
+                ctx.branch_memos[reorient as usize] = memo;
+                ctx.nodes += nodes;
+                ctx.timed_out |= timed_out;
+                ctx.cut_off |= cut_off;
+                if solutions.is_empty() && !cut_off {
+                    ctx.refuted.push(reorient);
+                }
+                ret.extend(solutions.into_iter().map(|mut solution| {
+                    solution.push(reorient);
+                    solution
+                }));
+            }
+            ctx.cut_off |= outer_cut_off;
+            return ret;
+        }
+        for &reorient in ctx.branch_order {
+            if !reorient.is_none() && !ctx.alg.allows_reorient(gap) {
+                continue;
+            }
+            if gap == 0 && ctx.skip.contains(&reorient) {
+                continue;
+            }
+            let remaining_reorients = max_reorients - 1 + reorient.is_none() as usize;
+            let child = match (ctx.tree.as_mut(), node) {
+                (Some(t), Some(_)) => t.add(
+                    node,
+                    format!("{}{}", display_move(moves[0]), reorient),
+                    t.nodes()[node.unwrap()].gap + 1,
+                    remaining_reorients,
+                ),
+                _ => None,
+            };
+            let cut_off = std::mem::take(&mut ctx.cut_off);
+            let solutions = dfs(
+                &new_state.apply(reorient.move_table()),
+                &moves[1..],
+                &boundaries[1..],
+                orientation.then(reorient),
+                remaining_reorients,
+                ctx,
+                child,
+            );
+            // A branch that never ran out of reorients would search the same
+            // nodes with more of them, so it can't have solutions with any.
+            if gap == 0 && solutions.is_empty() && !ctx.cut_off {
+                ctx.refuted.push(reorient);
+            }
+            ctx.cut_off |= cut_off;
+            ret.extend(solutions.into_iter().map(|mut solution| {
+                solution.push(reorient);
+                solution
+            }));
+        }
+
+        if let Some(key) = key.filter(|_| ret.is_empty() && !ctx.timed_out) {
+            if ctx.memo.len() < ctx.memo_capacity {
+                let failed = match ctx.cut_off {
+                    true => max_reorients,
+                    false => REFUTED,
+                };
+                ctx.memo.insert(key, failed);
+            }
+        }
+        ctx.cut_off |= outer_cut_off;
+
+        tree::record(
+            ctx.tree,
+            node,
+            Outcome::Expanded,
+            bound,
+            moves.len() + 1,
+            ret.len(),
+        );
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use cubesim::{Cube, FaceletCube};
+
+    use super::RktOptimizer;
+    use crate::{face_moves, meet, table, Alg, CostModel, Reorient, Solution};
+    use crate::{ALL_SOLVER_FACES, SHALLOW_TABLE_DEPTH};
+
+    /// Algorithms that need a few reorients, and are short enough to try
+    /// every placement of them.
+    const ALGS: &[&str] = &[
+        "R U R' U'",
+        "R U' R'",
+        "R' F R F'",
+        "F R U R' U' F'",
+        "R U2 R' U' R U' R'",
+    ];
+    /// Most reorients that the searches try.
+    const MAX_REORIENTS: usize = 2;
+
+    /// Returns an optimizer with a depth-3 pruning table.
+    fn optimizer() -> RktOptimizer {
+        RktOptimizer::new(3, 0, CostModel::default())
+    }
+
+    /// Packed states within one move of a rotation of the solved cube, which
+    /// is when searches count an algorithm as solved.
+    fn near_solved() -> HashSet<[u64; 3]> {
+        let mut ret = HashSet::new();
+        for &r in Reorient::ALL {
+            let cube = FaceletCube::new(3).apply_moves(r.equivalent_rkt_moves());
+            ret.insert(table::pack(&cube));
+            for mv in face_moves(ALL_SOLVER_FACES) {
+                ret.insert(table::pack(&cube.apply_move(mv)));
+            }
+        }
+        ret
+    }
+
+    /// Returns every placement of at most `max_reorients` reorients that
+    /// solves `alg`, by trying all of them on facelet cubes.
+    fn all_solutions(alg: &Alg, max_reorients: usize) -> Vec<Solution> {
+        fn place(
+            alg: &Alg,
+            near_solved: &HashSet<[u64; 3]>,
+            cube: FaceletCube,
+            solution: &mut Solution,
+            reorients_left: usize,
+            ret: &mut Vec<Solution>,
+        ) {
+            let i = solution.len();
+            let cube = cube.apply_move(alg.moves[i]);
+            if i + 1 == alg.moves.len() {
+                if near_solved.contains(&table::pack(&cube)) {
+                    ret.push(solution.clone());
+                }
+                return;
+            }
+            for &r in Reorient::ALL {
+                if !r.is_none() && reorients_left == 0 {
+                    continue;
+                }
+                solution.push(r);
+                let cube = cube.apply_moves(r.equivalent_rkt_moves());
+                let left = reorients_left - !r.is_none() as usize;
+                place(alg, near_solved, cube, solution, left, ret);
+                solution.pop();
+            }
+        }
+
+        let mut ret = vec![];
+        let start = FaceletCube::new(3).apply_moves(alg.start.equivalent_rkt_moves());
+        place(
+            alg,
+            &near_solved(),
+            start,
+            &mut vec![],
+            max_reorients,
+            &mut ret,
+        );
+        ret
+    }
+
+    fn reorient_count(solution: &Solution) -> usize {
+        solution.iter().filter(|r| !r.is_none()).count()
+    }
+
+    /// Returns the solutions with the fewest reorients, like a plain
+    /// iterative deepening search.
+    fn fewest_reorients(alg: &Alg) -> (usize, Vec<Solution>) {
+        let solutions = all_solutions(alg, MAX_REORIENTS);
+        let Some(fewest) = solutions.iter().map(reorient_count).min() else {
+            return (0, vec![]);
+        };
+        let mut ret: Vec<Solution> = solutions
+            .into_iter()
+            .filter(|s| reorient_count(s) == fewest)
+            .collect();
+        ret.sort();
+        (fewest, ret)
+    }
+
+    fn sorted(mut solutions: Vec<Solution>) -> Vec<Solution> {
+        solutions.sort();
+        solutions
+    }
+
+    fn check_iddfs(optimizer: &RktOptimizer) {
+        for alg in ALGS {
+            let alg = Alg::parse(alg).unwrap();
+            let (count, solutions) = optimizer.search(&alg, MAX_REORIENTS, false, None, &mut None);
+            assert_eq!(
+                (count, sorted(solutions)),
+                fewest_reorients(&alg),
+                "{alg:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn reference_finds_solutions() {
+        for alg in ALGS {
+            let (count, solutions) = fewest_reorients(&Alg::parse(alg).unwrap());
+            assert!(count > 0 && !solutions.is_empty(), "{alg}");
+        }
+    }
+
+    #[test]
+    fn exhaustive_matches_reference() {
+        check_iddfs(&optimizer().with_transpositions(false));
+    }
+
+    #[test]
+    fn transpositions_match_reference() {
+        for (count, deterministic) in [(1, false), (4, false), (4, true)] {
+            let optimizer = optimizer()
+                .with_transpositions(true)
+                .with_thread_count(count)
+                .with_deterministic(deterministic);
+            check_iddfs(&optimizer);
+        }
+    }
+
+    #[test]
+    fn shallow_table_matches_reference() {
+        check_iddfs(&RktOptimizer::new(
+            SHALLOW_TABLE_DEPTH,
+            0,
+            CostModel::default(),
+        ));
+    }
+
+    #[test]
+    fn deepened_table_matches_built_table() {
+        let built = optimizer().table();
+        let deepened = RktOptimizer::new(2, 0, CostModel::default())
+            .deepened()
+            .table();
+        assert_eq!(deepened.counts_by_distance(), built.counts_by_distance());
+    }
+
+    #[test]
+    fn meet_in_the_middle_matches_reference() {
+        for alg in ALGS {
+            let alg = Alg::parse(alg).unwrap();
+            let result = meet::search(&optimizer(), &alg, MAX_REORIENTS, false, None);
+            let found = (result.reorient_count, sorted(result.solutions));
+            assert_eq!(found, fewest_reorients(&alg), "{alg:?}");
+        }
+    }
+
+    #[test]
+    fn cost_matches_reference() {
+        let optimizer = optimizer();
+        for alg in ALGS {
+            let alg = Alg::parse(alg).unwrap();
+            // The cheapest solutions, and of those the ones with the fewest
+            // reorients.
+            let solutions = all_solutions(&alg, MAX_REORIENTS);
+            let cost_model = optimizer.cost_model();
+            let cheapest = solutions
+                .iter()
+                .map(|s| alg.cost(cost_model, s))
+                .min()
+                .unwrap();
+            let solutions: Vec<Solution> = solutions
+                .into_iter()
+                .filter(|s| alg.cost(cost_model, s) == cheapest)
+                .collect();
+            let fewest = solutions.iter().map(reorient_count).min().unwrap();
+            let expected: Vec<Solution> = solutions
+                .into_iter()
+                .filter(|s| reorient_count(s) == fewest)
+                .collect();
+
+            let result = optimizer.search_cost(&alg, MAX_REORIENTS, false, None);
+            let found = (result.reorient_count, sorted(result.solutions));
+            assert_eq!(found, (fewest, sorted(expected)), "{alg:?}");
+        }
+    }
+
+    /// Checks searches of `algs` with a cost model that forbids every move
+    /// that doesn't turn one of `faces`, which use a table of their turns.
+    fn check_subgroup(faces: &str, algs: &[&str]) {
+        let turnable = face_moves(crate::parse_solver_faces(faces).unwrap());
+        let mut cost_model = CostModel::default();
+        for &r in Reorient::ALL {
+            for mv in face_moves(ALL_SOLVER_FACES) {
+                if !turnable.contains(&r.physical_move(mv)) {
+                    cost_model.forbid(r, mv);
+                }
+            }
+        }
+        let optimizer = RktOptimizer::new(3, 0, cost_model.clone())
+            .with_thread_count(1)
+            .with_deterministic(false);
+        let forbidden = cost_model.forbidden_moves();
+        let allowed = |alg: &Alg, solution: &Solution| {
+            let mut orientation = alg.start;
+            alg.moves.iter().enumerate().all(|(i, mv)| {
+                let allowed = !forbidden[orientation as usize].contains(mv);
+                if let Some(&r) = solution.get(i) {
+                    orientation = orientation.then(r);
+                }
+                allowed
+            })
+        };
+        for alg in algs {
+            let alg = Alg::parse(alg).unwrap();
+            let tables = optimizer.tables();
+            let subgroup = optimizer.subgroup_table(&tables, &alg);
+            assert!(subgroup.is_some_and(|s| s.depth() > tables.table.depth()));
+
+            let solutions: Vec<Solution> = all_solutions(&alg, MAX_REORIENTS)
+                .into_iter()
+                .filter(|s| allowed(&alg, s))
+                .collect();
+            let fewest = solutions.iter().map(reorient_count).min().unwrap_or(0);
+            let expected: Vec<Solution> = solutions
+                .into_iter()
+                .filter(|s| reorient_count(s) == fewest)
+                .collect();
+            let (count, found) = optimizer.search(&alg, MAX_REORIENTS, false, None, &mut None);
+            assert_eq!(
+                (count, sorted(found)),
+                (fewest, sorted(expected)),
+                "{alg:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn subgroup_table_matches_reference() {
+        check_subgroup(
+            "RU",
+            &[
+                "R U R' U'",
+                "R U' R'",
+                "R U2 R' U R2 U'",
+                "R U2 R' U' R U' R'",
+            ],
+        );
+    }
+
+    #[test]
+    fn last_layer_subgroup_table_matches_reference() {
+        check_subgroup("RUF", &["R' F R F'", "F R U R' U' F'"]);
+    }
+}
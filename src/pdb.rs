@@ -0,0 +1,614 @@
+//! Pattern databases, which bound the number of moves needed to solve a cube
+//! by the number needed to solve some of its pieces on their own: the
+//! corners, and each half of the edges.
+//!
+//! The pruning table only knows the states within a few moves of solved, so
+//! it never bounds a state by more than one move past its depth. A pattern
+//! database instead has the exact distance of every arrangement of its
+//! pieces, indexed by the slot and orientation of each piece and packed into
+//! 4 bits each, which takes 42 MiB for the corners and 20 MiB for each half
+//! of the edges. Searches take the largest of their bounds and the pruning
+//! table's. They are built from every face turn, so unlike a pruning table
+//! built with [`crate::RktOptimizer::with_solver_faces()`], they never prune
+//! solutions.
+//!
+//! The databases are optional, and off unless
+//! [`crate::RktOptimizer::with_pattern_databases()`] turns them on. Building
+//! them takes about 20 seconds and 80 MiB, while most algorithms are short
+//! enough that the pruning table alone finishes their search in well under a
+//! second. They also can't replace the pruning table: a search only stops at
+//! a state the table knows is within one move of solved, and a bound from
+//! some of the pieces can't tell that.
+//!
+//! Face turns don't move the centers, so every state that a search reaches is
+//! a rotation followed by face turns, and it is solved when those face turns
+//! are. The databases look states up with that rotation undone.
+
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use cubesim::{Cube, Face, FaceletCube, Move, MoveVariant};
+
+use crate::perm::{Permutation, STICKERS};
+use crate::sticker_cube::StickerCube;
+use crate::{face_moves, mask, solved_states, Reorient, ALL_SOLVER_FACES};
+
+/// Start of every database file, followed by the format version.
+const MAGIC: &[u8; 8] = b"RKTPDB\0\0";
+const VERSION: u32 = 1;
+/// Distance of arrangements that haven't been reached yet.
+const UNKNOWN: u8 = 0xF;
+/// Most pieces that one database tracks, which is every corner.
+const MAX_PIECES: usize = 8;
+/// Number of edge slots, the most slots of any kind.
+const MAX_SLOTS: usize = 12;
+/// Most edges that one database tracks. Each one more takes about 12 times
+/// the memory.
+const MAX_EDGES: usize = 6;
+
+/// Slot and orientation of each piece that a database tracks.
+type Arrangement = [(u8, u8); MAX_PIECES];
+
+/// The corner or edge slots of a 3x3x3.
+#[derive(Clone)]
+struct Slots {
+    /// Facelets of each slot, starting with the one on the U or D face, or
+    /// on the F or B face for edges that have none, and going around every
+    /// slot in the same direction.
+    facelets: Vec<Vec<u8>>,
+    /// Slot of each facelet of these slots, and where it is among the slot's
+    /// facelets.
+    home: Vec<Option<(u8, u8)>>,
+}
+impl Slots {
+    /// Returns the slots of the pieces with `size` stickers.
+    fn new(size: u32) -> Self {
+        let pieces = mask::sticker_pieces();
+        let colors = FaceletCube::new(3).state();
+        let mut slot_pieces: Vec<u8> = vec![];
+        for &piece in &pieces {
+            if piece.count_ones() == size && !slot_pieces.contains(&piece) {
+                slot_pieces.push(piece);
+            }
+        }
+        let slot_of = |f: u8| slot_pieces.iter().position(|&p| p == pieces[f as usize]);
+
+        // Rotations carry the facelets of one slot onto those of every other
+        // slot without changing the direction around it.
+        let v = MoveVariant::Standard;
+        let rotations: Vec<Vec<u8>> = [Move::X(v), Move::Y(v)]
+            .iter()
+            .map(|&mv| {
+                let permutation = Permutation::of_moves(&[mv]);
+                let mut dest = vec![0; STICKERS];
+                for i in 0..STICKERS {
+                    dest[permutation.source(i)] = i as u8;
+                }
+                dest
+            })
+            .collect();
+        let primary = |facelets: &[u8]| {
+            let on = |faces: [Face; 2]| {
+                facelets
+                    .iter()
+                    .position(|&f| faces.contains(&colors[f as usize]))
+            };
+            on([Face::U, Face::D])
+                .or_else(|| on([Face::F, Face::B]))
+                .unwrap()
+        };
+
+        let mut facelets: Vec<Option<Vec<u8>>> = vec![None; slot_pieces.len()];
+        let seed = (0..STICKERS as u8).filter(|&f| slot_of(f) == Some(0));
+        let mut queue = vec![seed.collect::<Vec<u8>>()];
+        while let Some(mut slot_facelets) = queue.pop() {
+            let slot = slot_of(slot_facelets[0]).unwrap();
+            if facelets[slot].is_some() {
+                continue;
+            }
+            for dest in &rotations {
+                queue.push(slot_facelets.iter().map(|&f| dest[f as usize]).collect());
+            }
+            let first = primary(&slot_facelets);
+            slot_facelets.rotate_left(first);
+            facelets[slot] = Some(slot_facelets);
+        }
+        let facelets: Vec<Vec<u8>> = facelets.into_iter().map(Option::unwrap).collect();
+
+        let mut home = vec![None; STICKERS];
+        for (s, slot_facelets) in facelets.iter().enumerate() {
+            for (i, &f) in slot_facelets.iter().enumerate() {
+                home[f as usize] = Some((s as u8, i as u8));
+            }
+        }
+        Self { facelets, home }
+    }
+
+    /// Returns the number of orientations of each piece.
+    fn orientations(&self) -> usize {
+        self.facelets[0].len()
+    }
+
+    /// Returns the slot and orientation of the piece of each slot in `cube`,
+    /// indexed by the piece's solved slot.
+    fn arrangement(&self, cube: &StickerCube) -> [(u8, u8); MAX_SLOTS] {
+        let o = self.orientations() as u8;
+        let mut ret = [(0, 0); MAX_SLOTS];
+        for (s, slot_facelets) in self.facelets.iter().enumerate() {
+            let (piece, i) = self.home[cube.sticker_at(slot_facelets[0] as usize) as usize]
+                .expect("sticker should belong to a piece of this kind");
+            // Sticker `i` of the piece is on the first facelet, so its first
+            // sticker is `o - i` facelets further around.
+            ret[piece as usize] = (s as u8, (o - i) % o);
+        }
+        ret
+    }
+}
+
+/// Exact number of face turns needed to solve some of the corners or some
+/// of the edges, for every arrangement of them.
+struct PatternDatabase {
+    /// Name of the database in file names.
+    name: String,
+    slots: Slots,
+    /// Solved slot of each piece that the database tracks.
+    pieces: Vec<u8>,
+    /// Distance of each arrangement, two to a byte.
+    distances: Vec<u8>,
+}
+impl PatternDatabase {
+    /// Returns whether the orientation of the last piece follows from the
+    /// others', because the database tracks every piece of its kind and they
+    /// always add up to a multiple of the number of orientations.
+    fn last_orientation_fixed(&self) -> bool {
+        self.pieces.len() == self.slots.facelets.len()
+    }
+
+    /// Returns the number of arrangements.
+    fn len(&self) -> usize {
+        let (n, k) = (self.slots.facelets.len(), self.pieces.len());
+        let positions: usize = (n - k + 1..=n).product();
+        let free = k - self.last_orientation_fixed() as usize;
+        positions * self.slots.orientations().pow(free as u32)
+    }
+
+    /// Returns the index of an arrangement.
+    fn index(&self, arrangement: &Arrangement) -> usize {
+        let (n, o) = (self.slots.facelets.len(), self.slots.orientations());
+        let k = self.pieces.len();
+        let mut index = 0;
+        let mut used = 0u32;
+        for (i, &(slot, _)) in arrangement[..k].iter().enumerate() {
+            let before = (used & ((1 << slot) - 1)).count_ones() as usize;
+            index = index * (n - i) + slot as usize - before;
+            used |= 1 << slot;
+        }
+        let free = k - self.last_orientation_fixed() as usize;
+        for &(_, orientation) in &arrangement[..free] {
+            index = index * o + orientation as usize;
+        }
+        index
+    }
+
+    /// Returns the arrangement with an index.
+    fn arrangement(&self, mut index: usize) -> Arrangement {
+        let (n, o) = (self.slots.facelets.len(), self.slots.orientations());
+        let k = self.pieces.len();
+        let mut ret = [(0, 0); MAX_PIECES];
+        let free = k - self.last_orientation_fixed() as usize;
+        let mut total = 0;
+        for piece in ret[..free].iter_mut().rev() {
+            piece.1 = (index % o) as u8;
+            total += index % o;
+            index /= o;
+        }
+        if free < k {
+            ret[k - 1].1 = ((o - total % o) % o) as u8;
+        }
+        let mut ranks = [0; MAX_PIECES];
+        for (i, rank) in ranks[..k].iter_mut().enumerate().rev() {
+            *rank = index % (n - i);
+            index /= n - i;
+        }
+        let mut used = 0u32;
+        for (piece, &rank) in ret[..k].iter_mut().zip(&ranks) {
+            let slot = (0..n as u8)
+                .filter(|&s| used & (1 << s) == 0)
+                .nth(rank)
+                .unwrap();
+            piece.0 = slot;
+            used |= 1 << slot;
+        }
+        ret
+    }
+
+    /// Returns the arrangement of the tracked pieces in `cube`.
+    fn arrangement_of(&self, cube: &StickerCube) -> Arrangement {
+        let all = self.slots.arrangement(cube);
+        let mut ret = [(0, 0); MAX_PIECES];
+        for (piece, &home) in ret.iter_mut().zip(&self.pieces) {
+            *piece = all[home as usize];
+        }
+        ret
+    }
+
+    fn get(&self, index: usize) -> u8 {
+        (self.distances[index / 2] >> (index % 2 * 4)) & 0xF
+    }
+
+    fn set(&mut self, index: usize, distance: u8) {
+        let shift = index % 2 * 4;
+        let byte = &mut self.distances[index / 2];
+        *byte = (*byte & !(0xF << shift)) | (distance << shift);
+    }
+
+    /// Returns a lower bound on the number of face turns needed to solve
+    /// `cube`, whose centers must be solved.
+    fn lower_bound(&self, cube: &StickerCube) -> u8 {
+        self.get(self.index(&self.arrangement_of(cube)))
+    }
+
+    /// Finds the distance of every arrangement from those of `goals` by
+    /// breadth-first search with `moves`.
+    fn build(&mut self, goals: &[StickerCube], moves: &[Move]) {
+        let o = self.slots.orientations();
+        let k = self.pieces.len();
+        let free = k - self.last_orientation_fixed() as usize;
+        // Indices are a position index times the number of orientation
+        // indices plus an orientation index, in the same digits.
+        let orientations = o.pow(free as u32);
+        let positions = self.len() / orientations;
+        let orientation_index = |digits: &mut dyn Iterator<Item = usize>| {
+            digits.take(free).fold(0, |index, digit| index * o + digit)
+        };
+
+        // Where each move takes the piece in each slot, and how much it
+        // turns it.
+        let transitions: Vec<[(u8, u8); MAX_SLOTS]> = moves
+            .iter()
+            .map(|&mv| self.slots.arrangement(&StickerCube::of_moves(&[mv])))
+            .collect();
+        // Where each move takes the tracked pieces from each position, and how
+        // much it turns each of them, as an orientation index.
+        let mut next_positions = Vec::with_capacity(positions * moves.len());
+        let mut twists = Vec::with_capacity(positions * moves.len());
+        for position in 0..positions {
+            let arrangement = self.arrangement(position * orientations);
+            for transition in &transitions {
+                let mut next = [(0, 0); MAX_PIECES];
+                for (to, &(from, _)) in next.iter_mut().zip(&arrangement[..k]) {
+                    *to = (transition[from as usize].0, 0);
+                }
+                next_positions.push((self.index(&next) / orientations) as u32);
+                let mut twist = arrangement[..k]
+                    .iter()
+                    .map(|&(from, _)| transition[from as usize].1 as usize);
+                twists.push(orientation_index(&mut twist) as u16);
+            }
+        }
+        // The orientation index reached by turning the pieces with each
+        // orientation index by each twist.
+        let digits = |mut index: usize| {
+            let mut digits = [0; MAX_PIECES];
+            for digit in digits[..free].iter_mut().rev() {
+                *digit = index % o;
+                index /= o;
+            }
+            digits
+        };
+        let mut turned = Vec::with_capacity(orientations * orientations);
+        for orientation in 0..orientations {
+            let before = digits(orientation);
+            for twist in 0..orientations {
+                let mut after = before.iter().zip(digits(twist)).map(|(&a, b)| (a + b) % o);
+                turned.push(orientation_index(&mut after) as u16);
+            }
+        }
+
+        let len = self.len();
+        self.distances = vec![0xFF; len.div_ceil(2)];
+        for goal in goals {
+            let index = self.index(&self.arrangement_of(goal));
+            self.set(index, 0);
+        }
+        for depth in 0..UNKNOWN - 1 {
+            let mut reached = false;
+            for index in 0..len {
+                if self.get(index) != depth {
+                    continue;
+                }
+                let (position, orientation) = (index / orientations, index % orientations);
+                for m in position * moves.len()..(position + 1) * moves.len() {
+                    let next_index = next_positions[m] as usize * orientations
+                        + turned[orientation * orientations + twists[m] as usize] as usize;
+                    if self.get(next_index) == UNKNOWN {
+                        self.set(next_index, depth + 1);
+                        reached = true;
+                    }
+                }
+            }
+            if !reached {
+                break;
+            }
+        }
+    }
+
+    /// Writes the database to a file.
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            // Write to a temporary file first, so that an interrupted write
+            // never leaves a truncated database behind.
+            let tmp_path = path.with_extension("tmp");
+            let mut w = BufWriter::new(std::fs::File::create(&tmp_path)?);
+            w.write_all(MAGIC)?;
+            w.write_all(&VERSION.to_le_bytes())?;
+            w.write_all(&self.distances)?;
+            w.into_inner()?.sync_all()?;
+            std::fs::rename(tmp_path, path)
+        };
+        write().map_err(|e| format!("error writing {}: {e}", path.display()))
+    }
+
+    /// Reads the distances written by [`PatternDatabase::save()`].
+    fn load(&mut self, path: &Path) -> Result<(), String> {
+        let err = |e: &str| format!("error reading {}: {e}", path.display());
+        let bytes = std::fs::read(path).map_err(|e| err(&e.to_string()))?;
+        let header = MAGIC.len() + 4;
+        if bytes.len() < header || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(err("not a pattern database"));
+        }
+        let version = u32::from_le_bytes(bytes[MAGIC.len()..header].try_into().unwrap());
+        if version != VERSION {
+            return Err(err(&format!("unsupported version {version}")));
+        }
+        if bytes.len() - header != self.len().div_ceil(2) {
+            return Err(err("wrong size"));
+        }
+        self.distances = bytes[header..].to_vec();
+        Ok(())
+    }
+}
+
+/// Pattern databases for the corners and each half of the edges.
+pub struct PatternDatabases {
+    databases: Vec<PatternDatabase>,
+    /// Two adjacent center facelets, whose stickers tell the rotation.
+    centers: [usize; 2],
+    /// Inverse of the rotation with each pair of stickers at `centers`,
+    /// indexed by the first sticker times [`STICKERS`] plus the second.
+    unrotations: Vec<Option<Reorient>>,
+}
+impl PatternDatabases {
+    /// Returns the databases for a cube whose stickers in `mask` are ignored
+    /// and that counts as solved up to a turn of the U layer if `up_to_auf`.
+    /// Pieces with ignored stickers aren't tracked, and unless two centers on
+    /// adjacent faces are left to tell which rotation of the solved cube a
+    /// state is near, nothing is.
+    ///
+    /// If `cache_dir` is given, each database is loaded from there if it was
+    /// saved before, and saved there otherwise.
+    pub fn new(mask: u64, up_to_auf: bool, cache_dir: Option<&Path>) -> Self {
+        let pieces = mask::sticker_pieces();
+        let center_facelets: Vec<usize> = (0..STICKERS)
+            .filter(|&f| pieces[f].count_ones() == 1)
+            .collect();
+        let visible = |facelets: &[u8]| facelets.iter().all(|&f| (mask >> f) & 1 == 0);
+        let visible_centers: Vec<u8> = center_facelets
+            .iter()
+            .filter(|&&f| (mask >> f) & 1 == 0)
+            .map(|&f| pieces[f])
+            .collect();
+        // Faces are bits in the order ULFRBD.
+        let opposite = [0b100001, 0b001010, 0b010100];
+        let centers_visible = visible_centers.iter().any(|&a| {
+            visible_centers
+                .iter()
+                .any(|&b| a != b && !opposite.contains(&(a | b)))
+        });
+
+        let mut databases = vec![];
+        let corners = Slots::new(3);
+        let edges = Slots::new(2);
+        let visible_pieces = |slots: &Slots| -> Vec<u8> {
+            match centers_visible {
+                true => (0..slots.facelets.len() as u8)
+                    .filter(|&s| visible(&slots.facelets[s as usize]))
+                    .collect(),
+                false => vec![],
+            }
+        };
+        let visible_corners = visible_pieces(&corners);
+        if !visible_corners.is_empty() {
+            databases.push(PatternDatabase {
+                name: "corners".to_string(),
+                slots: corners,
+                pieces: visible_corners,
+                distances: vec![],
+            });
+        }
+        for (i, group) in visible_pieces(&edges).chunks(MAX_EDGES).enumerate() {
+            databases.push(PatternDatabase {
+                name: format!("edges{}", i + 1),
+                slots: edges.clone(),
+                pieces: group.to_vec(),
+                distances: vec![],
+            });
+        }
+
+        let goals: Vec<StickerCube> = solved_states(up_to_auf)
+            .into_iter()
+            .filter(|cube| {
+                center_facelets
+                    .iter()
+                    .all(|&f| cube.sticker_at(f) == f as u8)
+            })
+            .collect();
+        let moves = face_moves(ALL_SOLVER_FACES);
+        for database in &mut databases {
+            let path = cache_path(&database.name, mask, up_to_auf, cache_dir);
+            if let Some(path) = path.as_deref().filter(|path| path.exists()) {
+                match database.load(path) {
+                    Ok(()) => continue,
+                    Err(e) => eprintln!("Warning: {e}"),
+                }
+            }
+            database.build(&goals, &moves);
+            if let Some(path) = &path {
+                if let Err(e) = database.save(path) {
+                    eprintln!("Warning: {e}");
+                }
+            }
+        }
+
+        // Facelets of the U and L centers.
+        let centers = [center_facelets[0], center_facelets[1]];
+        let mut unrotations = vec![None; STICKERS * STICKERS];
+        for &r in Reorient::ALL {
+            let cube = r.sticker_cube();
+            let key = cube.sticker_at(centers[0]) as usize * STICKERS
+                + cube.sticker_at(centers[1]) as usize;
+            unrotations[key] = Some(r.inverse());
+        }
+        Self {
+            databases,
+            centers,
+            unrotations,
+        }
+    }
+
+    /// Returns a lower bound on the number of face turns needed to reach any
+    /// state that counts as solved from `state`.
+    pub fn lower_bound(&self, state: &StickerCube) -> i32 {
+        let key = state.sticker_at(self.centers[0]) as usize * STICKERS
+            + state.sticker_at(self.centers[1]) as usize;
+        let Some(unrotation) = self.unrotations[key] else {
+            return 0;
+        };
+        let unrotated = unrotation.sticker_cube().then(state);
+        self.databases
+            .iter()
+            .map(|database| database.lower_bound(&unrotated))
+            .max()
+            .unwrap_or(0) as i32
+    }
+
+    /// Returns the number of arrangements at each distance from solved in
+    /// each database, by name.
+    pub fn counts_by_distance(&self) -> Vec<(&str, Vec<usize>)> {
+        self.databases
+            .iter()
+            .map(|database| {
+                let mut counts = vec![];
+                for index in 0..database.len() {
+                    let distance = database.get(index) as usize;
+                    if counts.len() <= distance {
+                        counts.resize(distance + 1, 0);
+                    }
+                    counts[distance] += 1;
+                }
+                (database.name.as_str(), counts)
+            })
+            .collect()
+    }
+
+    /// Returns the memory used by the databases, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.databases.iter().map(|d| d.distances.len()).sum()
+    }
+}
+
+/// Returns the file in `cache_dir` that a database with these settings is
+/// saved to, if there is a cache directory.
+fn cache_path(name: &str, mask: u64, up_to_auf: bool, cache_dir: Option<&Path>) -> Option<PathBuf> {
+    let auf = if up_to_auf { "-auf" } else { "" };
+    cache_dir.map(|dir| dir.join(format!("pdb-3x3-{name}-m{mask:016x}{auf}.bin")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::PatternDatabases;
+    use crate::sticker_cube::{self, MoveTable, PackTable, StickerCube};
+    use crate::table::PruningTable;
+    use crate::{face_moves, mask, solved_states, ALL_SOLVER_FACES};
+
+    /// Depth of the pruning table checked against the true distances.
+    const TABLE_DEPTH: u8 = 2;
+    /// Depth up to which the true distance of every state is found.
+    const EXACT_DEPTH: u8 = 3;
+
+    /// Checks that the bounds never exceed the true distance, on a cube that
+    /// only keeps the U layer and the centers, so that every database is small.
+    fn check_bounds(up_to_auf: bool) {
+        let mask = mask::parse("D-corners D-edges FL FR BL BR").unwrap();
+        let colors = sticker_cube::colors(mask);
+        let pack_table = PackTable::new(&colors);
+        let moves = face_moves(ALL_SOLVER_FACES);
+        let move_tables: Vec<MoveTable> =
+            moves.iter().map(|&mv| MoveTable::of_moves(&[mv])).collect();
+        let goals = solved_states(up_to_auf);
+        let databases = PatternDatabases::new(mask, up_to_auf, None);
+        let table = PruningTable::new(&colors, &goals, TABLE_DEPTH, moves);
+
+        // Breadth-first search for the true distance of every state near the
+        // goals.
+        let mut seen = HashSet::new();
+        let mut frontier: Vec<StickerCube> = goals
+            .iter()
+            .copied()
+            .filter(|cube| seen.insert(cube.pack_with(&pack_table)))
+            .collect();
+        for distance in 0..=EXACT_DEPTH {
+            let mut next_frontier = vec![];
+            for cube in &frontier {
+                let packed = cube.pack_with(&pack_table);
+                assert!(databases.lower_bound(cube) <= distance as i32);
+                // States that share an entry of the table get the least of
+                // their distances, so bounds can be smaller but never larger.
+                let bound = table.lower_bound_packed(packed);
+                assert!(bound <= (distance as i32).min(TABLE_DEPTH as i32 + 1));
+                assert_eq!(table.within_one_move_packed(packed), distance <= 1);
+                if distance == EXACT_DEPTH {
+                    continue;
+                }
+                for mv in &move_tables {
+                    let new_cube = cube.apply(mv);
+                    if seen.insert(new_cube.pack_with(&pack_table)) {
+                        next_frontier.push(new_cube);
+                    }
+                }
+            }
+            frontier = next_frontier;
+        }
+
+        // Scrambles further than that are at most as far as their length.
+        let mut rng = StdRng::seed_from_u64(0);
+        for len in 0..=20 {
+            for _ in 0..50 {
+                let mut cube = goals[rng.gen_range(0..goals.len())];
+                for _ in 0..len {
+                    cube = cube.apply(&move_tables[rng.gen_range(0..move_tables.len())]);
+                }
+                assert!(databases.lower_bound(&cube) <= len);
+                assert!(table.lower_bound_packed(cube.pack_with(&pack_table)) <= len);
+            }
+        }
+    }
+
+    #[test]
+    fn bounds_are_admissible() {
+        check_bounds(false);
+    }
+
+    #[test]
+    fn bounds_are_admissible_up_to_auf() {
+        check_bounds(true);
+    }
+}
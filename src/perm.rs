@@ -0,0 +1,124 @@
+//! Permutations of the stickers of a 3x3x3, for applying a whole sequence of
+//! moves in one step.
+//!
+//! States that are permuted over and over, as when annealing, are kept as
+//! [`Stickers`], which are padded to 64 bytes so that a permutation is a
+//! handful of byte shuffles on CPUs that have them.
+
+use std::cell::RefCell;
+
+use cubesim::{Cube, FaceletCube, Move};
+
+/// Number of stickers on a 3x3x3.
+pub(crate) const STICKERS: usize = 54;
+/// Number of stickers padded to a whole number of 16-byte vectors.
+const PADDED: usize = 64;
+
+/// The sticker of the solved cube at each position of a 3x3x3, followed by
+/// padding that permutations leave in place.
+pub type Stickers = [u8; PADDED];
+
+/// Returns the stickers of the solved cube.
+pub fn solved_stickers() -> Stickers {
+    std::array::from_fn(|i| i as u8)
+}
+
+/// Where each sticker of a 3x3x3 comes from after a sequence of moves.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Permutation {
+    /// `sources[i]` is the position that the sticker at position `i` came
+    /// from. The padding comes from itself.
+    sources: Stickers,
+}
+impl Permutation {
+    /// Returns the permutation that does nothing.
+    pub fn identity() -> Self {
+        Self {
+            sources: solved_stickers(),
+        }
+    }
+
+    /// Returns the permutation made by a sequence of moves.
+    pub fn of_moves(moves: &[Move]) -> Self {
+        // A new cube remembers where each sticker started, which masking
+        // reveals in order of the stickers' current positions.
+        let sources = RefCell::new(solved_stickers());
+        let position = RefCell::new(0);
+        FaceletCube::new(3).apply_moves(moves).mask(&|start, face| {
+            let mut position = position.borrow_mut();
+            sources.borrow_mut()[*position] = start as u8;
+            *position += 1;
+            face
+        });
+        Self {
+            sources: sources.into_inner(),
+        }
+    }
+
+    /// Returns the position that the sticker at position `i` came from.
+    pub fn source(&self, i: usize) -> usize {
+        self.sources[i] as usize
+    }
+
+    /// Returns the permutation made by doing this one and then `next`.
+    pub fn then(&self, next: &Self) -> Self {
+        Self {
+            sources: std::array::from_fn(|i| self.sources[next.sources[i] as usize]),
+        }
+    }
+
+    /// Returns the state reached by applying the permutation to `cube`.
+    pub fn apply(&self, cube: &FaceletCube) -> FaceletCube {
+        let state = cube.state();
+        FaceletCube::from(
+            self.sources[..STICKERS]
+                .iter()
+                .map(|&source| state[source as usize])
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Returns the stickers reached by applying the permutation to
+    /// `stickers`, which is the same as [`Permutation::apply()`] but doesn't
+    /// allocate.
+    pub fn permute(&self, stickers: &Stickers) -> Stickers {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("ssse3") {
+            // SAFETY: the CPU supports SSSE3.
+            return unsafe { self.permute_ssse3(stickers) };
+        }
+        self.permute_scalar(stickers)
+    }
+
+    fn permute_scalar(&self, stickers: &Stickers) -> Stickers {
+        self.sources.map(|source| stickers[source as usize])
+    }
+
+    /// Does [`Permutation::permute()`] with `pshufb`, which picks bytes
+    /// from one 16-byte vector at a time. Each vector of the result is the
+    /// union of shuffles of each vector of `stickers`, with every byte that
+    /// comes from a different vector zeroed out.
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "ssse3")]
+    unsafe fn permute_ssse3(&self, stickers: &Stickers) -> Stickers {
+        use std::arch::x86_64::*;
+
+        let load = |bytes: &[u8]| _mm_loadu_si128(bytes.as_ptr() as *const __m128i);
+        let inputs: [__m128i; 4] = std::array::from_fn(|j| load(&stickers[16 * j..]));
+        let mut ret = [0; PADDED];
+        for (k, out) in ret.chunks_exact_mut(16).enumerate() {
+            let sources = load(&self.sources[16 * k..]);
+            let mut acc = _mm_setzero_si128();
+            for (j, &input) in inputs.iter().enumerate() {
+                // Indices into this vector become 0x70..=0x7f, which select
+                // by their low four bits, and the rest saturate to at least
+                // 0x80, which selects zero.
+                let offset = _mm_sub_epi8(sources, _mm_set1_epi8(16 * j as i8));
+                let control = _mm_adds_epu8(offset, _mm_set1_epi8(0x70));
+                acc = _mm_or_si128(acc, _mm_shuffle_epi8(input, control));
+            }
+            _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, acc);
+        }
+        ret
+    }
+}
@@ -0,0 +1,256 @@
+//! Estimates of how much work a search will do, for `--dry-run`, for
+//! choosing the depth of the pruning table with `--depth auto`, and for
+//! choosing how to search with `--strategy auto`.
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::sticker_cube::{self, MoveTable, PackTable, StickerCube};
+use crate::{face_moves, table, Alg, Reorient, RktOptimizer};
+
+/// Number of nodes to time when calibrating.
+const CALIBRATION_NODES: usize = 20_000;
+
+/// Shallowest and deepest pruning tables that [`auto_depth()`] picks.
+const MIN_AUTO_DEPTH: u8 = 2;
+const MAX_AUTO_DEPTH: u8 = 6;
+/// Number of moves to plan for when the algorithms aren't known yet, which
+/// is about as long as most last-layer algorithms.
+const TYPICAL_ALG_LEN: usize = 14;
+/// Memory budget when the free memory can't be detected, in bytes.
+const DEFAULT_MEMORY_BUDGET: usize = 1 << 30;
+
+/// Algorithms with at most this many gaps are always searched exhaustively
+/// by [`pick_strategy()`].
+const SHORT_ALG_GAPS: usize = 10;
+/// Searches projected to visit fewer nodes than this don't remember nodes
+/// without solutions, which costs more than it saves in searches this small.
+const SMALL_SEARCH_NODES: f64 = 100_000.0;
+/// Searches projected to take longer than this many seconds anneal instead.
+const LONG_SEARCH_SECS: f64 = 600.0;
+
+/// How to look for solutions.
+#[derive(clap::ArgEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Strategy {
+    /// Pick one of the others for each algorithm, from its length and how
+    /// quickly the search grows with each reorient.
+    Auto,
+    /// Search every placement of reorients.
+    Exhaustive,
+    /// Search every placement of reorients, remembering nodes without
+    /// solutions so that they aren't searched again.
+    Transposition,
+    /// Simulated annealing over reorient placements, which is fast but may
+    /// miss the cheapest solutions.
+    Anneal,
+    /// Place reorients one gap at a time, keeping only the most promising
+    /// placements, which handles long algorithms but may miss the cheapest
+    /// solutions.
+    Beam,
+    /// Search every placement of reorients, deepening on the ETM that they
+    /// add instead of on how many there are, so that cheap solutions with
+    /// more reorients are found before expensive ones with fewer.
+    Cost,
+    /// Search each half of the algorithm on its own and join the halves,
+    /// which finds the same solutions as searching every placement in far
+    /// fewer nodes for long algorithms, but takes memory for the first half.
+    MeetInTheMiddle,
+}
+
+/// Returns the strategy that [`Strategy::Auto`] uses for `alg` with up to
+/// `max_depth` reorients on `optimizer`. Annealing is only picked if
+/// `can_anneal`.
+///
+/// Short algorithms are searched exhaustively. Others are searched with 0 and
+/// 1 reorients to measure how much the search grows with each reorient, and
+/// the full search is projected from that.
+pub fn pick_strategy(
+    optimizer: &RktOptimizer,
+    alg: &Alg,
+    max_depth: usize,
+    can_anneal: bool,
+) -> Strategy {
+    let gaps = alg.moves.len().saturating_sub(1);
+    if gaps <= SHORT_ALG_GAPS || max_depth <= 1 {
+        return Strategy::Exhaustive;
+    }
+    let probe = optimizer
+        .clone()
+        .with_transpositions(false)
+        .search_until(alg, 1, false, None, &mut None, None);
+    let &[first, second] = probe.nodes.as_slice() else {
+        // The probe found solutions, so the search will be short.
+        return Strategy::Exhaustive;
+    };
+    let growth = second as f64 / first.max(1) as f64;
+    let projected_nodes = second as f64 * growth.powi(max_depth.min(gaps) as i32 - 1);
+    if projected_nodes < SMALL_SEARCH_NODES {
+        Strategy::Exhaustive
+    } else if can_anneal && projected_nodes / nodes_per_sec(optimizer) > LONG_SEARCH_SECS {
+        Strategy::Anneal
+    } else {
+        Strategy::Transposition
+    }
+}
+
+/// Returns the number of search nodes visited per second, calibrating with
+/// `optimizer` the first time it is called.
+fn nodes_per_sec(optimizer: &RktOptimizer) -> f64 {
+    static NODES_PER_SEC: OnceLock<f64> = OnceLock::new();
+    *NODES_PER_SEC.get_or_init(|| calibrate(optimizer))
+}
+
+/// Returns the number of search nodes visited per second by `optimizer`,
+/// measured by doing the same work the search does at each node.
+pub fn calibrate(optimizer: &RktOptimizer) -> f64 {
+    let moves = &Alg::parse("R U R' U' R' F R2 U' R' U' R U R' F'")
+        .expect("calibration alg should parse")
+        .moves;
+    let table = optimizer.table();
+    let start = Instant::now();
+    let steps: Vec<MoveTable> = moves.iter().map(|&mv| MoveTable::of_moves(&[mv])).collect();
+    let pack_table = PackTable::new(&sticker_cube::colors(optimizer.mask()));
+    let mut state = StickerCube::solved();
+    for i in 0..CALIBRATION_NODES {
+        let reorient = Reorient::ALL[i % Reorient::ALL.len()];
+        state = state
+            .apply(&steps[i % steps.len()])
+            .apply(reorient.move_table());
+        std::hint::black_box(table.lower_bound_packed(state.pack_with(&pack_table)));
+    }
+    CALIBRATION_NODES as f64 / start.elapsed().as_secs_f64()
+}
+
+/// Returns the number of nodes visited by one iteration of the search with at
+/// most `max_reorients` reorients across `gaps` gaps, assuming nothing is
+/// pruned.
+pub fn max_nodes(gaps: usize, max_reorients: usize) -> f64 {
+    let branching = (Reorient::ALL.len() - 1) as f64;
+    // `counts[j]` is the number of nodes at the current depth that have used
+    // `j` reorients.
+    let mut counts = vec![0.0; max_reorients + 1];
+    counts[0] = 1.0;
+    let mut total = 1.0;
+    for _ in 0..gaps {
+        let mut next = vec![0.0; max_reorients + 1];
+        for j in 0..max_reorients {
+            next[j] += counts[j];
+            next[j + 1] += counts[j] * branching;
+        }
+        total += next.iter().sum::<f64>();
+        counts = next;
+    }
+    total
+}
+
+/// Returns a pruning table depth suited to searching algorithms of up to
+/// `alg_len` moves (or typical ones, if `None`) with up to `max_depth`
+/// reorients, whose table is built from turns of `faces`, a bitmask as
+/// returned by [`crate::parse_solver_faces()`], and takes at most `memory`
+/// bytes to build.
+///
+/// Each depth takes more than 10 times as long to build as the last but
+/// prunes the search further, so this picks the deepest table that takes no
+/// more work to build than the search would without pruning.
+pub fn auto_depth(alg_len: Option<usize>, max_depth: usize, faces: u8, memory: usize) -> u8 {
+    let gaps = alg_len.unwrap_or(TYPICAL_ALG_LEN).saturating_sub(1);
+    let search_nodes = max_nodes(gaps, max_depth.min(gaps));
+    let moves = face_moves(faces).len();
+    let initial_states = Reorient::ALL.len();
+    let mut depth = MIN_AUTO_DEPTH;
+    while depth < MAX_AUTO_DEPTH {
+        let next = depth + 1;
+        // Building the table applies every move to every state.
+        let build_nodes = (1..=next).fold(initial_states as f64, |states, i| {
+            states
+                * if i == 1 {
+                    moves as f64
+                } else {
+                    moves as f64 * 0.75
+                }
+        }) * moves as f64;
+        if build_nodes > search_nodes
+            || table::projected_memory(initial_states, next, moves) > memory
+        {
+            break;
+        }
+        depth = next;
+    }
+    depth
+}
+
+/// Returns how much memory a pruning table may take to build, in bytes: half
+/// of the memory that is free, if that can be detected.
+pub fn memory_budget() -> usize {
+    available_memory().map_or(DEFAULT_MEMORY_BUDGET, |bytes| bytes / 2)
+}
+
+/// Returns the memory available to start new programs, in bytes, if that can
+/// be detected.
+fn available_memory() -> Option<usize> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+    let kib: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Prints the iterations a search of `alg` would run and how long each is
+/// expected to take at `nodes_per_sec`.
+pub fn print(alg: &Alg, max_depth: usize, neutral: bool, nodes_per_sec: f64) {
+    let gaps = alg.moves.len().saturating_sub(1);
+    let iterations = std::cmp::min(alg.moves.len(), max_depth + 1);
+    let starts = if neutral { Reorient::ALL.len() } else { 1 };
+
+    println!(
+        "Search plan: {} moves ({gaps} gaps), up to {} reorients{}.",
+        alg.moves.len(),
+        iterations.saturating_sub(1),
+        if neutral {
+            ", from each of 24 starting orientations"
+        } else {
+            ""
+        },
+    );
+    println!(
+        "{:>9}  {:>12}  {:>9}  {:>10}",
+        "Reorients", "Max nodes", "Branching", "Est. time"
+    );
+    let mut total = 0.0;
+    let mut prev: Option<f64> = None;
+    for max_reorients in 0..iterations {
+        let nodes = max_nodes(gaps, max_reorients) * starts as f64;
+        total += nodes;
+        let branching = match prev {
+            Some(prev) => format!("{:.1}", nodes / prev),
+            None => "-".to_string(),
+        };
+        println!(
+            "{max_reorients:>9}  {nodes:>12.0}  {branching:>9}  {:>10}",
+            format_duration(nodes / nodes_per_sec),
+        );
+        prev = Some(nodes);
+    }
+    println!(
+        "Total: at most {total:.0} nodes, about {} at {nodes_per_sec:.0} nodes/s.",
+        format_duration(total / nodes_per_sec),
+    );
+    println!("These are upper bounds; pruning and stopping at the first iteration with solutions usually cut them substantially.");
+}
+
+/// Formats a number of seconds for humans.
+pub fn format_duration(secs: f64) -> String {
+    if !secs.is_finite() || secs > 1e9 {
+        "forever".to_string()
+    } else if secs < 1e-3 {
+        "<1ms".to_string()
+    } else if secs < 60.0 {
+        format!("{:?}", Duration::from_millis((secs * 1e3) as u64))
+    } else if secs < 3600.0 {
+        let secs = secs as u64;
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else if secs < 86400.0 * 2.0 {
+        format!("{:.1}h", secs / 3600.0)
+    } else {
+        format!("{:.1} days", secs / 86400.0)
+    }
+}
@@ -0,0 +1,119 @@
+//! Loading heuristics and cost models from dynamic libraries, so that
+//! alternative pruning strategies can be tried without changing this crate.
+//!
+//! A plugin is a `cdylib` that exports some of these C functions:
+//!
+//! ```c
+//! // Required. Must return PLUGIN_ABI_VERSION.
+//! uint32_t rocket_plugin_abi_version(void);
+//!
+//! // Cost model: returns the cost of reorient `index` (an index into
+//! // `Reorient::ALL`, where 0 is no reorient), or UINT32_MAX to keep the
+//! // cost from the command line and config file.
+//! uint32_t rocket_reorient_cost(uint32_t index);
+//!
+//! // Heuristic: returns a lower bound on the number of moves needed to reach
+//! // any rotation of the solved cube, or a negative number if it has none.
+//! // `facelets` holds `len` stickers in `cubesim` order, each being the face
+//! // of its color: 0 = U, 1 = L, 2 = F, 3 = R, 4 = B, 5 = D, 6 = ignored.
+//! int32_t rocket_lower_bound(const uint8_t *facelets, size_t len);
+//! ```
+//!
+//! The search uses the larger of the plugin's bound and the pruning table's,
+//! so a bound that overestimates will make it miss solutions. Searches with a
+//! plugin's bound are never reported as optimal, since it can't be checked.
+//! `rocket_lower_bound` is called from several threads at once.
+//!
+//! A plugin's bound is used by the optimizers it is given to with
+//! [`crate::RktOptimizer::with_plugin()`].
+
+use std::path::Path;
+
+use cubesim::Face;
+use libloading::Library;
+
+use crate::{CostModel, Reorient};
+
+/// Version of the interface above.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+type CostFn = unsafe extern "C" fn(u32) -> u32;
+type LowerBoundFn = unsafe extern "C" fn(*const u8, usize) -> i32;
+
+/// Functions loaded from a plugin.
+pub struct Plugin {
+    reorient_cost: Option<CostFn>,
+    lower_bound: Option<LowerBoundFn>,
+    /// Keeps the functions above loaded.
+    _library: Library,
+}
+
+/// Loads a plugin. Its costs only take effect in cost models passed to
+/// [`Plugin::apply_costs()`].
+pub fn load(path: &Path) -> Result<Plugin, String> {
+    // SAFETY: loading a library runs its initializers, which we have to trust
+    // the user about.
+    let library = unsafe { Library::new(path) }.map_err(|e| e.to_string())?;
+    // SAFETY: the signatures match the documented interface.
+    unsafe {
+        let version = library
+            .get::<unsafe extern "C" fn() -> u32>(b"rocket_plugin_abi_version")
+            .map_err(|_| "missing rocket_plugin_abi_version".to_string())?;
+        if version() != PLUGIN_ABI_VERSION {
+            return Err(format!(
+                "plugin implements version {} of the interface, but version {PLUGIN_ABI_VERSION} is required",
+                version(),
+            ));
+        }
+        let plugin = Plugin {
+            reorient_cost: library
+                .get::<CostFn>(b"rocket_reorient_cost")
+                .ok()
+                .map(|f| *f),
+            lower_bound: library
+                .get::<LowerBoundFn>(b"rocket_lower_bound")
+                .ok()
+                .map(|f| *f),
+            _library: library,
+        };
+        if plugin.reorient_cost.is_none() && plugin.lower_bound.is_none() {
+            return Err(
+                "plugin exports neither rocket_reorient_cost nor rocket_lower_bound".to_string(),
+            );
+        }
+        Ok(plugin)
+    }
+}
+
+impl Plugin {
+    /// Overrides the costs that the plugin assigns.
+    pub fn apply_costs(&self, cost_model: &mut CostModel) {
+        let Some(f) = self.reorient_cost else {
+            return;
+        };
+        for (i, &r) in Reorient::ALL.iter().enumerate().skip(1) {
+            // SAFETY: the signature matches the documented interface.
+            let cost = unsafe { f(i as u32) };
+            if cost != u32::MAX {
+                cost_model.set_cost(r, cost as usize);
+            }
+        }
+    }
+
+    /// Returns whether the plugin has a lower bound.
+    pub fn has_lower_bound(&self) -> bool {
+        self.lower_bound.is_some()
+    }
+
+    /// Returns the plugin's lower bound on the number of moves to solve
+    /// `state`, or 0 if it has none.
+    pub fn lower_bound(&self, state: impl IntoIterator<Item = Face>) -> i32 {
+        let Some(f) = self.lower_bound else {
+            return 0;
+        };
+        let facelets: Vec<u8> = state.into_iter().map(|face| face as u8).collect();
+        // SAFETY: the signature matches the documented interface, and the
+        // pointer is valid for `len` bytes.
+        unsafe { f(facelets.as_ptr(), facelets.len()) }.max(0)
+    }
+}
@@ -0,0 +1,95 @@
+//! Live progress line for long searches, so that they don't look hung.
+
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
+
+/// Time between redraws of the progress line.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+/// Frames of the spinner at the start of the progress line, followed by the
+/// one shown when it is done.
+const SPINNER: &str = "|/-\\ ";
+
+/// Counts shared between the search threads and the progress line.
+#[derive(Debug)]
+pub struct Counts {
+    /// Progress line, whose position is the number of nodes expanded.
+    bar: ProgressBar,
+    solutions: AtomicUsize,
+}
+impl Counts {
+    /// Adds to the number of nodes expanded.
+    pub fn add_nodes(&self, nodes: usize) {
+        self.bar.inc(nodes as u64);
+    }
+    /// Adds to the number of solutions found.
+    pub fn add_solutions(&self, solutions: usize) {
+        let total = self.solutions.fetch_add(solutions, Relaxed) + solutions;
+        self.bar.set_message(total.to_string());
+    }
+}
+
+/// Progress line for one search iteration, which is redrawn on stderr until
+/// it is dropped.
+pub struct Progress {
+    counts: Counts,
+}
+impl Progress {
+    /// Starts drawing progress for an iteration with `max_reorients`
+    /// reorients. Nothing is drawn unless stderr is a terminal and the output
+    /// isn't `deterministic`, meaning the same on every run.
+    pub fn start(max_reorients: usize, deterministic: bool) -> Self {
+        let style = ProgressStyle::with_template(
+            "{spinner} {prefix} reorients: {nodes} nodes ({rate}/s), {msg} solutions",
+        )
+        .expect("the progress template is valid")
+        .tick_chars(SPINNER)
+        .with_key("nodes", |state: &ProgressState, w: &mut dyn Write| {
+            let _ = w.write_str(&format_count(state.pos() as f64));
+        })
+        .with_key("rate", |state: &ProgressState, w: &mut dyn Write| {
+            let _ = w.write_str(&format_count(state.per_sec()));
+        });
+        let drawn = std::io::stderr().is_terminal() && !deterministic;
+        let bar = match drawn {
+            true => ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr()),
+            false => ProgressBar::hidden(),
+        }
+        .with_style(style)
+        .with_prefix(max_reorients.to_string())
+        .with_message("0");
+        if drawn {
+            bar.enable_steady_tick(REDRAW_INTERVAL);
+        }
+        Self {
+            counts: Counts {
+                bar,
+                solutions: AtomicUsize::new(0),
+            },
+        }
+    }
+
+    /// Returns the counts for the search to add to.
+    pub fn counts(&self) -> &Counts {
+        &self.counts
+    }
+}
+impl Drop for Progress {
+    fn drop(&mut self) {
+        // Erase the line so that the next message starts on a clean one.
+        self.counts.bar.finish_and_clear();
+    }
+}
+
+/// Formats a count with a metric suffix, such as `12.3M`.
+fn format_count(n: f64) -> String {
+    match n {
+        n if n >= 1e9 => format!("{:.1}G", n / 1e9),
+        n if n >= 1e6 => format!("{:.1}M", n / 1e6),
+        n if n >= 1e3 => format!("{:.1}k", n / 1e3),
+        n => format!("{n:.0}"),
+    }
+}
@@ -0,0 +1,183 @@
+//! Ordering of solutions by their cost and, among equally cheap ones, by how
+//! pleasant they are to execute.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::{distinct_reorients, Alg, CostModel, Reorient, Solution};
+
+/// Quantity that solutions are optimized for.
+#[derive(clap::ArgEnum, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Objective {
+    /// Number of ETM added by reorients and split double moves.
+    #[default]
+    Etm,
+    /// Estimated execution time of the whole algorithm.
+    Time,
+}
+
+/// Preferences used to order solutions.
+#[derive(Debug, Default, Clone)]
+pub struct Ranking {
+    /// What to minimize first.
+    pub objective: Objective,
+    /// Whether to prefer reorients that were used by earlier algorithms.
+    pub prefer_familiar: bool,
+    /// Whether to avoid consecutive reorients that reverse each other.
+    pub penalize_alternation: bool,
+
+    /// Number of times each reorient was used by earlier algorithms.
+    vocabulary: [usize; Reorient::ALL.len()],
+    /// User-supplied comfort score of each reorient.
+    comfort: [i64; Reorient::ALL.len()],
+}
+impl Ranking {
+    /// Sorts solutions from most preferred to least preferred, with costs
+    /// under `cost_model`.
+    pub fn sort(&self, cost_model: &CostModel, alg: &Alg, solutions: &mut [Solution]) {
+        // Prefer cheap solutions, then solutions that are comfortable to
+        // execute, then solutions that use fewer distinct reorients because
+        // they are easier to memorize.
+        solutions.sort_by_cached_key(|solution| {
+            (
+                alg.score(cost_model, self.objective, solution),
+                self.alternations(solution),
+                distinct_reorients(solution),
+                Reverse(self.familiarity(solution)),
+                Reverse(self.comfort(solution)),
+            )
+        });
+    }
+
+    /// Returns the number of consecutive reorients in a solution that rotate
+    /// around the same axis in opposite directions, or 0 if alternation is not
+    /// penalized.
+    pub fn alternations(&self, solution: &Solution) -> usize {
+        if !self.penalize_alternation {
+            return 0;
+        }
+        let reorients: Vec<Reorient> = solution.iter().copied().filter(|r| !r.is_none()).collect();
+        reorients
+            .windows(2)
+            .filter(|pair| pair[1].reverses(pair[0]))
+            .count()
+    }
+
+    /// Returns how many times the reorients in a solution were used by earlier
+    /// algorithms, or 0 if familiar reorients are not preferred.
+    pub fn familiarity(&self, solution: &Solution) -> usize {
+        if !self.prefer_familiar {
+            return 0;
+        }
+        solution.iter().map(|&r| self.vocabulary[r as usize]).sum()
+    }
+
+    /// Returns the total comfort score of the reorients in a solution.
+    pub fn comfort(&self, solution: &Solution) -> i64 {
+        solution.iter().map(|&r| self.comfort[r as usize]).sum()
+    }
+
+    /// Loads comfort scores from a file containing one `<reorient> <score>`
+    /// pair per line. Blank lines and lines starting with `#` are ignored.
+    /// Reorients that are not listed have a score of 0. Reorients may be
+    /// named with the cell names configured in `cost_model`.
+    pub fn load_comfort(&mut self, cost_model: &CostModel, contents: &str) -> Result<(), String> {
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (reorient, score) = line
+                .rsplit_once(char::is_whitespace)
+                .ok_or_else(|| format!("line {}: expected `<reorient> <score>`", i + 1))?;
+            let reorient = cost_model
+                .parse_reorient(reorient)
+                .map_err(|e| format!("line {}: {e}", i + 1))?;
+            let score = score
+                .parse()
+                .map_err(|_| format!("line {}: invalid score {score:?}", i + 1))?;
+            self.comfort[reorient as usize] = score;
+        }
+        Ok(())
+    }
+
+    /// Records the reorients used by a chosen solution so that later
+    /// algorithms prefer them.
+    pub fn record(&mut self, solution: &Solution) {
+        for &r in solution {
+            if !r.is_none() {
+                self.vocabulary[r as usize] += 1;
+            }
+        }
+    }
+}
+
+/// Returns a canonical representative of the reorient patterns related to a
+/// solution by mirroring each reorient or by inverting the whole pattern
+/// (reversing its order and inverting each reorient).
+pub fn family_key(solution: &Solution) -> Solution {
+    let mirror = |s: &Solution| -> Solution { s.iter().map(|r| r.mirror()).collect() };
+    let inverse = |s: &Solution| -> Solution { s.iter().rev().map(|r| r.inverse()).collect() };
+
+    let inverted = inverse(solution);
+    [
+        mirror(solution),
+        mirror(&inverted),
+        inverted,
+        solution.clone(),
+    ]
+    .into_iter()
+    .min()
+    .unwrap()
+}
+
+/// Groups solutions into families related by mirroring or inversion,
+/// preserving the order of the solutions so that the first member of each
+/// family is its most preferred solution.
+pub fn group_families(solutions: &[Solution]) -> Vec<Vec<Solution>> {
+    let mut families: Vec<Vec<Solution>> = vec![];
+    let mut family_indices = HashMap::new();
+    for solution in solutions {
+        let i = *family_indices
+            .entry(family_key(solution))
+            .or_insert_with(|| {
+                families.push(vec![]);
+                families.len() - 1
+            });
+        families[i].push(solution.clone());
+    }
+    families
+}
+
+/// Keeps at most `max` of the solutions with each score under `objective` and
+/// `cost_model`, which should be sorted as by [`Ranking::sort()`], and returns how many
+/// were dropped with each score, from lowest to highest score. Scores that
+/// lost no solutions are left out.
+pub fn cap_per_score(
+    cost_model: &CostModel,
+    alg: &Alg,
+    objective: Objective,
+    solutions: &mut Vec<Solution>,
+    max: usize,
+) -> Vec<(u64, usize)> {
+    let mut omitted: Vec<(u64, usize)> = vec![];
+    let mut kept = 0;
+    let mut previous = None;
+    solutions.retain(|solution| {
+        let score = alg.score(cost_model, objective, solution);
+        if previous != Some(score) {
+            previous = Some(score);
+            kept = 0;
+        }
+        kept += 1;
+        if kept <= max {
+            return true;
+        }
+        match omitted.last_mut() {
+            Some((s, count)) if *s == score => *count += 1,
+            _ => omitted.push((score, 1)),
+        }
+        false
+    });
+    omitted
+}
@@ -0,0 +1,137 @@
+//! Reading algorithms from the web: files of algorithms at URLs, and single
+//! algorithms in links to alg.cubing.net and Twizzle.
+
+use std::io::Read;
+
+/// Returns whether `s` is an HTTP or HTTPS URL.
+pub fn is_url(s: &str) -> bool {
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+/// Sends a GET request to `url`.
+pub fn get(url: &str) -> Result<ureq::Response, String> {
+    ureq::get(url)
+        .set("User-Agent", concat!("rocket/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| format!("error fetching {e}"))
+}
+
+/// Downloads the text at `url`.
+pub fn fetch(url: &str) -> Result<String, String> {
+    let mut contents = String::new();
+    get(url)?
+        .into_reader()
+        .read_to_string(&mut contents)
+        .map_err(|e| format!("error downloading {url}: {e}"))?;
+    Ok(contents)
+}
+
+/// Returns the algorithm in a link to alg.cubing.net or Twizzle, such as
+/// `https://alg.cubing.net/?alg=R_U_R-_U-`, or `None` if `s` isn't a URL.
+/// Comments and parentheses around groups of moves are left out.
+pub fn alg_from_link(s: &str) -> Option<Result<String, String>> {
+    let s = s.trim();
+    if !is_url(s) {
+        return None;
+    }
+    Some(parse_link(s))
+}
+
+fn parse_link(url: &str) -> Result<String, String> {
+    let (_, rest) = url.split_once("://").unwrap();
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    // alg.cubing.net writes spaces as `_` and primes as `-`.
+    let alg_cubing_net = host == "alg.cubing.net";
+    if !alg_cubing_net && host != "twizzle.net" && !host.ends_with(".twizzle.net") {
+        return Err(format!(
+            "{url} isn't a link to alg.cubing.net or Twizzle; files of algorithms are read with --input"
+        ));
+    }
+    let query = rest
+        .split_once('?')
+        .map_or("", |(_, query)| query.split('#').next().unwrap_or_default());
+    let value = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("alg="))
+        .ok_or_else(|| format!("{url} has no alg"))?;
+    let value = match alg_cubing_net {
+        true => value.replace('_', " ").replace('-', "'"),
+        false => value.to_string(),
+    };
+    let alg = percent_decode(&value.replace('+', " "))
+        .ok_or_else(|| format!("{url} has an invalid alg"))?;
+
+    let mut moves = vec![];
+    for line in alg.lines() {
+        let line = line.split("//").next().unwrap_or_default();
+        let repeated = line
+            .split(')')
+            .skip(1)
+            .any(|after| after.starts_with(|c: char| c.is_ascii_digit()));
+        if repeated {
+            return Err(format!(
+                "{url} repeats a group of moves, which isn't supported"
+            ));
+        }
+        moves.push(line.replace(['(', ')'], " "));
+    }
+    Ok(moves
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Decodes `%XX` escapes, or returns `None` if one is invalid or the result
+/// isn't UTF-8.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_algs_from_links() {
+        for (link, alg) in [
+            ("https://alg.cubing.net/?alg=R_U_R-_U-", "R U R' U'"),
+            (
+                "https://alg.cubing.net/?setup=F&alg=(R_U2_R-)_U-_%2F%2F_sexy%0AR",
+                "R U2 R' U' R",
+            ),
+            (
+                "https://alpha.twizzle.net/edit/?alg=R+U+R%27+U%27&puzzle=3x3x3#top",
+                "R U R' U'",
+            ),
+            ("  https://twizzle.net/edit/?alg=F-R  ", "F-R"),
+        ] {
+            assert_eq!(alg_from_link(link), Some(Ok(alg.to_string())), "{link}");
+        }
+    }
+
+    #[test]
+    fn rejects_links_without_algs() {
+        assert_eq!(alg_from_link("R U R' U'"), None);
+        for link in [
+            "https://example.com/?alg=R_U",
+            "https://alg.cubing.net/?setup=R",
+            "https://alg.cubing.net/?alg=R%2",
+            "https://alg.cubing.net/?alg=(R_U)2",
+        ] {
+            assert!(matches!(alg_from_link(link), Some(Err(_))), "{link}");
+        }
+    }
+}
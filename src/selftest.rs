@@ -0,0 +1,216 @@
+//! Checks that the reorient tables, costs, notations, and cube tables agree
+//! with each other, for `rocket selftest` and for other tools that embed
+//! them.
+
+use std::collections::HashMap;
+
+use cubesim::{Cube, Face, FaceletCube, Move, MoveVariant};
+
+use crate::perm::{self, Permutation, STICKERS};
+use crate::sticker_cube::{self, MoveTable, PackTable, StickerCube};
+use crate::{table, CostModel, Reorient};
+
+/// Check that the built-in tables are consistent with each other and with the
+/// current config.
+#[derive(clap::Args, Debug, Clone)]
+pub struct SelftestArgs {}
+
+/// Runs every check with `cost_model` from the current config and prints the
+/// results, and returns an error if any failed.
+pub fn run(_args: &SelftestArgs, cost_model: &CostModel) -> Result<(), String> {
+    let checks: [(&str, Result<(), String>); 4] = [
+        ("reorient tables", check_reorient_tables()),
+        ("notations", check_notations(cost_model)),
+        ("costs", check_costs(cost_model)),
+        ("cube tables", check_cube_tables()),
+    ];
+    let mut failed = 0;
+    for (name, result) in checks {
+        match result {
+            Ok(()) => println!("{name}: ok"),
+            Err(e) => {
+                failed += 1;
+                println!("{name}: FAILED: {e}");
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{failed} checks failed")),
+    }
+}
+
+/// Returns the stickers of a cube rotated by `moves`.
+fn rotated(moves: &[Move]) -> Vec<Face> {
+    FaceletCube::new(3).apply_moves(moves).state()
+}
+
+/// Checks that every reorient's RKT moves rotate the cube differently, that
+/// [`Reorient::then()`] gives the reorient with the same rotation as doing
+/// both, and that [`Reorient::inverse()`] undoes each reorient.
+pub fn check_reorient_tables() -> Result<(), String> {
+    let mut seen = HashMap::new();
+    for &r in Reorient::ALL {
+        if let Some(other) = seen.insert(rotated(r.equivalent_rkt_moves()), r) {
+            return Err(format!(
+                "{} and {} rotate the cube the same way",
+                other.xyz_name(),
+                r.xyz_name(),
+            ));
+        }
+    }
+    if !Reorient::None.equivalent_rkt_moves().is_empty() {
+        return Err("the null reorient rotates the cube".to_string());
+    }
+    for &a in Reorient::ALL {
+        for &b in Reorient::ALL {
+            let both = [a.equivalent_rkt_moves(), b.equivalent_rkt_moves()].concat();
+            if rotated(&both) != rotated(a.then(b).equivalent_rkt_moves()) {
+                return Err(format!(
+                    "{} then {} is not {}",
+                    a.xyz_name(),
+                    b.xyz_name(),
+                    a.then(b).xyz_name(),
+                ));
+            }
+        }
+        if a.then(a.inverse()) != Reorient::None {
+            return Err(format!(
+                "{} is not the inverse of {}",
+                a.inverse().xyz_name(),
+                a.xyz_name(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every reorient's name in XYZ, sticker, and hypercubing
+/// notation parses back to the same reorient, including with the cell names
+/// configured in `cost_model`.
+pub fn check_notations(cost_model: &CostModel) -> Result<(), String> {
+    for &r in Reorient::ALL {
+        let mut names = vec![
+            r.xyz_name().to_string(),
+            r.sticker_name().to_string(),
+            cost_model.localize(r.sticker_name()),
+        ];
+        if !r.is_none() {
+            for size in [2, 3] {
+                let name = r.hypercubing_name(size);
+                names.push(cost_model.localize(&name));
+                names.push(name);
+            }
+        }
+        for name in names {
+            match cost_model.parse_reorient(&name) {
+                Ok(parsed) if parsed == r => {}
+                Ok(parsed) => {
+                    return Err(format!(
+                        "{name} is {} but parses as {}",
+                        r.xyz_name(),
+                        parsed.xyz_name(),
+                    ))
+                }
+                Err(e) => return Err(format!("{name} is {}, but {e}", r.xyz_name())),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the null reorient is free and the rest aren't, that each
+/// reorient's expansion in `cost_model` rotates the cube the same way as the
+/// reorient, and that each macro used for a reorient performs it.
+pub fn check_costs(cost_model: &CostModel) -> Result<(), String> {
+    if cost_model.cost(Reorient::None) != 0 {
+        return Err("the null reorient isn't free".to_string());
+    }
+    for &r in &Reorient::ALL[1..] {
+        if cost_model.cost(r) == 0 {
+            return Err(format!("{} is free", r.xyz_name()));
+        }
+        if rotated(&cost_model.expansion(r)) != rotated(r.equivalent_rkt_moves()) {
+            return Err(format!(
+                "the expansion of {} rotates the cube differently",
+                r.xyz_name()
+            ));
+        }
+        if let Some(m) = cost_model.macro_for(r) {
+            if m.net() != r {
+                return Err(format!("macro {} doesn't perform {}", m.name, r.xyz_name()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that turning and packing a [`StickerCube`], with and without
+/// precomputed tables, and permuting stickers match turning a
+/// [`FaceletCube`].
+pub fn check_cube_tables() -> Result<(), String> {
+    let v = [
+        MoveVariant::Standard,
+        MoveVariant::Double,
+        MoveVariant::Inverse,
+    ];
+    let moves: Vec<Move> = v
+        .iter()
+        .flat_map(|&v| {
+            [
+                Move::U(v),
+                Move::L(v),
+                Move::F(v),
+                Move::R(v),
+                Move::B(v),
+                Move::D(v),
+            ]
+        })
+        .collect();
+    let colors = sticker_cube::colors(0);
+    let pack_table = PackTable::new(&colors);
+    let check = |cube: &StickerCube, facelets: &FaceletCube, what: &str| {
+        if cube.pack(&colors) != table::pack(facelets) {
+            return Err(format!("{what} gives the wrong stickers"));
+        }
+        if cube.pack_with(&pack_table) != cube.pack(&colors) {
+            return Err(format!("{what} packs differently with a table"));
+        }
+        Ok(())
+    };
+    for &r in Reorient::ALL {
+        let facelets = FaceletCube::new(3).apply_moves(r.equivalent_rkt_moves());
+        check(
+            r.sticker_cube(),
+            &facelets,
+            &format!("reorient {}", r.xyz_name()),
+        )?;
+    }
+    // Walk through a fixed sequence of every move and reorient, turning
+    // each kind of cube.
+    let (mut cube, mut via_tables) = (StickerCube::solved(), StickerCube::solved());
+    let mut facelets = FaceletCube::new(3);
+    let mut stickers = perm::solved_stickers();
+    for (i, &mv) in moves.iter().cycle().take(moves.len() * 4).enumerate() {
+        let r = Reorient::ALL[i * 7 % Reorient::ALL.len()];
+        let step = [&[mv], r.equivalent_rkt_moves()].concat();
+        cube = cube.then(&StickerCube::of_moves(&step));
+        via_tables = via_tables
+            .apply(&MoveTable::of_moves(&[mv]))
+            .apply(r.move_table());
+        facelets = facelets.apply_moves(&step);
+        stickers = Permutation::of_moves(&step).permute(&stickers);
+        check(&cube, &facelets, "turning")?;
+        let permuted: Vec<Face> = stickers[..STICKERS]
+            .iter()
+            .map(|&sticker| colors[sticker as usize])
+            .collect();
+        if permuted != facelets.state() {
+            return Err("permuting stickers gives the wrong stickers".to_string());
+        }
+        if via_tables != cube {
+            return Err("turning with move tables gives a different cube".to_string());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! How robust solutions are to uncertainty in reorient costs.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{Alg, Reorient, RktOptimizer, Solution};
+
+/// Ranks solutions under randomly perturbed reorient costs from `optimizer`'s
+/// cost model and prints how often each one is optimal.
+///
+/// Every solution with the fewest reorients is already known, and perturbing
+/// costs only changes which of them is cheapest, so this ranks `solutions`
+/// again instead of searching again.
+pub fn print(
+    optimizer: &RktOptimizer,
+    alg: &Alg,
+    solutions: &[Solution],
+    trials: usize,
+    perturbation: f64,
+) {
+    if solutions.is_empty() || trials == 0 {
+        return;
+    }
+    let mut rng = match optimizer.deterministic() {
+        true => StdRng::seed_from_u64(0),
+        false => StdRng::from_entropy(),
+    };
+    let cost_model = optimizer.cost_model();
+    let base_costs: Vec<f64> = Reorient::ALL
+        .iter()
+        .map(|&r| cost_model.cost(r) as f64)
+        .collect();
+    let cost = |solution: &Solution, costs: &[f64]| {
+        let reorients: f64 = solution.iter().map(|&r| costs[r as usize]).sum();
+        reorients + alg.extra_moves(solution) as f64
+    };
+
+    let mut optimal_count = vec![0; solutions.len()];
+    for _ in 0..trials {
+        let costs: Vec<f64> = base_costs
+            .iter()
+            .map(|c| c * rng.gen_range(1.0 - perturbation..=1.0 + perturbation))
+            .collect();
+        let costs_of_solutions: Vec<f64> = solutions.iter().map(|s| cost(s, &costs)).collect();
+        let min = costs_of_solutions
+            .iter()
+            .copied()
+            .fold(f64::INFINITY, f64::min);
+        for (count, c) in optimal_count.iter_mut().zip(costs_of_solutions) {
+            if c <= min + 1e-9 {
+                *count += 1;
+            }
+        }
+    }
+
+    let min_etm = solutions
+        .iter()
+        .map(|s| alg.cost(cost_model, s))
+        .min()
+        .unwrap_or(0);
+    let mut results: Vec<(usize, &Solution)> = optimal_count
+        .into_iter()
+        .zip(solutions)
+        .filter(|&(count, s)| count > 0 || alg.cost(cost_model, s) == min_etm)
+        .collect();
+    results.sort_by_key(|&(count, s)| (std::cmp::Reverse(count), alg.cost(cost_model, s)));
+
+    println!(
+        "Optimal in how many of {trials} trials with costs perturbed by up to {:.0}%:",
+        perturbation * 100.0,
+    );
+    for (count, solution) in results {
+        // Mark the solutions that are optimal under the unperturbed costs.
+        let marker = match alg.cost(cost_model, solution) == min_etm {
+            true => '*',
+            false => ' ',
+        };
+        println!(
+            "{:>4.0}% {marker} {}",
+            count as f64 * 100.0 / trials as f64,
+            optimizer.display(alg, solution),
+        );
+    }
+}
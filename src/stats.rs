@@ -0,0 +1,140 @@
+//! Monte Carlo statistics about the ETM overhead of random algorithms.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+use cubesim::{Move, MoveVariant};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{display_move, solution_cost, threads, Alg, RktOptimizer};
+
+/// Solve random rotationless algorithms and report how many reorients and
+/// ETM they need.
+#[derive(clap::Args, Debug, Clone)]
+pub struct StatsArgs {
+    /// Number of moves in each random algorithm.
+    #[clap(short, long, default_value_t = 12)]
+    length: usize,
+
+    /// Number of random algorithms to solve.
+    #[clap(short, long, default_value_t = 100)]
+    samples: usize,
+
+    /// Seed for the random number generator.
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Print each random algorithm along with its result.
+    #[clap(short, long)]
+    verbose: bool,
+}
+
+pub fn run(args: &StatsArgs, optimizer: &RktOptimizer, max_depth: usize) {
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let mut reorient_counts = BTreeMap::new();
+    let mut etm_counts = BTreeMap::new();
+    let mut unsolved = 0;
+
+    let algs: Vec<Vec<Move>> = (0..args.samples)
+        .map(|_| random_alg(&mut rng, args.length))
+        .collect();
+    let done = AtomicUsize::new(0);
+    let results = threads::map_on(optimizer.thread_count(), &algs, |alg| {
+        let (reorient_count, solutions) =
+            optimizer.search(&Alg::new(alg.clone()), max_depth, false, None, &mut None);
+        if !args.verbose {
+            let done = done.fetch_add(1, SeqCst) + 1;
+            eprint!("\rSolved {}/{} ...", done, args.samples);
+        }
+        let cost_model = optimizer.cost_model();
+        let min_cost = solutions.iter().map(|s| solution_cost(cost_model, s)).min();
+        (reorient_count, min_cost)
+    });
+
+    for (alg, (reorient_count, min_cost)) in algs.iter().zip(results) {
+        if args.verbose {
+            let alg_string: Vec<String> = alg.iter().copied().map(display_move).collect();
+            match min_cost {
+                Some(cost) => println!(
+                    "{}: {reorient_count} reorients, {cost} ETM",
+                    alg_string.join(" "),
+                ),
+                None => println!("{}: no solution", alg_string.join(" ")),
+            }
+        }
+
+        match min_cost {
+            Some(cost) => {
+                *reorient_counts.entry(reorient_count).or_insert(0) += 1;
+                *etm_counts.entry(cost).or_insert(0) += 1;
+            }
+            None => unsolved += 1,
+        }
+    }
+    if !args.verbose {
+        eprintln!();
+    }
+
+    let solved = args.samples - unsolved;
+    println!(
+        "Solved {solved} of {} random {}-move algorithms.",
+        args.samples, args.length,
+    );
+    if unsolved > 0 {
+        println!("{unsolved} of them need more than {max_depth} reorients.");
+    }
+    if solved == 0 {
+        return;
+    }
+    println!();
+    print_histogram("Reorients", &reorient_counts, solved);
+    println!();
+    print_histogram("Added ETM", &etm_counts, solved);
+    println!();
+
+    let mean = |counts: &BTreeMap<usize, usize>| {
+        counts.iter().map(|(k, v)| k * v).sum::<usize>() as f64 / solved as f64
+    };
+    let mean_etm = mean(&etm_counts);
+    println!(
+        "Mean: {:.2} reorients, {:.2} ETM ({:.3} ETM per move)",
+        mean(&reorient_counts),
+        mean_etm,
+        mean_etm / args.length as f64,
+    );
+}
+
+/// Returns a random rotationless algorithm that never turns the same face
+/// twice in a row.
+fn random_alg(rng: &mut impl Rng, length: usize) -> Vec<Move> {
+    use Move::{B, D, F, L, R, U};
+    use MoveVariant::*;
+
+    let faces = [R, L, U, D, F, B];
+    let variants = [Standard, Double, Inverse];
+
+    let mut ret = vec![];
+    let mut last_face = None;
+    while ret.len() < length {
+        let face = rng.gen_range(0..faces.len());
+        if last_face == Some(face) {
+            continue;
+        }
+        last_face = Some(face);
+        ret.push(faces[face](variants[rng.gen_range(0..variants.len())]));
+    }
+    ret
+}
+
+fn print_histogram(label: &str, counts: &BTreeMap<usize, usize>, total: usize) {
+    println!("{label:>9}  Algs");
+    for (&k, &v) in counts {
+        let bar = "#".repeat((v * 50).div_ceil(total));
+        println!("{k:>9}  {v:>4}  {bar}");
+    }
+}
@@ -0,0 +1,277 @@
+//! A 3x3x3 stored as one sticker for each piece, which turns without
+//! allocating and is much faster to search with than a [`FaceletCube`].
+//! Facelet cubes are only needed to read and print states.
+//!
+//! Each of the 26 slots (8 corners, 12 edges, and 6 centers) holds the
+//! sticker of the solved cube that is at its reference facelet, which is the
+//! first of the slot's facelets. That one sticker determines both which
+//! piece is in the slot and how it is twisted or flipped, so the rest of the
+//! slot's stickers are looked up in a table. This isn't a coordinate
+//! encoding: there is no single number for the permutation or orientation of
+//! the corners or edges, and nothing indexes tables by them. Rotations move
+//! the centers too, so a state has to keep them, and the stickers pack
+//! directly into the keys of the pruning table. Turning the cube is a table
+//! lookup for each slot, and each move that the search makes over and over
+//! has a [`MoveTable`] so that it is one array lookup for each slot.
+
+use std::collections::HashSet;
+
+use cubesim::{Cube, Face, FaceletCube, Move, MoveVariant};
+use lazy_static::lazy_static;
+
+use crate::perm::{Permutation, STICKERS};
+use crate::{mask, table};
+
+/// Number of pieces on a 3x3x3, counting centers.
+const SLOTS: usize = 26;
+
+/// Colors of the stickers of a solved cube, some of which may be blank.
+pub type Colors = [Face; STICKERS];
+
+/// Returns the colors of a solved cube whose stickers in `mask` are blank.
+pub fn colors(mask: u64) -> Colors {
+    mask::solved_cube(mask)
+        .state()
+        .try_into()
+        .expect("3x3x3 should have 54 stickers")
+}
+
+/// Which facelets make up each slot, and which stickers can be next to each
+/// other.
+struct Layout {
+    /// Slot that each facelet is part of.
+    slot: [u8; STICKERS],
+    /// Reference facelet of each slot.
+    reference: [u8; SLOTS],
+    /// `sticker_at[f][x]` is the sticker at facelet `f` when sticker `x` is
+    /// at the reference facelet of `f`'s slot.
+    sticker_at: Box<[[u8; STICKERS]; STICKERS]>,
+}
+
+lazy_static! {
+    static ref LAYOUT: Layout = make_layout();
+}
+
+fn make_layout() -> Layout {
+    // Facelets of the same piece turn with the same faces.
+    let pieces = mask::sticker_pieces();
+    let mut slot = [0; STICKERS];
+    let mut reference = [0; SLOTS];
+    let mut slot_pieces = vec![];
+    for (facelet, &piece) in pieces.iter().enumerate() {
+        let i = match slot_pieces.iter().position(|&p| p == piece) {
+            Some(i) => i,
+            None => {
+                reference[slot_pieces.len()] = facelet as u8;
+                slot_pieces.push(piece);
+                slot_pieces.len() - 1
+            }
+        };
+        slot[facelet] = i as u8;
+    }
+    assert_eq!(slot_pieces.len(), SLOTS, "3x3x3 should have 26 pieces");
+
+    // Move each piece around by every face turn and rotation, and record
+    // where its stickers end up relative to each other.
+    let v = MoveVariant::Standard;
+    let generators: Vec<Permutation> = [
+        Move::U(v),
+        Move::L(v),
+        Move::F(v),
+        Move::R(v),
+        Move::B(v),
+        Move::D(v),
+        Move::X(v),
+        Move::Y(v),
+        Move::Z(v),
+    ]
+    .iter()
+    .map(|&mv| Permutation::of_moves(&[mv]))
+    .collect();
+    let mut destinations = vec![[0; STICKERS]; generators.len()];
+    for (dest, generator) in destinations.iter_mut().zip(&generators) {
+        for i in 0..STICKERS {
+            dest[generator.source(i)] = i as u8;
+        }
+    }
+    let mut sticker_at = Box::new([[u8::MAX; STICKERS]; STICKERS]);
+    for s in 0..SLOTS {
+        let stickers: Vec<u8> = (0..STICKERS as u8)
+            .filter(|&f| slot[f as usize] as usize == s)
+            .collect();
+        // `placement[i]` is the facelet that `stickers[i]` is at.
+        let mut seen = HashSet::new();
+        let mut queue = vec![stickers.clone()];
+        while let Some(placement) = queue.pop() {
+            if !seen.insert(placement.clone()) {
+                continue;
+            }
+            for (&x, &at) in stickers.iter().zip(&placement) {
+                if reference[slot[at as usize] as usize] == at {
+                    for (&y, &y_at) in stickers.iter().zip(&placement) {
+                        sticker_at[y_at as usize][x as usize] = y;
+                    }
+                }
+            }
+            for dest in &destinations {
+                queue.push(placement.iter().map(|&f| dest[f as usize]).collect());
+            }
+        }
+    }
+
+    Layout {
+        slot,
+        reference,
+        sticker_at,
+    }
+}
+
+/// State of a 3x3x3, stored as the sticker at one facelet of each piece.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StickerCube {
+    /// Sticker at the reference facelet of each slot.
+    stickers: [u8; SLOTS],
+}
+impl StickerCube {
+    /// Returns the solved cube.
+    pub fn solved() -> Self {
+        Self {
+            stickers: LAYOUT.reference,
+        }
+    }
+
+    /// Returns the state reached by applying `moves` to the solved cube,
+    /// which turns any cube the same way when used with
+    /// [`StickerCube::then()`].
+    pub fn of_moves(moves: &[Move]) -> Self {
+        let permutation = Permutation::of_moves(moves);
+        Self {
+            stickers: LAYOUT
+                .reference
+                .map(|f| permutation.source(f as usize) as u8),
+        }
+    }
+
+    /// Returns the state reached by doing this one and then `next`.
+    pub fn then(&self, next: &Self) -> Self {
+        let layout = &*LAYOUT;
+        Self {
+            stickers: next.stickers.map(|from| {
+                let from_slot = layout.slot[from as usize];
+                layout.sticker_at[from as usize][self.stickers[from_slot as usize] as usize]
+            }),
+        }
+    }
+
+    /// Returns the state reached by turning this one with `table`, which is
+    /// the same as [`StickerCube::then()`] with the cube the table was made
+    /// from, but faster.
+    pub fn apply(&self, table: &MoveTable) -> Self {
+        let mut stickers = [0; SLOTS];
+        for (s, sticker) in stickers.iter_mut().enumerate() {
+            *sticker = table.stickers[s][self.stickers[table.source[s] as usize] as usize];
+        }
+        Self { stickers }
+    }
+
+    /// Returns the color at each facelet, given the `colors` of the solved
+    /// cube.
+    pub fn facelets<'a>(&'a self, colors: &'a Colors) -> impl Iterator<Item = Face> + 'a {
+        let layout = &*LAYOUT;
+        (0..STICKERS).map(move |f| {
+            let reference_sticker = self.stickers[layout.slot[f] as usize];
+            colors[layout.sticker_at[f][reference_sticker as usize] as usize]
+        })
+    }
+
+    /// Returns the sticker of the solved cube that is at `facelet`.
+    pub fn sticker_at(&self, facelet: usize) -> u8 {
+        let layout = &*LAYOUT;
+        layout.sticker_at[facelet][self.stickers[layout.slot[facelet] as usize] as usize]
+    }
+
+    /// Returns the colors at each facelet packed like [`table::pack()`].
+    pub fn pack(&self, colors: &Colors) -> [u64; 3] {
+        table::pack_faces(self.facelets(colors))
+    }
+
+    /// Returns the same as [`StickerCube::pack()`] with the colors that
+    /// `table` was made from, but faster.
+    pub fn pack_with(&self, table: &PackTable) -> [u64; 3] {
+        let mut words = [0; 3];
+        for (s, &sticker) in self.stickers.iter().enumerate() {
+            let slot_words = &table.words[s][sticker as usize];
+            for (word, slot_word) in words.iter_mut().zip(slot_words) {
+                *word |= slot_word;
+            }
+        }
+        words
+    }
+
+    /// Returns the state as a facelet cube, given the `colors` of the solved
+    /// cube.
+    pub fn to_facelet_cube(&self, colors: &Colors) -> FaceletCube {
+        FaceletCube::from(self.facelets(colors).collect::<Vec<_>>())
+    }
+}
+
+/// Transitions of every slot's sticker under one move, for turning cubes with
+/// [`StickerCube::apply()`].
+#[derive(Debug, Clone)]
+pub struct MoveTable {
+    /// Slot that each slot's piece comes from.
+    source: [u8; SLOTS],
+    /// `stickers[s][x]` is the sticker that ends up at slot `s`'s reference
+    /// facelet when sticker `x` is at the reference facelet of `source[s]`.
+    stickers: Box<[[u8; STICKERS]; SLOTS]>,
+}
+impl MoveTable {
+    /// Returns the table for turning cubes the way that the solved cube is
+    /// turned into `mv`.
+    pub fn new(mv: &StickerCube) -> Self {
+        let layout = &*LAYOUT;
+        let mut source = [0; SLOTS];
+        let mut stickers = Box::new([[0; STICKERS]; SLOTS]);
+        for (s, &from) in mv.stickers.iter().enumerate() {
+            source[s] = layout.slot[from as usize];
+            stickers[s] = layout.sticker_at[from as usize];
+        }
+        Self { source, stickers }
+    }
+
+    /// Returns the table for turning cubes by `moves`.
+    pub fn of_moves(moves: &[Move]) -> Self {
+        Self::new(&StickerCube::of_moves(moves))
+    }
+}
+
+/// Packed colors of every slot's facelets for each sticker at its reference
+/// facelet, for packing cubes with [`StickerCube::pack_with()`].
+#[derive(Debug, Clone)]
+pub struct PackTable {
+    /// `words[s][x]` has the packed colors of slot `s`'s facelets when
+    /// sticker `x` is at its reference facelet, and zeros elsewhere.
+    words: Box<[[[u64; 3]; STICKERS]; SLOTS]>,
+}
+impl PackTable {
+    /// Returns the table for packing cubes whose stickers are colored as in
+    /// `colors` when solved.
+    pub fn new(colors: &Colors) -> Self {
+        let layout = &*LAYOUT;
+        let mut words = Box::new([[[0; 3]; STICKERS]; SLOTS]);
+        for f in 0..STICKERS {
+            let s = layout.slot[f] as usize;
+            for x in 0..STICKERS {
+                let sticker = layout.sticker_at[f][x];
+                if sticker == u8::MAX {
+                    continue;
+                }
+                let packed = table::pack_sticker(f, colors[sticker as usize]);
+                for (word, packed) in words[s][x].iter_mut().zip(packed) {
+                    *word |= packed;
+                }
+            }
+        }
+        Self { words }
+    }
+}
@@ -0,0 +1,184 @@
+//! Deeper pruning tables for searches that can only turn a few faces, such as
+//! searches of 2-gen algorithms with a cost model that forbids the reorients
+//! that would move them onto other faces.
+//!
+//! The pruning table bounds the moves left by the distance from solved using
+//! every face turn, since reorients move the rest of an algorithm onto other
+//! faces. When the forbidden moves leave every move of the algorithm on the
+//! same few faces in every orientation, though, a search only ever turns
+//! those faces, and so it can't solve a state in fewer moves than their turns
+//! alone take. There are far fewer states within some distance using a few
+//! faces, so a table of them reaches much deeper in the same memory: about 10
+//! moves for ⟨R, U⟩ in what a depth-5 table of every face takes.
+//!
+//! This is also the bound for last-layer algorithms, which mostly turn R, U,
+//! and F. A table of last-layer cases alone wouldn't be one: a search tracks
+//! the algorithm's moves and reorients together, and each reorient moves the
+//! rest of the algorithm onto other faces, so the states in between scramble
+//! the first two layers even when the algorithm doesn't.
+//!
+//! Face turns don't move the centers, so every state that a search reaches is
+//! a rotation followed by face turns. Unlike the [`crate::pdb`] databases,
+//! these tables are of turns of physical faces, so they look states up with
+//! the face turns before the rotation instead.
+
+use std::collections::HashSet;
+
+use cubesim::{Move, MoveVariant};
+
+use crate::perm::STICKERS;
+use crate::sticker_cube::{self, PackTable, StickerCube};
+use crate::table::{self, PruningTable};
+use crate::{face_moves, is_face_move, Alg, Reorient, ALL_SOLVER_FACES};
+
+/// Most faces that a search can turn for a table of their turns to be
+/// worth building. Tables of more faces aren't much deeper than the pruning
+/// table.
+const MAX_FACES: u32 = 3;
+
+/// Number of moves needed to reach each state from solved using the turns of
+/// a few faces, for every state within some depth of solved.
+pub struct SubgroupTable {
+    table: PruningTable,
+    /// Table for packing states with every sticker.
+    pack_table: PackTable,
+    /// Most turns of the faces that a state a search stops at can be from
+    /// solved.
+    slack: i32,
+}
+impl SubgroupTable {
+    /// Builds a table of the turns of `faces`, a bitmask as returned by
+    /// [`crate::parse_solver_faces()`], as deep as it can in `max_memory`
+    /// bytes. `near_moves` are the moves that a search may still be from a
+    /// solved state when it stops, which are the pruning table's moves, and
+    /// `up_to_auf` is whether a turn of the U layer counts as solved.
+    ///
+    /// Returns `None` if a state that a search stops at might take many
+    /// turns of the faces to solve, so that the table can't bound the moves
+    /// left.
+    pub fn new(faces: u8, up_to_auf: bool, near_moves: &[Move], max_memory: usize) -> Option<Self> {
+        let moves = face_moves(faces);
+        let turns: HashSet<StickerCube> = moves
+            .iter()
+            .map(|&mv| StickerCube::of_moves(&[mv]))
+            .collect();
+        let two_turns: HashSet<StickerCube> = turns
+            .iter()
+            .flat_map(|a| turns.iter().map(|b| a.then(b)))
+            .collect();
+        // Stickers that no turn of the faces moves, which only states outside
+        // the subgroup move.
+        let fixed: Vec<usize> = (0..STICKERS)
+            .filter(|&i| turns.iter().all(|turn| turn.sticker_at(i) == i as u8))
+            .collect();
+
+        // A search stops at a rotation, then an AUF if `up_to_auf`, then any
+        // of `near_moves`. With the rotation moved to the end, that is a turn
+        // of some face followed by a turn of another, which takes at most two
+        // turns of the faces if the state is in the subgroup at all.
+        let mut aufs = vec![StickerCube::solved()];
+        if up_to_auf {
+            aufs.extend(
+                [
+                    MoveVariant::Standard,
+                    MoveVariant::Double,
+                    MoveVariant::Inverse,
+                ]
+                .map(|v| StickerCube::of_moves(&[Move::U(v)])),
+            );
+        }
+        let near: Vec<StickerCube> = std::iter::once(StickerCube::solved())
+            .chain(near_moves.iter().map(|&mv| StickerCube::of_moves(&[mv])))
+            .collect();
+        let mut slack = 0;
+        for &r in Reorient::ALL {
+            for auf in &aufs {
+                for mv in &near {
+                    let end = r
+                        .sticker_cube()
+                        .then(auf)
+                        .then(mv)
+                        .then(r.inverse().sticker_cube());
+                    if fixed.iter().any(|&i| end.sticker_at(i) != i as u8) {
+                        continue;
+                    }
+                    let distance = if end == StickerCube::solved() {
+                        0
+                    } else if turns.contains(&end) {
+                        1
+                    } else if two_turns.contains(&end) {
+                        2
+                    } else {
+                        return None;
+                    };
+                    slack = slack.max(distance);
+                }
+            }
+        }
+
+        let colors = sticker_cube::colors(0);
+        Some(Self {
+            table: PruningTable::with_memory_limit(
+                &colors,
+                &[StickerCube::solved()],
+                table::MAX_DEPTH,
+                moves,
+                max_memory,
+            ),
+            pack_table: PackTable::new(&colors),
+            slack,
+        })
+    }
+
+    /// Returns the depth of the table.
+    pub fn depth(&self) -> u8 {
+        self.table.depth()
+    }
+
+    /// Returns a lower bound like [`crate::RktOptimizer::lower_bound()`] on
+    /// the number of moves needed to solve `state`, a state that a search
+    /// reached in `orientation` by turning only the faces of the table. Like
+    /// that bound, it may be one more than the moves left to a state that the
+    /// search stops at.
+    pub fn lower_bound(&self, state: &StickerCube, orientation: Reorient) -> i32 {
+        let turns = state.then(orientation.inverse().sticker_cube());
+        let bound = self
+            .table
+            .lower_bound_packed(turns.pack_with(&self.pack_table));
+        bound + 1 - self.slack
+    }
+}
+
+/// Returns the faces that a search of `alg` can turn, as a bitmask as
+/// returned by [`crate::parse_solver_faces()`], if it can only turn a few of
+/// them because of the moves in `forbidden` in each orientation.
+pub fn faces(alg: &Alg, forbidden: &[Vec<Move>]) -> Option<u8> {
+    let mut faces = 0;
+    for &r in Reorient::ALL {
+        for &mv in &alg.moves {
+            if forbidden[r as usize].contains(&mv) {
+                continue;
+            }
+            let turn = physical_turn(r, mv)?;
+            faces |= (0..6)
+                .map(|i| 1 << i)
+                .find(|&face| face_moves(face).contains(&turn))?;
+        }
+    }
+    (faces != ALL_SOLVER_FACES && faces.count_ones() <= MAX_FACES).then_some(faces)
+}
+
+/// Returns the turn of a physical face that making `mv` in `orientation`
+/// makes, if `mv` is a face turn.
+fn physical_turn(orientation: Reorient, mv: Move) -> Option<Move> {
+    if !is_face_move(mv) {
+        return None;
+    }
+    let turn = orientation
+        .sticker_cube()
+        .then(&StickerCube::of_moves(&[mv]))
+        .then(orientation.inverse().sticker_cube());
+    face_moves(ALL_SOLVER_FACES)
+        .into_iter()
+        .find(|&face_move| StickerCube::of_moves(&[face_move]) == turn)
+}
@@ -0,0 +1,155 @@
+//! Finding cheaper algorithms for the same case in a library of candidates.
+
+use std::path::Path;
+
+use cubesim::{simplify_moves, Cube, Move, MoveVariant};
+
+use crate::{display_move, invert_moves, threads, Alg, CostModel, RktOptimizer, Solution};
+
+/// Library used when no candidate file is given.
+const BUNDLED_LIBRARY: &str = include_str!("pll.txt");
+
+/// Algorithm from a library, labeled by the case it solves.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub case: String,
+    pub moves: Vec<Move>,
+}
+
+/// Cheapest substitute found for an algorithm.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub case: String,
+    /// Candidate with whatever AUFs make it match the original algorithm.
+    pub alg: Alg,
+    pub solution: Solution,
+}
+impl Suggestion {
+    /// Returns the total ETM of the substitute under `cost_model`, including
+    /// its moves.
+    pub fn etm(&self, cost_model: &CostModel) -> usize {
+        self.alg.original_len() + self.alg.cost(cost_model, &self.solution)
+    }
+}
+
+/// Loads a candidate file, or the bundled library if there is none. Each line
+/// is `<case>: <algorithm>`; blank lines and lines starting with `#` are
+/// ignored.
+pub fn load(path: Option<&Path>) -> Result<Vec<Candidate>, String> {
+    let contents = match path {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| format!("error reading {}: {e}", path.display()))?,
+        None => BUNDLED_LIBRARY.to_string(),
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            let (case, alg) = line
+                .split_once(':')
+                .ok_or_else(|| format!("line {}: expected `<case>: <algorithm>`", i + 1))?;
+            Ok(Candidate {
+                case: case.trim().to_string(),
+                moves: Alg::parse(alg)?.moves,
+            })
+        })
+        .collect()
+}
+
+/// Solves every candidate that has the same effect as `alg`, up to a U turn
+/// before and after, with `optimizer`, and returns the one with the lowest
+/// total ETM.
+pub fn find(
+    optimizer: &RktOptimizer,
+    alg: &Alg,
+    candidates: &[Candidate],
+    max_depth: usize,
+) -> Option<Suggestion> {
+    let cost_model = optimizer.cost_model();
+    let matches: Vec<(&Candidate, Vec<Move>)> = candidates
+        .iter()
+        .filter_map(|c| Some((c, with_aufs(optimizer, &alg.moves, &c.moves)?)))
+        .collect();
+    let results = threads::map_on(optimizer.thread_count(), &matches, |(candidate, moves)| {
+        let substitute = Alg {
+            start: alg.start,
+            ..Alg::new(moves.clone())
+        };
+        let (_, solutions) = optimizer.search(&substitute, max_depth, false, None, &mut None);
+        let solution = solutions
+            .into_iter()
+            .min_by_key(|s| substitute.cost(cost_model, s))?;
+        Some(Suggestion {
+            case: candidate.case.clone(),
+            alg: substitute,
+            solution,
+        })
+    });
+    results
+        .into_iter()
+        .flatten()
+        .min_by_key(|suggestion| suggestion.etm(cost_model))
+}
+
+/// Returns `candidate` with U turns added before and after so that it has
+/// the same effect as `target`, or `None` if no U turns do that.
+fn with_aufs(optimizer: &RktOptimizer, target: &[Move], candidate: &[Move]) -> Option<Vec<Move>> {
+    let target_state = optimizer.solved_cube().apply_moves(target);
+    let table = optimizer.table();
+    let aufs = [
+        vec![],
+        vec![Move::U(MoveVariant::Standard)],
+        vec![Move::U(MoveVariant::Double)],
+        vec![Move::U(MoveVariant::Inverse)],
+    ];
+    aufs.iter()
+        .flat_map(|before| aufs.iter().map(move |after| (before, after)))
+        .map(|(before, after)| {
+            simplify_moves(&[before.as_slice(), candidate, after.as_slice()].concat())
+        })
+        .find(|moves| {
+            // Undoing the candidate must leave a rotation of the solved cube.
+            let state = target_state.apply_moves(&invert_moves(moves));
+            table.within_one_move(&state) && table.lower_bound(&state) == 0
+        })
+}
+
+/// Prints the best substitute for an algorithm whose best solution takes
+/// `etm` ETM in total, if it is cheaper.
+pub fn print(
+    optimizer: &RktOptimizer,
+    alg: &Alg,
+    etm: usize,
+    candidates: &[Candidate],
+    max_depth: usize,
+) {
+    let cost_model = optimizer.cost_model();
+    match find(optimizer, alg, candidates, max_depth) {
+        Some(suggestion) if suggestion.etm(cost_model) < etm => {
+            let moves: Vec<String> = suggestion
+                .alg
+                .moves
+                .iter()
+                .copied()
+                .map(display_move)
+                .collect();
+            println!(
+                "Cheaper alternative for {} ({} ETM instead of {etm}): {}",
+                suggestion.case,
+                suggestion.etm(cost_model),
+                moves.join(" "),
+            );
+            println!(
+                "{}",
+                optimizer.display(&suggestion.alg, &suggestion.solution)
+            );
+        }
+        Some(suggestion) => println!(
+            "No cheaper alternative; the best for {} takes {} ETM.",
+            suggestion.case,
+            suggestion.etm(cost_model),
+        ),
+        None => println!("No alternatives for this case in the library."),
+    }
+}
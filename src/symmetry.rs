@@ -0,0 +1,127 @@
+//! Symmetry of the first move of an algorithm, which makes some reorients
+//! after it interchangeable.
+//!
+//! If rotating the cube by `σ` right after the first move leaves the same
+//! state as rotating it by some `τ` right before the algorithm, as `Ox` does
+//! after `R`, then the branch that starts with `σ` then `r` reaches the same
+//! states as the branch that starts with `r`, only seen from another side.
+//! Being solved and every lower bound are the same from every side, so both
+//! branches have the same solutions apart from the first reorient, and only
+//! one of them needs to be searched.
+
+use cubesim::{Face, Move};
+
+use crate::sticker_cube::{self, StickerCube};
+use crate::{Alg, Reorient, Solution};
+
+/// Returns the rotations `σ` such that reorienting by `σ.then(r)` at the
+/// first gap of `alg` has the same solutions as reorienting by `r`, apart
+/// from the first reorient, including [`Reorient::None`]. Returns only
+/// [`Reorient::None`] if the algorithm has no such symmetry or its
+/// constraints break it.
+///
+/// `mask` is the stickers that are ignored, which have to look the same from
+/// each side, and `forbidden` is the moves that can't be made in each
+/// orientation, which have to be none.
+pub fn first_gap(alg: &Alg, mask: u64, forbidden: &[Vec<Move>]) -> Vec<Reorient> {
+    let boundaries = alg.boundaries();
+    if alg.moves.len() <= 1
+        || !alg.allows_reorient(0)
+        || boundaries[1..].iter().any(Option::is_some)
+        || forbidden.iter().any(|moves| !moves.is_empty())
+    {
+        return vec![Reorient::None];
+    }
+    let colors = sticker_cube::colors(mask);
+    let solved: Vec<_> = StickerCube::solved().facelets(&colors).collect();
+    let after_first = alg
+        .start
+        .sticker_cube()
+        .then(&StickerCube::of_moves(&alg.moves[..1]));
+    Reorient::ALL
+        .iter()
+        .copied()
+        .filter(|&sigma| {
+            let rotated = after_first.then(sigma.sticker_cube());
+            Reorient::ALL.iter().any(|&tau| {
+                tau.sticker_cube().then(&after_first) == rotated
+                    && permutes_colors(&solved, tau.sticker_cube().facelets(&colors))
+            })
+        })
+        .collect()
+}
+
+/// Returns whether `rotated` is `solved` with its colors permuted, keeping
+/// blank stickers blank, so that states that look the same still look the
+/// same after the rotation.
+fn permutes_colors(solved: &[Face], rotated: impl Iterator<Item = Face>) -> bool {
+    let mut pairs: Vec<(Face, Face)> = vec![];
+    solved.iter().zip(rotated).all(
+        |(&a, b)| match pairs.iter().find(|&&(x, y)| x == a || y == b) {
+            Some(&(x, y)) => x == a && y == b,
+            None => {
+                pairs.push((a, b));
+                true
+            }
+        },
+    ) && pairs.iter().all(|&(x, y)| (x == Face::X) == (y == Face::X))
+}
+
+/// Returns the reorient that represents `reorient` among those that
+/// `symmetries` make interchangeable with it: whichever comes first in
+/// `order`.
+pub fn representative(reorient: Reorient, symmetries: &[Reorient], order: &[Reorient]) -> Reorient {
+    symmetries
+        .iter()
+        .map(|sigma| sigma.then(reorient))
+        .min_by_key(|r| order.iter().position(|o| o == r))
+        .unwrap_or(reorient)
+}
+
+/// Adds the solutions that the search skipped to `solutions`, which were
+/// found by searching only the representative of each set of interchangeable
+/// first reorients, in `order`. Solutions with more than `max_reorients`
+/// reorients are left out, as are branches without solutions in `order`.
+pub fn expand(
+    solutions: Vec<Solution>,
+    symmetries: &[Reorient],
+    order: &[Reorient],
+    max_reorients: usize,
+) -> Vec<Solution> {
+    if symmetries.len() <= 1 {
+        return solutions;
+    }
+    order
+        .iter()
+        .flat_map(|&first| {
+            let rep = representative(first, symmetries, order);
+            solutions.iter().filter(move |s| s[0] == rep).map(move |s| {
+                let mut s = s.clone();
+                s[0] = first;
+                s
+            })
+        })
+        .filter(|s| s.iter().filter(|r| !r.is_none()).count() <= max_reorients)
+        .collect()
+}
+
+/// Keeps only the first of each set of solutions that differ only in
+/// interchangeable first reorients.
+pub fn collapse(alg: &Alg, mask: u64, forbidden: &[Vec<Move>], solutions: &mut Vec<Solution>) {
+    let symmetries = first_gap(alg, mask, forbidden);
+    let mut seen = vec![];
+    solutions.retain(|s| {
+        let Some((&first, rest)) = s.split_first() else {
+            return true;
+        };
+        let key = (
+            representative(first, &symmetries, Reorient::ALL),
+            rest.to_vec(),
+        );
+        let new = !seen.contains(&key);
+        if new {
+            seen.push(key);
+        }
+        new
+    });
+}
@@ -0,0 +1,542 @@
+//! Pruning table giving a lower bound on the number of moves needed to solve
+//! a cube, which can be saved to disk so that it only has to be built once.
+//!
+//! The table is a flat array of 4-bit entries, two to a byte, like the
+//! [`crate::pdb`] databases. There are far too many states of the cube for
+//! an entry each, so a state's entry is picked by a hash of its stickers, and
+//! holds the least distance of the states that hash to it. States that share
+//! an entry only get a smaller bound than their own, so the table never
+//! prunes a solution. The array has [`ENTRIES_PER_STATE`] entries for every
+//! state in it, so a state further than the depth of the table shares an
+//! entry with one in it about once in that many lookups.
+//!
+//! Whether a state is solved is never left to the hash: the states within one
+//! move of solved are kept exactly, and [`PruningTable::within_one_move()`]
+//! checks against them.
+
+use std::collections::HashSet;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use cubesim::{Cube, Face, Move};
+
+use crate::sticker_cube::{Colors, MoveTable, PackTable, StickerCube};
+
+/// Start of every table file, followed by the format version.
+const MAGIC: &[u8; 8] = b"RKTPRUNE";
+const VERSION: u32 = 5;
+
+/// Greatest depth of a table, since [`EMPTY`] takes the last value of an
+/// entry.
+pub const MAX_DEPTH: u8 = 14;
+/// Value of an entry that no state within the depth of the table hashes to.
+const EMPTY: u8 = 0xF;
+/// Number of entries per state in the table, rounded up to a power of 2.
+const ENTRIES_PER_STATE: usize = 16;
+/// Number of stickers packed into each word of a packed state, at 3 bits
+/// each.
+const STICKERS_PER_WORD: usize = 21;
+
+/// Packed states at the two depths before the one being built, which are the
+/// only ones besides that depth's that its states can be reached again from.
+type Layers = [HashSet<[u64; 3]>; 2];
+
+/// Number of moves needed to reach each state from a set of initial states,
+/// for every state within some depth of them.
+#[derive(Clone)]
+pub struct PruningTable {
+    /// Entries packed two to a byte, the even one in the low bits.
+    entries: Vec<u8>,
+    /// Number of entries, as a power of 2.
+    entry_bits: u32,
+    /// Number of states at each distance, from 0 up to the depth.
+    counts: Vec<usize>,
+    /// Packed states within one move of the initial states.
+    near: HashSet<[u64; 3]>,
+    /// Moves that the table is built from.
+    pub moves: Vec<Move>,
+}
+impl PruningTable {
+    /// Builds a table of every state within `depth` moves of
+    /// `initial_states`, whose stickers are colored as in `colors` when
+    /// solved. `depth` must be at most [`MAX_DEPTH`].
+    pub fn new(
+        colors: &Colors,
+        initial_states: &[StickerCube],
+        depth: u8,
+        moves: Vec<Move>,
+    ) -> Self {
+        Self::with_memory_limit(colors, initial_states, depth, moves, usize::MAX)
+    }
+
+    /// Builds a table like [`PruningTable::new()`], but stops at a smaller
+    /// depth if the next depth is projected to take more than `max_memory`
+    /// bytes to build. Check [`PruningTable::depth()`] to see how deep it got.
+    pub fn with_memory_limit(
+        colors: &Colors,
+        initial_states: &[StickerCube],
+        depth: u8,
+        moves: Vec<Move>,
+        max_memory: usize,
+    ) -> Self {
+        assert!(depth <= MAX_DEPTH, "pruning table is too deep");
+        let pack_table = PackTable::new(colors);
+        let mut layers = initial_layers(initial_states, &pack_table);
+        // Hashes of the states at each distance, which are only put in the
+        // array once the number of states is known.
+        let mut hashes: Vec<Vec<u64>> = vec![layers[1].iter().map(|&p| hash(p)).collect()];
+        let move_tables: Vec<MoveTable> =
+            moves.iter().map(|&mv| MoveTable::of_moves(&[mv])).collect();
+        let near = initial_states
+            .iter()
+            .flat_map(|cube| {
+                let moved = move_tables.iter().map(|mv| cube.apply(mv));
+                std::iter::once(*cube).chain(moved)
+            })
+            .map(|cube| cube.pack_with(&pack_table))
+            .collect();
+        let mut frontier: Vec<StickerCube> = initial_states.to_vec();
+        let mut len = hashes[0].len();
+        // Number of new states per state at the last depth.
+        let mut growth = moves.len() as f64;
+        for _ in 1..=depth {
+            // Building the next depth needs this depth's states, the next
+            // depth's states, and the hashes and array for all of them.
+            let projected_len = (frontier.len() as f64 * growth) as usize;
+            let projected_memory =
+                build_memory(len + projected_len, frontier.len() + projected_len);
+            if projected_memory > max_memory {
+                break;
+            }
+
+            let (next_frontier, next_hashes) =
+                next_depth(&frontier, &mut layers, &move_tables, &pack_table);
+            growth = next_frontier.len() as f64 / frontier.len().max(1) as f64;
+            len += next_hashes.len();
+            hashes.push(next_hashes);
+            frontier = next_frontier;
+        }
+
+        let entry_bits = entry_bits(len);
+        let mut table = Self {
+            entries: vec![EMPTY | EMPTY << 4; 1 << (entry_bits - 1)],
+            entry_bits,
+            counts: hashes.iter().map(Vec::len).collect(),
+            near,
+            moves,
+        };
+        for (distance, hashes) in hashes.iter().enumerate() {
+            for &hash in hashes {
+                table.lower_entry(hash, distance as u8);
+            }
+        }
+        table
+    }
+
+    /// Adds every state one move further from `initial_states` than the
+    /// deepest ones in the table, which must have been built from them with
+    /// `colors`. The array has to grow to fit the new states, and an entry
+    /// doesn't say which states it holds, so this takes as long as building
+    /// the deeper table.
+    pub fn deepen(&mut self, colors: &Colors, initial_states: &[StickerCube]) {
+        assert!(self.depth() < MAX_DEPTH, "pruning table is too deep");
+        let moves = std::mem::take(&mut self.moves);
+        *self = Self::new(colors, initial_states, self.depth() + 1, moves);
+    }
+
+    /// Returns the depth of the table.
+    pub fn depth(&self) -> u8 {
+        self.counts.len() as u8 - 1
+    }
+
+    /// Returns the number of states in the table.
+    pub fn len(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Returns whether the table has no states, which never happens for a
+    /// table built from at least one initial state.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of states at each distance from the initial states,
+    /// from 0 up to the depth of the table.
+    pub fn counts_by_distance(&self) -> Vec<usize> {
+        self.counts.clone()
+    }
+
+    /// Returns the memory used by the table, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns a lower bound on the number of moves needed to reach `cube`
+    /// from the initial states, or `None` if it is more than the depth of the
+    /// table. See the [module docs](self).
+    pub fn get(&self, cube: &impl Cube) -> Option<u8> {
+        self.get_packed(pack(cube))
+    }
+
+    /// Returns a lower bound for a state packed by [`pack()`] like
+    /// [`PruningTable::get()`].
+    pub fn get_packed(&self, packed: [u64; 3]) -> Option<u8> {
+        match self.entry(hash(packed)) {
+            EMPTY => None,
+            distance => Some(distance),
+        }
+    }
+
+    /// Returns a lower bound on the number of moves needed to reach `cube`
+    /// from the initial states, which is one more than the depth of the table
+    /// if it is more than the depth.
+    pub fn lower_bound(&self, cube: &impl Cube) -> i32 {
+        self.lower_bound_packed(pack(cube))
+    }
+
+    /// Returns a lower bound for a state packed by [`pack()`] like
+    /// [`PruningTable::lower_bound()`].
+    pub fn lower_bound_packed(&self, packed: [u64; 3]) -> i32 {
+        match self.get_packed(packed) {
+            Some(n) => n as i32,
+            None => self.depth() as i32 + 1,
+        }
+    }
+
+    /// Returns whether `cube` is at most one move from the initial states.
+    /// Unlike [`PruningTable::lower_bound()`], this is exact.
+    pub fn within_one_move(&self, cube: &impl Cube) -> bool {
+        self.within_one_move_packed(pack(cube))
+    }
+
+    /// Returns whether a state packed by [`pack()`] is at most one move from
+    /// the initial states, like [`PruningTable::within_one_move()`].
+    pub fn within_one_move_packed(&self, packed: [u64; 3]) -> bool {
+        self.near.contains(&packed)
+    }
+
+    /// Returns the entry that a hash picks.
+    fn entry(&self, hash: u64) -> u8 {
+        let i = self.index(hash);
+        self.entries[i / 2] >> (i % 2 * 4) & 0xF
+    }
+
+    /// Lowers the entry that a hash picks to `distance`, if it is greater.
+    fn lower_entry(&mut self, hash: u64, distance: u8) {
+        if distance < self.entry(hash) {
+            let i = self.index(hash);
+            let shift = i % 2 * 4;
+            self.entries[i / 2] = self.entries[i / 2] & !(0xF << shift) | distance << shift;
+        }
+    }
+
+    /// Returns the index of the entry that a hash picks.
+    fn index(&self, hash: u64) -> usize {
+        (hash >> (64 - self.entry_bits)) as usize
+    }
+
+    /// Writes the table to a file.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            // Write to a temporary file first, so that an interrupted write
+            // never leaves a truncated table behind.
+            let tmp_path = path.with_extension("tmp");
+            let mut w = BufWriter::new(std::fs::File::create(&tmp_path)?);
+            w.write_all(MAGIC)?;
+            w.write_all(&VERSION.to_le_bytes())?;
+            w.write_all(&[self.depth()])?;
+            for &count in &self.counts {
+                w.write_all(&(count as u64).to_le_bytes())?;
+            }
+            w.write_all(&self.entry_bits.to_le_bytes())?;
+            w.write_all(&self.entries)?;
+            w.write_all(&(self.near.len() as u64).to_le_bytes())?;
+            for word in self.near.iter().flatten() {
+                w.write_all(&word.to_le_bytes())?;
+            }
+            w.into_inner()?.sync_all()?;
+            std::fs::rename(tmp_path, path)
+        };
+        write().map_err(|e| format!("error writing {}: {e}", path.display()))
+    }
+
+    /// Reads a table written by [`PruningTable::save()`], which must have
+    /// been built from `moves`.
+    pub fn load(path: &Path, moves: Vec<Move>) -> Result<Self, String> {
+        let err = |e: &str| format!("error reading {}: {e}", path.display());
+        let bytes = std::fs::read(path).map_err(|e| err(&e.to_string()))?;
+        let mut rest = bytes.as_slice();
+        let mut read = |n: usize| -> Result<&[u8], String> {
+            if rest.len() < n {
+                return Err(err("file is truncated"));
+            }
+            let (head, tail) = rest.split_at(n);
+            rest = tail;
+            Ok(head)
+        };
+        let u32_from = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap());
+        let u64_from = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().unwrap());
+
+        if read(MAGIC.len())? != MAGIC {
+            return Err(err("not a pruning table"));
+        }
+        let version = u32_from(read(4)?);
+        if version != VERSION {
+            return Err(err(&format!("unsupported version {version}")));
+        }
+        let depth = read(1)?[0];
+        if depth > MAX_DEPTH {
+            return Err(err(&format!("invalid depth {depth}")));
+        }
+        let counts: Vec<usize> = read(8 * (depth as usize + 1))?
+            .chunks_exact(8)
+            .map(|bytes| u64_from(bytes) as usize)
+            .collect();
+        let len = counts
+            .iter()
+            .try_fold(0usize, |len, &count| len.checked_add(count))
+            .ok_or_else(|| err("invalid number of states"))?;
+        let entry_bits = u32_from(read(4)?);
+        if len == 0 || entry_bits != self::entry_bits(len) {
+            return Err(err("number of entries doesn't match number of states"));
+        }
+        let entries = read(1 << (entry_bits - 1))?.to_vec();
+        // Each entry holds the least distance of the states that hash to it,
+        // so there are at most as many entries at a distance as states.
+        let mut entry_counts = vec![0; depth as usize + 1];
+        for distance in entries.iter().flat_map(|&byte| [byte & 0xF, byte >> 4]) {
+            match entry_counts.get_mut(distance as usize) {
+                Some(count) => *count += 1,
+                None if distance == EMPTY => (),
+                None => return Err(err(&format!("entry {distance} is deeper than the table"))),
+            }
+        }
+        if entry_counts.iter().zip(&counts).any(|(e, &c)| *e > c) {
+            return Err(err("more entries than states at some distance"));
+        }
+        let near_len = u64_from(read(8)?) as usize;
+        let near = read(
+            near_len
+                .checked_mul(24)
+                .ok_or_else(|| err("file is truncated"))?,
+        )?
+        .chunks_exact(24)
+        .map(|state| [0, 1, 2].map(|i| u64_from(&state[i * 8..i * 8 + 8])))
+        .collect();
+        if !rest.is_empty() {
+            return Err(err("unexpected data after the table"));
+        }
+        Ok(Self {
+            entries,
+            entry_bits,
+            counts,
+            near,
+            moves,
+        })
+    }
+}
+
+/// Returns the states one move from `frontier` that aren't in `layers` or
+/// already found, with their hashes. `layers` holds the packed states at the
+/// two depths before the one being built, and is moved along to end at it.
+fn next_depth(
+    frontier: &[StickerCube],
+    layers: &mut Layers,
+    move_tables: &[MoveTable],
+    pack_table: &PackTable,
+) -> (Vec<StickerCube>, Vec<u64>) {
+    let mut next_frontier = vec![];
+    let mut next_hashes = vec![];
+    let mut next_layer = HashSet::new();
+    for cube in frontier {
+        for mv in move_tables {
+            let new_cube = cube.apply(mv);
+            let packed = new_cube.pack_with(pack_table);
+            // States one move from the frontier are at most one depth
+            // closer or further, so these are the only depths to check.
+            if !layers.iter().any(|l| l.contains(&packed)) && next_layer.insert(packed) {
+                next_frontier.push(new_cube);
+                next_hashes.push(hash(packed));
+            }
+        }
+    }
+    let [_, last] = std::mem::take(layers);
+    *layers = [last, next_layer];
+    (next_frontier, next_hashes)
+}
+
+/// Returns the number of entries in a table of `len` states, as a power of
+/// 2.
+fn entry_bits(len: usize) -> u32 {
+    // At least 2 entries, so that the array has at least a byte.
+    (len.max(1) * ENTRIES_PER_STATE)
+        .next_power_of_two()
+        .trailing_zeros()
+        .max(1)
+}
+
+/// Returns roughly how much memory building a table of every state within
+/// `depth` of `initial_states` states takes with `moves` moves, in bytes.
+/// This assumes no two moves lead to the same state, so it overestimates
+/// tables that ignore some stickers.
+pub fn projected_memory(initial_states: usize, depth: u8, moves: usize) -> usize {
+    let (mut len, mut frontier) = (initial_states, initial_states);
+    let mut peak = build_memory(len, frontier);
+    for i in 0..depth {
+        // Only the first move can turn any face. After that, turning the
+        // same face again is never new, and about half of the turns of the
+        // opposite face aren't either.
+        let growth = match i {
+            0 => moves as f64,
+            _ => moves as f64 * 0.75,
+        };
+        let next_frontier = (frontier as f64 * growth) as usize;
+        len += next_frontier;
+        peak = peak.max(build_memory(len, frontier + next_frontier));
+        frontier = next_frontier;
+    }
+    peak
+}
+
+/// Returns the memory needed while building a table, in bytes, once it has
+/// `len` states, with `frontier_len` states waiting to be expanded or just
+/// reached.
+fn build_memory(len: usize, frontier_len: usize) -> usize {
+    let cube_size = std::mem::size_of::<StickerCube>();
+    // The packed states of each depth are kept too, in a hash set that is
+    // at most 7/8 full.
+    let packed_size = std::mem::size_of::<[u64; 3]>() * 8 / 7 + 1;
+    let hashes = len * std::mem::size_of::<u64>();
+    let entries = 1 << (entry_bits(len) - 1);
+    frontier_len * (cube_size + packed_size) + hashes + entries
+}
+
+/// Returns the [`Layers`] to start building a table from `initial_states`.
+fn initial_layers(initial_states: &[StickerCube], pack_table: &PackTable) -> Layers {
+    let initial = initial_states
+        .iter()
+        .map(|cube| cube.pack_with(pack_table))
+        .collect();
+    [HashSet::new(), initial]
+}
+
+/// Returns the stickers of a 3x3x3 packed into 3 bits each, which identifies
+/// the state exactly.
+pub fn pack(cube: &impl Cube) -> [u64; 3] {
+    pack_faces(cube.state())
+}
+
+/// Returns the colors of the stickers of a 3x3x3 packed like [`pack()`].
+pub fn pack_faces(faces: impl IntoIterator<Item = Face>) -> [u64; 3] {
+    let mut words = [0u64; 3];
+    for (i, face) in faces.into_iter().enumerate() {
+        words[i / STICKERS_PER_WORD] |= pack_sticker(i, face)[i / STICKERS_PER_WORD];
+    }
+    words
+}
+
+/// Returns the bits that sticker `i` sets in a packed state when it has the
+/// color `face`.
+pub fn pack_sticker(i: usize, face: Face) -> [u64; 3] {
+    let mut words = [0u64; 3];
+    words[i / STICKERS_PER_WORD] = (face as u64) << (i % STICKERS_PER_WORD * 3);
+    words
+}
+
+/// Returns a 64-bit hash of a packed state, whose high bits pick its entry.
+fn hash(packed: [u64; 3]) -> u64 {
+    // Mix the packed stickers together.
+    packed
+        .into_iter()
+        .fold(0x243F_6A88_85A3_08D3, |h, w| mix(h ^ mix(w)))
+}
+
+/// Scrambles the bits of a word, using the finalizer of MurmurHash3.
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    x ^ (x >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{face_moves, solved_states, sticker_cube, ALL_SOLVER_FACES};
+
+    fn table() -> PruningTable {
+        let colors = sticker_cube::colors(0);
+        PruningTable::new(
+            &colors,
+            &solved_states(false),
+            3,
+            face_moves(ALL_SOLVER_FACES),
+        )
+    }
+
+    /// Returns a file in the temporary directory for a test to write a table
+    /// to.
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rocket-test-{}-{name}.bin", std::process::id()))
+    }
+
+    /// Saves `table` and returns the bytes of the file.
+    fn saved_bytes(table: &PruningTable, name: &str) -> Vec<u8> {
+        let path = temp_path(name);
+        table.save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        bytes
+    }
+
+    /// Loads a table from `bytes` written to a file.
+    fn load_bytes(bytes: &[u8], name: &str) -> Result<PruningTable, String> {
+        let path = temp_path(name);
+        std::fs::write(&path, bytes).unwrap();
+        let table = PruningTable::load(&path, face_moves(ALL_SOLVER_FACES));
+        std::fs::remove_file(path).unwrap();
+        table
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let table = table();
+        let loaded = load_bytes(&saved_bytes(&table, "round-trip"), "round-trip").unwrap();
+        assert_eq!(loaded.depth(), table.depth());
+        assert_eq!(loaded.counts_by_distance(), table.counts_by_distance());
+        assert_eq!(loaded.entries, table.entries);
+        assert_eq!(loaded.near, table.near);
+        assert_eq!(loaded.moves, table.moves);
+    }
+
+    #[test]
+    fn load_rejects_corrupted_tables() {
+        let table = table();
+        let bytes = saved_bytes(&table, "corrupted");
+        let depth_at = MAGIC.len() + 4;
+        let entries_at = depth_at + 1 + 8 * (table.depth() as usize + 1) + 4;
+        let corrupted = |f: &dyn Fn(&mut Vec<u8>)| {
+            let mut bytes = bytes.clone();
+            f(&mut bytes);
+            load_bytes(&bytes, "corrupted")
+        };
+
+        assert!(corrupted(&|b| b[0] = b'X').is_err());
+        // Too deep for an entry to tell from empty.
+        assert!(corrupted(&|b| b[depth_at] = MAX_DEPTH + 1).is_err());
+        // A depth that doesn't match the number of states at each distance.
+        assert!(corrupted(&|b| b[depth_at] = table.depth() - 1).is_err());
+        // A distance deeper than the table.
+        assert!(corrupted(&|b| b[entries_at] = (table.depth() + 1) | (EMPTY << 4)).is_err());
+        // More entries than states at distance 0.
+        assert!(corrupted(&|b| b[entries_at..entries_at + 64].fill(0)).is_err());
+        // A state count that doesn't match the number of entries.
+        assert!(corrupted(&|b| b[depth_at + 1..depth_at + 9].fill(0xFF)).is_err());
+        assert!(corrupted(&|b| b.truncate(b.len() - 1)).is_err());
+        assert!(corrupted(&|b| b.push(0)).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+//! Statistics about the pruning table.
+
+use cubesim::Cube;
+
+use crate::sticker_cube::StickerCube;
+use crate::{display_move, Alg, RktOptimizer};
+
+/// Print the size of the pruning table for the current settings and how many
+/// states it has at each distance from solved, and the same for the pattern
+/// databases if they are used.
+#[derive(clap::Args, Debug, Clone)]
+pub struct TableInfoArgs {
+    /// Also look up how far the state reached by this algorithm is from
+    /// solved.
+    #[clap(long, allow_hyphen_values = true)]
+    lookup: Option<String>,
+}
+
+pub fn run(args: &TableInfoArgs, optimizer: &RktOptimizer) -> Result<(), String> {
+    let table = optimizer.table();
+    let moves: Vec<String> = table.moves.iter().copied().map(display_move).collect();
+    println!("Depth: {}", table.depth());
+    println!("Moves: {}", moves.join(" "));
+    println!("States: {}", table.len());
+    for (distance, count) in table.counts_by_distance().into_iter().enumerate() {
+        println!("  {distance} moves from solved: {count}");
+    }
+    println!(
+        "Memory: about {:.1} MiB",
+        table.memory_usage() as f64 / (1024.0 * 1024.0),
+    );
+
+    if let Some(databases) = optimizer.pattern_databases() {
+        for (name, counts) in databases.counts_by_distance() {
+            println!(
+                "Pattern database {name}: {} arrangements",
+                counts.iter().sum::<usize>()
+            );
+            for (distance, count) in counts.into_iter().enumerate() {
+                println!("  {distance} moves from solved: {count}");
+            }
+        }
+        println!(
+            "Memory: about {:.1} MiB",
+            databases.memory_usage() as f64 / (1024.0 * 1024.0),
+        );
+    }
+
+    if let Some(alg) = &args.lookup {
+        let moves = Alg::parse_with(alg, optimizer.cost_model())?.moves;
+        let state = optimizer.solved_cube().apply_moves(&moves);
+        match table.get(&state) {
+            Some(distance) => println!("{alg}: at least {distance} moves from solved"),
+            None => println!("{alg}: more than {} moves from solved", table.depth()),
+        }
+        if let Some(databases) = optimizer.pattern_databases() {
+            let state = StickerCube::of_moves(&moves);
+            println!(
+                "{alg}: at least {} moves from solved by the pattern databases",
+                databases.lower_bound(&state),
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Running independent searches on several threads.
+//!
+//! A single search splits into one branch per reorient at the first gap, and
+//! the searches done by `--neutral`, `variants`, and `stats` are independent
+//! of each other. All threads share one pruning table, so its size does not
+//! depend on the thread count.
+//!
+//! Results from several threads are always combined in the order of their
+//! inputs, so the solutions found and their order don't depend on the thread
+//! count either. [`crate::RktOptimizer::with_deterministic()`] makes the rest
+//! of the output reproducible too.
+//!
+//! The thread count, `--nice` mode, and deterministic output are settings of
+//! each [`crate::RktOptimizer`], so that searches with different settings can
+//! run in one process.
+
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Mutex;
+
+thread_local! {
+    /// Whether this thread was spawned by [`map_on()`].
+    static IN_MAP: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Number of search nodes between yield points in `--nice` mode.
+const YIELD_INTERVAL: usize = 1024;
+
+/// Returns the default number of threads, which is the number of physical
+/// cores.
+pub fn default_count() -> usize {
+    num_cpus::get_physical()
+}
+
+/// Returns `count` as a number of threads to use, which is at least one,
+/// warning if it is more than the number of physical cores.
+pub fn check_count(count: usize) -> usize {
+    let physical = num_cpus::get_physical();
+    if count > physical {
+        eprintln!(
+            "Warning: {count} threads is more than the {physical} physical cores. \
+             The search is limited by memory access, so hyperthreads usually \
+             slow it down."
+        );
+    }
+    count.max(1)
+}
+
+/// Applies `f` to each item on up to `thread_count` threads and returns the
+/// results in the same order as the items.
+pub fn map_on<T: Sync, R: Send>(
+    thread_count: usize,
+    items: &[T],
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    std::thread::scope(|s| {
+        for _ in 0..thread_count.max(1).min(items.len()) {
+            s.spawn(|| {
+                IN_MAP.set(true);
+                loop {
+                    let i = next.fetch_add(1, SeqCst);
+                    let Some(item) = items.get(i) else { break };
+                    let result = f(item);
+                    results.lock().unwrap().push((i, result));
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Returns whether this thread is already one of the threads of [`map_on()`],
+/// so that spawning more would oversubscribe the cores.
+pub fn in_map() -> bool {
+    IN_MAP.get()
+}
+
+/// Lowers the priority of this process. Threads spawned afterwards inherit
+/// the priority. Searches of optimizers made with
+/// [`crate::RktOptimizer::with_nice()`] also yield to other threads
+/// regularly.
+pub fn be_nice() {
+    #[cfg(unix)]
+    // SAFETY: `nice()` has no memory safety requirements.
+    if unsafe { libc::nice(10) } == -1 {
+        eprintln!(
+            "Warning: could not lower priority: {}",
+            std::io::Error::last_os_error(),
+        );
+    }
+    #[cfg(not(unix))]
+    eprintln!("Warning: lowering priority is not supported on this platform");
+}
+
+/// Called by the search after visiting each node. If `nice`, yields to other
+/// threads every so often.
+pub fn yield_point(nodes: usize, nice: bool) {
+    if nice && nodes.is_multiple_of(YIELD_INTERVAL) {
+        std::thread::yield_now();
+    }
+}
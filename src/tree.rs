@@ -0,0 +1,181 @@
+//! Recording of the search tree explored by `dfs`, for debugging heuristics
+//! and for teaching how the solver works.
+
+use std::io::{self, Write};
+
+/// What the search did at a node.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The node was expanded into its children.
+    Expanded,
+    /// No reorients are left and the remaining moves solve the cube.
+    Solved,
+    /// No reorients are left and the remaining moves do not solve the cube.
+    Unsolved,
+    /// The lower bound exceeds the number of remaining moves.
+    Pruned,
+    /// The orientation does not match a chunk boundary.
+    WrongOrientation,
+    /// The executor can't perform the next move in this orientation.
+    Forbidden,
+}
+impl Outcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Expanded => "expanded",
+            Outcome::Solved => "solved",
+            Outcome::Unsolved => "unsolved",
+            Outcome::Pruned => "pruned",
+            Outcome::WrongOrientation => "wrong_orientation",
+            Outcome::Forbidden => "forbidden",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Outcome::Expanded => "black",
+            Outcome::Solved => "darkgreen",
+            Outcome::Unsolved => "gray",
+            Outcome::Pruned => "red",
+            Outcome::WrongOrientation => "orange",
+            Outcome::Forbidden => "purple",
+        }
+    }
+}
+
+/// Node in a recorded search tree.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub parent: Option<usize>,
+    /// Move and reorient that lead to this node.
+    pub label: String,
+    /// Number of moves applied before this node.
+    pub gap: usize,
+    pub reorients_left: usize,
+    /// Lower bound from the pruning table.
+    pub bound: i32,
+    /// Largest lower bound that does not prune the node.
+    pub budget: usize,
+    pub outcome: Outcome,
+    /// Number of solutions found below this node.
+    pub solutions: usize,
+}
+
+/// Search tree recorded during one or more iterations of iterative deepening,
+/// capped at a maximum number of nodes.
+#[derive(Debug, Clone)]
+pub struct SearchTree {
+    max_nodes: usize,
+    nodes: Vec<Node>,
+    skipped: usize,
+}
+impl SearchTree {
+    pub fn new(max_nodes: usize) -> Self {
+        Self {
+            max_nodes,
+            nodes: vec![],
+            skipped: 0,
+        }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Returns the number of nodes that were not recorded because the cap
+    /// was reached.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Adds a node whose outcome is not yet known and returns its index, or
+    /// `None` if the node cap has been reached.
+    pub fn add(
+        &mut self,
+        parent: Option<usize>,
+        label: String,
+        gap: usize,
+        reorients_left: usize,
+    ) -> Option<usize> {
+        if self.nodes.len() >= self.max_nodes {
+            self.skipped += 1;
+            return None;
+        }
+        self.nodes.push(Node {
+            parent,
+            label,
+            gap,
+            reorients_left,
+            bound: 0,
+            budget: 0,
+            outcome: Outcome::Expanded,
+            solutions: 0,
+        });
+        Some(self.nodes.len() - 1)
+    }
+
+    /// Writes the tree in Graphviz DOT format.
+    pub fn write_dot(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "digraph search {{")?;
+        writeln!(w, "  node [shape=box, fontname=\"monospace\"];")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            let reason = match node.outcome {
+                Outcome::Expanded => format!("{} solutions below", node.solutions),
+                Outcome::Solved => "solved".to_string(),
+                Outcome::Unsolved => {
+                    format!("unsolved: end state is {}+ moves away", node.bound)
+                }
+                Outcome::Pruned => format!(
+                    "pruned: lower bound {} > {} remaining",
+                    node.bound, node.budget,
+                ),
+                Outcome::WrongOrientation => "orientation does not match chunk".to_string(),
+                Outcome::Forbidden => "move is forbidden in this orientation".to_string(),
+            };
+            writeln!(
+                w,
+                "  n{i} [label=\"{}\\nlb {}/{}, {} left\", color={}, tooltip=\"{reason}\", \
+                 order={i}, gap={}, bound={}, budget={}, reorients_left={}, outcome={}];",
+                node.label.trim(),
+                node.bound,
+                node.budget,
+                node.reorients_left,
+                node.outcome.color(),
+                node.gap,
+                node.bound,
+                node.budget,
+                node.reorients_left,
+                node.outcome.name(),
+            )?;
+            if let Some(parent) = node.parent {
+                writeln!(w, "  n{parent} -> n{i};")?;
+            }
+        }
+        if self.skipped > 0 {
+            writeln!(
+                w,
+                "  skipped [shape=plaintext, label=\"... {} more nodes not recorded\"];",
+                self.skipped,
+            )?;
+        }
+        writeln!(w, "}}")
+    }
+}
+
+/// Records what the search did at a node, if the node is being recorded.
+pub fn record(
+    tree: &mut Option<SearchTree>,
+    node: Option<usize>,
+    outcome: Outcome,
+    bound: i32,
+    budget: usize,
+    solutions: usize,
+) {
+    if let (Some(tree), Some(node)) = (tree, node) {
+        let node = &mut tree.nodes[node];
+        node.outcome = outcome;
+        node.bound = bound;
+        node.budget = budget;
+        node.solutions = solutions;
+    }
+}
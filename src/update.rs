@@ -0,0 +1,164 @@
+//! Replacing the running binary with the latest GitHub release.
+//!
+//! Each release has a binary named `rocket-<target triple>`, with `.exe` on
+//! Windows, such as `rocket-x86_64-unknown-linux-gnu`. The download is only
+//! installed if it matches the SHA-256 digest that GitHub publishes for it.
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::remote::get;
+
+/// GitHub API URL of the latest release.
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/HactarCE/rocket/releases/latest";
+
+/// Download the latest release from GitHub and replace this binary with it.
+#[derive(clap::Args, Debug, Clone)]
+pub struct SelfUpdateArgs {
+    /// Only check whether a newer release exists.
+    #[clap(long)]
+    check: bool,
+}
+
+pub fn run(args: &SelfUpdateArgs) -> Result<(), String> {
+    let current = env!("CARGO_PKG_VERSION");
+    println!("Checking for updates ...");
+    let release: serde_json::Value = get(LATEST_RELEASE_URL)?
+        .into_json()
+        .map_err(|e| format!("invalid response from GitHub: {e}"))?;
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or("latest release has no tag")?;
+    let latest = tag.trim_start_matches('v');
+
+    if parse_version(latest) <= parse_version(current) {
+        println!("Already up to date (version {current}).");
+        return Ok(());
+    }
+    println!("Version {latest} is available (currently {current}).");
+    if args.check {
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let assets = release["assets"].as_array().map_or(&[][..], |a| a);
+    let asset = assets
+        .iter()
+        .find(|asset| asset["name"].as_str() == Some(&name))
+        .ok_or_else(|| {
+            match assets
+                .iter()
+                .filter_map(|asset| asset["name"].as_str())
+                .find(|other| is_archive(other) && other.contains(env!("TARGET")))
+            {
+                Some(archive) => format!(
+                    "release {tag} only has {archive} for {}, and archives can't be installed; \
+                     download it from GitHub instead",
+                    env!("TARGET"),
+                ),
+                None => format!("release {tag} has no binary named {name}"),
+            }
+        })?;
+    let url = asset["browser_download_url"]
+        .as_str()
+        .ok_or("release asset has no download URL")?;
+    let expected = expected_sha256(asset, assets)?;
+
+    println!("Downloading {url} ...");
+    let mut contents = vec![];
+    get(url)?
+        .into_reader()
+        .read_to_end(&mut contents)
+        .map_err(|e| format!("error downloading {url}: {e}"))?;
+    let actual = sha256(&contents);
+    if actual != expected {
+        return Err(format!(
+            "{name} has SHA-256 {actual}, but the release says {expected}; not installing it"
+        ));
+    }
+
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    replace_binary(&exe, &contents)
+        .map_err(|e| format!("error replacing {}: {e}", exe.display()))?;
+    println!("Updated to version {latest}.");
+    Ok(())
+}
+
+/// Parses a version like `1.2.3` so that versions compare numerically.
+fn parse_version(s: &str) -> Vec<u64> {
+    s.split('.').map(|part| part.parse().unwrap_or(0)).collect()
+}
+
+/// Returns the name of the release asset built for this target.
+fn asset_name() -> String {
+    format!("rocket-{}{}", env!("TARGET"), std::env::consts::EXE_SUFFIX)
+}
+
+/// Returns whether a release asset is an archive rather than a binary.
+fn is_archive(name: &str) -> bool {
+    [".tar.gz", ".tgz", ".tar.xz", ".zip"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+/// Returns the hex SHA-256 digest that the release publishes for `asset`,
+/// either as the asset's `digest` or in a `<name>.sha256` asset next to it.
+fn expected_sha256(
+    asset: &serde_json::Value,
+    assets: &[serde_json::Value],
+) -> Result<String, String> {
+    let name = asset["name"].as_str().unwrap_or_default();
+    if let Some(digest) = asset["digest"].as_str() {
+        return digest
+            .strip_prefix("sha256:")
+            .map(str::to_lowercase)
+            .ok_or_else(|| format!("{name} has a digest that isn't SHA-256: {digest}"));
+    }
+    let checksum_name = format!("{name}.sha256");
+    let url = assets
+        .iter()
+        .find(|asset| asset["name"].as_str() == Some(&checksum_name))
+        .and_then(|asset| asset["browser_download_url"].as_str())
+        .ok_or_else(|| format!("release has no SHA-256 digest for {name}; not installing it"))?;
+    // Checksum files look like `sha256sum` output: the digest, then the name.
+    crate::remote::fetch(url)?
+        .split_whitespace()
+        .next()
+        .filter(|digest| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_lowercase)
+        .ok_or_else(|| format!("{checksum_name} doesn't start with a SHA-256 digest"))
+}
+
+/// Returns the hex SHA-256 digest of `contents`.
+fn sha256(contents: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, contents)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Writes the new binary next to the old one and then moves it into place,
+/// so that a failed download never leaves a broken binary behind.
+fn replace_binary(exe: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let new = exe.with_extension("new");
+    std::fs::File::create(&new)?.write_all(contents)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&new, std::fs::Permissions::from_mode(0o755))?;
+    }
+    // Windows won't overwrite a running executable, but it will rename one.
+    // The binary that the last update moved aside isn't running anymore, so
+    // it can be deleted to make room.
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("old");
+        match std::fs::remove_file(&old) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        std::fs::rename(exe, old)?;
+    }
+    std::fs::rename(&new, exe)
+}
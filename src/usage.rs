@@ -0,0 +1,208 @@
+//! How often each reorient appears in the solutions chosen for a batch of
+//! algorithms, and how much ETM it adds in total, and which reorients would
+//! save the most ETM if they were made cheap.
+
+use std::collections::HashSet;
+
+use crate::{Alg, CostModel, Reorient, RktOptimizer, Solution};
+
+/// Usage of one reorient across the chosen solutions.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct ReorientUsage {
+    /// Number of times the reorient appears.
+    pub uses: usize,
+    /// Number of solutions that use it at least once.
+    pub solutions: usize,
+    /// Total ETM that it adds.
+    pub etm: usize,
+}
+
+/// Reorient usage statistics, recorded one chosen solution at a time.
+#[derive(Debug, Default, Clone)]
+pub struct Usage {
+    /// Usage of each reorient, indexed by `Reorient as usize`.
+    reorients: [ReorientUsage; 24],
+    /// Number of solutions recorded.
+    solutions: usize,
+    /// For each algorithm, the reorients of every solution that was found,
+    /// sorted, along with the ETM of the rest of the algorithm.
+    found: Vec<Vec<(Solution, usize)>>,
+}
+
+/// A reorient that would make a batch cheaper if it cost 1 ETM.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CheapSuggestion {
+    pub reorient: Reorient,
+    /// ETM saved across the batch by making this reorient cheap, in addition
+    /// to the earlier suggestions.
+    pub saved: usize,
+    /// Total ETM of the batch once this and the earlier suggestions are
+    /// cheap.
+    pub total: usize,
+}
+impl Usage {
+    /// Records the reorients of a chosen solution, with their costs under
+    /// `cost_model`.
+    pub fn record(&mut self, cost_model: &CostModel, solution: &Solution) {
+        self.solutions += 1;
+        let mut seen = [false; 24];
+        for &r in solution.iter().filter(|r| !r.is_none()) {
+            let usage = &mut self.reorients[r as usize];
+            usage.uses += 1;
+            usage.etm += cost_model.cost(r);
+            if !seen[r as usize] {
+                seen[r as usize] = true;
+                usage.solutions += 1;
+            }
+        }
+    }
+
+    /// Records every solution found for `alg`, any of which could be chosen
+    /// under different costs.
+    pub fn record_found(&mut self, alg: &Alg, solutions: &[Solution]) {
+        // Only which reorients a solution uses affects its cost.
+        let found: HashSet<(Solution, usize)> = solutions
+            .iter()
+            .map(|solution| {
+                let mut reorients: Solution =
+                    solution.iter().copied().filter(|r| !r.is_none()).collect();
+                reorients.sort_by_key(|&r| r as usize);
+                let rest = alg.original_len() + alg.extra_moves(solution);
+                (reorients, rest)
+            })
+            .collect();
+        if !found.is_empty() {
+            self.found.push(found.into_iter().collect());
+        }
+    }
+
+    /// Returns the total ETM of the batch if each algorithm used its cheapest
+    /// solution under `cost_model`.
+    fn total(&self, cost_model: &CostModel) -> usize {
+        self.found
+            .iter()
+            .map(|solutions| {
+                solutions
+                    .iter()
+                    .map(|(reorients, rest)| {
+                        rest + reorients.iter().map(|&r| cost_model.cost(r)).sum::<usize>()
+                    })
+                    .min()
+                    .unwrap_or(0)
+            })
+            .sum()
+    }
+
+    /// Returns the total ETM of the batch under `cost_model`, from the
+    /// solutions recorded with [`Usage::record_found()`].
+    pub fn total_etm(&self, cost_model: &CostModel) -> usize {
+        self.total(cost_model)
+    }
+
+    /// Returns up to `n` reorients that would save the most ETM across the
+    /// batch if they cost 1 ETM instead of their cost under `cost_model`,
+    /// picked one at a time so that each saves the most given the ones
+    /// before it. Reorients that wouldn't save anything are left out.
+    ///
+    /// Only the solutions recorded with [`Usage::record_found()`] are
+    /// considered. Those are every solution with the fewest reorients, which
+    /// don't depend on the costs.
+    pub fn suggest_cheap(&self, cost_model: &CostModel, n: usize) -> Vec<CheapSuggestion> {
+        let mut cost_model = cost_model.clone();
+        let mut total = self.total(&cost_model);
+        let mut ret: Vec<CheapSuggestion> = vec![];
+        while ret.len() < n {
+            let best = Reorient::ALL[1..]
+                .iter()
+                .filter(|&&r| cost_model.cost(r) > 1)
+                .map(|&r| {
+                    let mut cheaper = cost_model.clone();
+                    cheaper.make_cheap(&[r]);
+                    (r, self.total(&cheaper))
+                })
+                // Ties go to the first reorient.
+                .min_by_key(|&(_, total)| total);
+            match best {
+                Some((reorient, new_total)) if new_total < total => {
+                    cost_model.make_cheap(&[reorient]);
+                    ret.push(CheapSuggestion {
+                        reorient,
+                        saved: total - new_total,
+                        total: new_total,
+                    });
+                    total = new_total;
+                }
+                _ => break,
+            }
+        }
+        ret
+    }
+
+    /// Prints the reorients from [`Usage::suggest_cheap()`] for `optimizer`'s
+    /// cost model, in its notation.
+    pub fn print_cheap_suggestions(&self, optimizer: &RktOptimizer, n: usize) {
+        let suggestions = self.suggest_cheap(optimizer.cost_model(), n);
+        if suggestions.is_empty() {
+            println!("Making any one reorient cheap wouldn't save any ETM.");
+            return;
+        }
+        println!(
+            "Reorients to make cheap, from {} ETM across {} algorithms:",
+            self.total_etm(optimizer.cost_model()),
+            self.found.len(),
+        );
+        for suggestion in &suggestions {
+            println!(
+                "{:>9}  saves {} ETM ({} ETM total)",
+                optimizer.reorient_name(suggestion.reorient),
+                suggestion.saved,
+                suggestion.total,
+            );
+        }
+    }
+
+    /// Returns the number of solutions recorded.
+    pub fn solutions(&self) -> usize {
+        self.solutions
+    }
+
+    /// Returns the usage of every reorient that was used at least once, from
+    /// most to least used.
+    pub fn rows(&self) -> Vec<(Reorient, ReorientUsage)> {
+        let mut rows: Vec<(Reorient, ReorientUsage)> = Reorient::ALL
+            .iter()
+            .map(|&r| (r, self.reorients[r as usize]))
+            .filter(|(_, usage)| usage.uses > 0)
+            .collect();
+        // The sort is stable, so ties stay in the order of `Reorient::ALL`.
+        rows.sort_by_key(|(_, usage)| std::cmp::Reverse((usage.uses, usage.etm)));
+        rows
+    }
+
+    /// Prints a table of the usage of each reorient, in `optimizer`'s
+    /// notation.
+    pub fn print(&self, optimizer: &RktOptimizer) {
+        let rows = self.rows();
+        println!("Reorient usage across {} chosen solutions:", self.solutions);
+        if rows.is_empty() {
+            println!("No reorients were used.");
+            return;
+        }
+        println!(
+            "{:>9}  {:>5}  {:>5}  {:>5}",
+            "Reorient", "Uses", "Algs", "ETM"
+        );
+        for (r, usage) in &rows {
+            println!(
+                "{:>9}  {:>5}  {:>5}  {:>5}",
+                optimizer.reorient_name(*r),
+                usage.uses,
+                usage.solutions,
+                usage.etm,
+            );
+        }
+        let uses: usize = rows.iter().map(|(_, usage)| usage.uses).sum();
+        let etm: usize = rows.iter().map(|(_, usage)| usage.etm).sum();
+        println!("Total: {uses} reorients adding {etm} ETM");
+    }
+}
@@ -0,0 +1,106 @@
+//! Comparison of an algorithm with its conjugates by every whole-cube
+//! rotation.
+
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+use crate::{display_move, is_face_move, threads, Alg, Reorient, RktOptimizer, Solution};
+
+/// Conjugate an algorithm by every whole-cube rotation, solve each variant,
+/// and tabulate the results.
+#[derive(clap::Args, Debug, Clone)]
+pub struct VariantsArgs {
+    /// Rotationless algorithm.
+    #[clap(required = true)]
+    alg: Vec<String>,
+}
+
+pub fn run(args: &VariantsArgs, optimizer: &RktOptimizer, max_depth: usize) -> Result<(), String> {
+    let alg = Alg::parse_with(&args.alg.join(" "), optimizer.cost_model())?;
+    let cost_model = optimizer.cost_model();
+    // Conjugating wide turns and rotations would need moves that the search
+    // doesn't support.
+    if let Some(&mv) = alg.moves.iter().find(|&&mv| !is_face_move(mv)) {
+        return Err(format!(
+            "unsupported move {}: variants needs a rotationless algorithm of face turns",
+            display_move(mv),
+        ));
+    }
+
+    let done = AtomicUsize::new(0);
+    let mut results: Vec<(Reorient, Alg, usize, Vec<Solution>)> =
+        threads::map_on(optimizer.thread_count(), Reorient::ALL, |&rotation| {
+            let variant = Alg {
+                moves: alg
+                    .moves
+                    .iter()
+                    .map(|&mv| rotation.physical_move(mv))
+                    .collect(),
+                ..alg.clone()
+            };
+            let (reorient_count, solutions) =
+                optimizer.search(&variant, max_depth, false, None, &mut None);
+            let done = done.fetch_add(1, SeqCst) + 1;
+            eprint!("\rSolved variant {}/{} ...", done, Reorient::ALL.len());
+            (rotation, variant, reorient_count, solutions)
+        });
+    eprintln!();
+
+    // Sort by ETM, then by number of reorients, putting variants without any
+    // solutions last.
+    results.sort_by_key(|(_, variant, reorient_count, solutions)| {
+        let cost = solutions.iter().map(|s| variant.cost(cost_model, s)).min();
+        (cost.is_none(), cost, *reorient_count)
+    });
+
+    let alg_width = results
+        .iter()
+        .map(|(_, variant, _, _)| display_moves(variant).len())
+        .max()
+        .unwrap_or(0);
+    println!("Rotation  {:<alg_width$}  Reorients  ETM", "Algorithm");
+    for (rotation, variant, reorient_count, solutions) in &results {
+        let rotation = match rotation.pre_rotation_name() {
+            "" => "-",
+            name => name,
+        };
+        let moves = display_moves(variant);
+        match solutions.iter().map(|s| variant.cost(cost_model, s)).min() {
+            Some(cost) => {
+                println!("{rotation:<8}  {moves:<alg_width$}  {reorient_count:>9}  {cost:>3}")
+            }
+            None => println!("{rotation:<8}  {moves:<alg_width$}  {:>9}  {:>3}", "-", "-"),
+        }
+    }
+
+    if let Some((_, variant, _, solutions)) = results.first() {
+        if let Some(best) = solutions.iter().min_by_key(|s| variant.cost(cost_model, s)) {
+            println!();
+            println!("Best variant: {}", optimizer.display(variant, best));
+        }
+    }
+
+    Ok(())
+}
+
+fn display_moves(alg: &Alg) -> String {
+    let moves: Vec<String> = alg.moves.iter().copied().map(display_move).collect();
+    moves.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CostModel;
+
+    #[test]
+    fn rejects_rotations_and_wide_moves() {
+        let optimizer = RktOptimizer::new(3, 0, CostModel::default());
+        for (alg, mv) in [("R x U", "x"), ("Rw U R'", "Rw")] {
+            let args = VariantsArgs {
+                alg: vec![alg.to_string()],
+            };
+            let error = run(&args, &optimizer, 3).unwrap_err();
+            assert!(error.contains(&format!("unsupported move {mv}")), "{error}");
+        }
+    }
+}
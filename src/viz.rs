@@ -0,0 +1,274 @@
+//! Replay of a search tree recorded with `--dump-tree`, showing how the
+//! frontier grows and where it gets pruned.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::tree::Outcome;
+
+const OUTCOMES: [Outcome; 6] = [
+    Outcome::Expanded,
+    Outcome::Pruned,
+    Outcome::WrongOrientation,
+    Outcome::Unsolved,
+    Outcome::Solved,
+    Outcome::Forbidden,
+];
+const BAR_WIDTH: usize = 60;
+
+/// Replay a search tree recorded with `--dump-tree` as an animation.
+#[derive(clap::Args, Debug, Clone)]
+pub struct VizArgs {
+    /// Graphviz file written by `--dump-tree`.
+    input: PathBuf,
+
+    /// Write a self-contained animated HTML page instead of animating in the
+    /// terminal.
+    #[clap(long)]
+    html: Option<PathBuf>,
+
+    /// Number of nodes replayed per frame.
+    #[clap(long, default_value_t = 10)]
+    step: usize,
+
+    /// Delay between frames, in milliseconds.
+    #[clap(long, default_value_t = 50)]
+    delay: u64,
+}
+
+/// Node of a recorded search tree, in the order it was visited.
+#[derive(Debug, Copy, Clone)]
+struct VizNode {
+    gap: usize,
+    reorients_left: usize,
+    outcome: Outcome,
+}
+
+pub fn run(args: &VizArgs) -> Result<(), String> {
+    let contents = std::fs::read_to_string(&args.input)
+        .map_err(|e| format!("error reading {}: {}", args.input.display(), e))?;
+    let nodes = parse_dot(&contents)?;
+    if nodes.is_empty() {
+        return Err(format!("no search tree nodes in {}", args.input.display()));
+    }
+
+    match &args.html {
+        Some(path) => {
+            std::fs::write(path, html(&nodes))
+                .map_err(|e| format!("error writing {}: {}", path.display(), e))?;
+            println!(
+                "Wrote visualization of {} nodes to {}.",
+                nodes.len(),
+                path.display()
+            );
+        }
+        None => animate(&nodes, args.step.max(1), Duration::from_millis(args.delay)),
+    }
+    Ok(())
+}
+
+/// Parses the nodes written by `SearchTree::write_dot()`.
+fn parse_dot(contents: &str) -> Result<Vec<VizNode>, String> {
+    let mut nodes = vec![];
+    for line in contents.lines() {
+        let line = line.trim();
+        let is_node = line
+            .strip_prefix('n')
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+        if !is_node || line.contains("->") {
+            continue;
+        }
+        let Some(attrs) = line
+            .split_once('[')
+            .and_then(|(_, rest)| rest.rsplit_once(']'))
+        else {
+            continue;
+        };
+        let attrs = parse_attributes(attrs.0);
+        let get = |key: &str| {
+            attrs
+                .get(key)
+                .ok_or_else(|| format!("node is missing attribute {key:?}: {line}"))
+        };
+        let parse_usize = |key: &str| {
+            get(key)?
+                .parse::<usize>()
+                .map_err(|_| format!("invalid attribute {key:?}: {line}"))
+        };
+        let outcome = get("outcome")?;
+        let outcome = OUTCOMES
+            .into_iter()
+            .find(|o| o.name() == outcome)
+            .ok_or_else(|| format!("unknown outcome {outcome:?}"))?;
+        let order = parse_usize("order")?;
+        if order != nodes.len() {
+            return Err(format!("nodes are out of order at {line}"));
+        }
+        nodes.push(VizNode {
+            gap: parse_usize("gap")?,
+            reorients_left: parse_usize("reorients_left")?,
+            outcome,
+        });
+    }
+    Ok(nodes)
+}
+
+/// Parses a comma-separated list of `key=value` pairs, where values may be
+/// quoted.
+fn parse_attributes(s: &str) -> HashMap<&str, String> {
+    let mut ret = HashMap::new();
+    let mut rest = s;
+    while let Some((key, after_key)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim();
+        let after_key = after_key.trim_start();
+        let (value, after_value) = if let Some(quoted) = after_key.strip_prefix('"') {
+            let mut end = 0;
+            let mut escaped = false;
+            for (i, c) in quoted.char_indices() {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => {
+                        end = i;
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+            (quoted[..end].to_string(), &quoted[end + 1..])
+        } else {
+            let end = after_key.find(',').unwrap_or(after_key.len());
+            (after_key[..end].trim().to_string(), &after_key[end..])
+        };
+        ret.insert(key, value);
+        rest = after_value;
+    }
+    ret
+}
+
+/// Counts of each outcome at each gap, for the iteration being replayed.
+#[derive(Debug, Default, Clone)]
+struct Frontier {
+    iteration: usize,
+    reorient_budget: usize,
+    counts: Vec<[usize; OUTCOMES.len()]>,
+}
+impl Frontier {
+    fn add(&mut self, node: VizNode) {
+        if node.gap == 0 {
+            // Each root starts a new iteration of iterative deepening.
+            *self = Frontier {
+                iteration: self.iteration + 1,
+                reorient_budget: node.reorients_left,
+                counts: vec![],
+            };
+        }
+        if self.counts.len() <= node.gap {
+            self.counts.resize(node.gap + 1, [0; OUTCOMES.len()]);
+        }
+        let outcome_index = OUTCOMES.iter().position(|&o| o == node.outcome).unwrap();
+        self.counts[node.gap][outcome_index] += 1;
+    }
+}
+
+fn animate(nodes: &[VizNode], step: usize, delay: Duration) {
+    let mut frontier = Frontier::default();
+    for (i, &node) in nodes.iter().enumerate() {
+        frontier.add(node);
+        if (i + 1) % step != 0 && i + 1 != nodes.len() {
+            continue;
+        }
+
+        let mut frame = String::new();
+        frame += "\x1b[H\x1b[2J";
+        frame += &format!(
+            "Iteration {} (up to {} reorients), node {}/{}\n\n",
+            frontier.iteration,
+            frontier.reorient_budget,
+            i + 1,
+            nodes.len(),
+        );
+        let widest = frontier
+            .counts
+            .iter()
+            .map(|c| c.iter().sum::<usize>())
+            .max()
+            .unwrap_or(1);
+        for (gap, counts) in frontier.counts.iter().enumerate() {
+            frame += &format!("gap {gap:>3} |");
+            for (count, symbol) in counts.iter().zip(['#', 'x', 'o', '.', '*', '!']) {
+                let width = (count * BAR_WIDTH).div_ceil(widest);
+                frame += &symbol.to_string().repeat(width);
+            }
+            frame += &format!(" {}\n", counts.iter().sum::<usize>());
+        }
+        frame += "\n# expanded  x pruned  o wrong orientation  . unsolved  * solved  ! forbidden\n";
+
+        print!("{frame}");
+        std::io::stdout().flush().unwrap();
+        std::thread::sleep(delay);
+    }
+}
+
+fn html(nodes: &[VizNode]) -> String {
+    let data: Vec<String> = nodes
+        .iter()
+        .map(|n| {
+            let outcome_index = OUTCOMES.iter().position(|&o| o == n.outcome).unwrap();
+            format!("[{},{},{}]", n.gap, n.reorients_left, outcome_index)
+        })
+        .collect();
+    HTML_TEMPLATE.replace("/*NODES*/", &data.join(","))
+}
+
+const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>rocket search replay</title>
+<style>
+  body { font-family: monospace; background: #111; color: #ddd; }
+  .row { display: flex; align-items: center; height: 18px; }
+  .label { width: 70px; }
+  .bar { height: 14px; }
+  .o0 { background: #ccc; } .o1 { background: #d33; } .o2 { background: #e93; }
+  .o3 { background: #666; } .o4 { background: #3c3; } .o5 { background: #a4e; }
+</style>
+</head>
+<body>
+<h3 id="title"></h3>
+<div id="rows"></div>
+<p><span class="o0">&nbsp;&nbsp;</span> expanded
+   <span class="o1">&nbsp;&nbsp;</span> pruned
+   <span class="o2">&nbsp;&nbsp;</span> wrong orientation
+   <span class="o3">&nbsp;&nbsp;</span> unsolved
+   <span class="o4">&nbsp;&nbsp;</span> solved
+   <span class="o5">&nbsp;&nbsp;</span> forbidden</p>
+<p><label>Speed <input id="speed" type="range" min="1" max="500" value="20"></label></p>
+<script>
+const nodes = [/*NODES*/];
+let i = 0, iteration = 0, budget = 0, counts = [];
+function step() {
+  const speed = +document.getElementById("speed").value;
+  for (let k = 0; k < speed && i < nodes.length; k++, i++) {
+    const [gap, left, outcome] = nodes[i];
+    if (gap === 0) { iteration++; budget = left; counts = []; }
+    while (counts.length <= gap) counts.push([0, 0, 0, 0, 0, 0]);
+    counts[gap][outcome]++;
+  }
+  document.getElementById("title").textContent =
+    `Iteration ${iteration} (up to ${budget} reorients), node ${i}/${nodes.length}`;
+  const widest = Math.max(1, ...counts.map(c => c.reduce((a, b) => a + b, 0)));
+  document.getElementById("rows").innerHTML = counts.map((c, gap) =>
+    `<div class="row"><span class="label">gap ${gap}</span>` +
+    c.map((n, o) => `<span class="bar o${o}" style="width:${600 * n / widest}px"></span>`).join("") +
+    `&nbsp;${c.reduce((a, b) => a + b, 0)}</div>`).join("");
+  if (i < nodes.length) requestAnimationFrame(step);
+}
+step();
+</script>
+</body>
+</html>
+"#;
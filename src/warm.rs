@@ -0,0 +1,40 @@
+//! Building the pruning table ahead of time, so that searches load it from
+//! the table cache instead of waiting for it.
+
+use crate::RktOptimizer;
+
+/// Build the pruning table for the current settings, and the pattern
+/// databases if they are used, and save them to the table cache.
+#[derive(clap::Args, Debug, Clone)]
+pub struct WarmArgs {
+    /// Build the table by deepening the deepest shallower one in the table
+    /// cache, one move at a time, instead of from scratch.
+    #[clap(long)]
+    pub extend: bool,
+}
+
+pub fn run(optimizer: &RktOptimizer) -> Result<(), String> {
+    let table = optimizer.table();
+    let path = optimizer
+        .table_cache_path()
+        .ok_or("there is no table cache to save to")?;
+    if !path.exists() {
+        return Err(format!(
+            "the pruning table only got to depth {} and wasn't saved",
+            table.depth(),
+        ));
+    }
+    println!(
+        "Saved the pruning table of depth {} with {} states to {}.",
+        table.depth(),
+        table.len(),
+        path.display(),
+    );
+    if let Some(databases) = optimizer.pattern_databases() {
+        println!(
+            "Saved the pattern databases, which take {:.1} MiB.",
+            databases.memory_usage() as f64 / (1024.0 * 1024.0),
+        );
+    }
+    Ok(())
+}